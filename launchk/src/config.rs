@@ -0,0 +1,622 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::launchd::edit::default_backup_dir;
+use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::launchd::privileged::{HelperCommand, PRIVILEGED_HELPER_VAR};
+use crate::launchd::search_dirs::{default_search_dirs, expand_home, SearchDir};
+use crate::tui::keymap::Keymap;
+use crate::tui::preset::{validate_presets, Preset};
+use crate::tui::service_list::columns::{default_columns, validate_columns, ColumnConfig};
+use crate::tui::service_list::filter::FilterMode;
+use crate::tui::theme::{DEFAULT_THEME, NO_COLOR_THEME};
+
+/// Overrides where the config file is read from
+pub static CONFIG_VAR: &str = "LAUNCHK_CONFIG";
+
+lazy_static! {
+    static ref CONFIG_PATH: PathBuf = config_path();
+}
+
+/// $LAUNCHK_CONFIG, then $XDG_CONFIG_HOME/launchk/config.toml (or
+/// ~/.config/launchk/config.toml) if it exists, then Application Support
+fn config_path() -> PathBuf {
+    if let Ok(path) = env::var(CONFIG_VAR) {
+        return PathBuf::from(path);
+    }
+
+    let home = PathBuf::from(env::var("HOME").expect("Must read $HOME"));
+    let xdg = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| home.join(".config"))
+        .join("launchk/config.toml");
+
+    if xdg.exists() {
+        xdg
+    } else {
+        home.join("Library/Application Support/launchk/config.toml")
+    }
+}
+
+/// Split a configured command into program and arguments, quoted like a shell.
+/// An environment variable that doesn't split is taken as one program.
+fn command(configured: Option<&str>, var: &str, default: &str) -> Vec<String> {
+    let words = match configured {
+        Some(command) => shell_words::split(command).ok(),
+        None => env::var(var)
+            .ok()
+            .map(|command| shell_words::split(&command).unwrap_or_else(|_| vec![command])),
+    };
+
+    words
+        .filter(|w| !w.is_empty() && !w[0].trim().is_empty())
+        .unwrap_or_else(|| vec![default.to_string()])
+}
+
+/// What the service list starts with, and what `clear` goes back to
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterDefaults {
+    /// Any of sguadlb
+    pub job_types: String,
+    pub filter_mode: FilterMode,
+}
+
+impl Default for FilterDefaults {
+    fn default() -> Self {
+        Self {
+            job_types: JobTypeFilter::launchk_default().to_string(),
+            filter_mode: FilterMode::default(),
+        }
+    }
+}
+
+impl FilterDefaults {
+    pub fn job_type_filter(&self) -> JobTypeFilter {
+        JobTypeFilter::from_letters(&self.job_types)
+            .unwrap_or_else(|_| JobTypeFilter::launchk_default())
+    }
+}
+
+/// Milliseconds between polls
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollIntervals {
+    /// Asking launchd which jobs are loaded
+    pub jobs: u64,
+    /// Reading followed log files
+    pub logs: u64,
+    /// How long the omnibox waits for keys before going back to the list
+    pub idle: u64,
+}
+
+impl Default for PollIntervals {
+    fn default() -> Self {
+        Self {
+            jobs: 1000,
+            logs: 500,
+            idle: 2000,
+        }
+    }
+}
+
+impl PollIntervals {
+    const MIN: u64 = 100;
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, ms) in [
+            ("jobs", self.jobs),
+            ("logs", self.logs),
+            ("idle", self.idle),
+        ]
+        .iter()
+        {
+            if *ms < Self::MIN {
+                return Err(format!(
+                    "poll.{} is {}ms, it must be at least {}ms",
+                    name,
+                    ms,
+                    Self::MIN
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn jobs(&self) -> Duration {
+        Duration::from_millis(self.jobs)
+    }
+
+    pub fn logs(&self) -> Duration {
+        Duration::from_millis(self.logs)
+    }
+
+    pub fn idle(&self) -> Duration {
+        Duration::from_millis(self.idle)
+    }
+}
+
+/// Which actions ask before they run
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmPolicy {
+    pub unload: bool,
+    pub disable: bool,
+    pub reload: bool,
+    /// Actions on marked jobs
+    pub bulk: bool,
+    pub undo_edit: bool,
+}
+
+impl Default for ConfirmPolicy {
+    fn default() -> Self {
+        Self {
+            unload: false,
+            disable: false,
+            reload: false,
+            bulk: true,
+            undo_edit: true,
+        }
+    }
+}
+
+/// The first of these that exists: $LAUNCHK_CONFIG,
+/// $XDG_CONFIG_HOME/launchk/config.toml (~/.config if unset), or
+/// ~/Library/Application Support/launchk/config.toml, e.g.
+///
+/// ```toml
+/// # dark, light, high-contrast or no-color (the default if $NO_COLOR is
+/// # set), a name from themes/ next to this file, or a path to a theme file
+/// theme = "light"
+///
+/// # Program and arguments, instead of $EDITOR and $PAGER. Quote paths with spaces
+/// editor = "code --wait"
+/// pager = "less -R"
+///
+/// # Instead of $LAUNCHK_PRIVILEGED_HELPER: writes read-only plists, given
+/// # the path as its last argument and the contents on stdin
+/// privileged_helper = "sudo tee"
+///
+/// # Where plists are copied before each edit, for undo-edit
+/// backup_dir = "~/.local/share/launchk/backups"
+///
+/// # Set false to only scan the dirs below (like a fixture tree)
+/// default_search_dirs = true
+///
+/// # Service list columns, in order. Width is optional, 0 shares the space left
+/// columns = [{ name = "label" }, { name = "pid", width = 8 }, { name = "health" }]
+///
+/// # Preset applied at startup, instead of [filters] below
+/// default_preset = "crashed"
+///
+/// [[search_dirs]]
+/// path = "~/src/*/launchd"
+/// scope = "user"     # system, global or user
+/// type = "agent"     # agent or daemon
+///
+/// # What the list starts with, and what clear goes back to
+/// [filters]
+/// job_types = "l"         # any of sguadlb
+/// filter_mode = "fuzzy"   # exact, fuzzy, regex or query
+///
+/// # Milliseconds, at least 100
+/// [poll]
+/// jobs = 1000   # asking launchd which jobs are loaded
+/// logs = 500    # reading followed logs
+/// idle = 2000   # before the omnibox goes back to the list
+///
+/// # Which actions ask first
+/// [confirm]
+/// unload = false
+/// disable = false
+/// reload = false
+/// bulk = true
+/// undo_edit = true
+///
+/// # Saved views, also recalled with :preset or their number key.
+/// # :preset-save appends these
+/// [[presets]]
+/// name = "crashed"
+/// filter = "loaded and exit!=0"
+/// filter_mode = "query"
+///
+/// # Keys, or chords of space separated keys, for command-mode, filter-mode,
+/// # clear, the sguadlb toggles (system, global, ...) and commands.
+/// # "none" unbinds a default
+/// [keys]
+/// "ctrl-x r" = "reload"
+/// "s" = "none"
+/// "S" = "system"
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Scan the directories launchd loads from, and app bundles
+    pub default_search_dirs: bool,
+    /// Extra directories, scanned before the defaults
+    pub search_dirs: Vec<SearchDir>,
+    /// Service list columns, empty for the defaults
+    pub columns: Vec<ColumnConfig>,
+    pub presets: Vec<Preset>,
+    pub default_preset: Option<String>,
+    /// Key -> action, on top of the default keymap
+    pub keys: BTreeMap<String, String>,
+    /// A built-in theme, one in the themes dir next to this file, or a path
+    pub theme: Option<String>,
+    pub editor: Option<String>,
+    pub pager: Option<String>,
+    /// Writes plists we can't, e.g. "sudo tee"
+    pub privileged_helper: Option<String>,
+    /// Instead of Application Support
+    pub backup_dir: Option<String>,
+    pub filters: FilterDefaults,
+    pub poll: PollIntervals,
+    pub confirm: ConfirmPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            default_search_dirs: true,
+            search_dirs: vec![],
+            columns: vec![],
+            presets: vec![],
+            default_preset: None,
+            keys: BTreeMap::new(),
+            theme: None,
+            editor: None,
+            pager: None,
+            privileged_helper: None,
+            backup_dir: None,
+            filters: FilterDefaults::default(),
+            poll: PollIntervals::default(),
+            confirm: ConfirmPolicy::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        validate_columns(&config.columns)?;
+        validate_presets(&config.presets, config.default_preset.as_deref())?;
+        Keymap::new(&config.keys)?;
+        config.poll.validate()?;
+
+        JobTypeFilter::from_letters(&config.filters.job_types)
+            .map_err(|e| format!("filters.job_types: {}", e))?;
+
+        let commands = [
+            ("editor", &config.editor),
+            ("pager", &config.pager),
+            ("privileged_helper", &config.privileged_helper),
+        ];
+
+        for (name, command) in commands.iter() {
+            if let Some(command) = command {
+                let words = shell_words::split(command).map_err(|e| format!("{}: {}", name, e))?;
+
+                if words.is_empty() {
+                    return Err(format!("{} is empty", name));
+                }
+            }
+        }
+
+        if config
+            .backup_dir
+            .as_ref()
+            .map(|d| d.trim().is_empty())
+            .unwrap_or(false)
+        {
+            return Err("backup_dir is empty".to_string());
+        }
+
+        Ok(config)
+    }
+
+    pub fn path() -> &'static Path {
+        &*CONFIG_PATH
+    }
+
+    /// Read the config file, defaults if there is none
+    pub fn load() -> Result<Self, String> {
+        let path = &*CONFIG_PATH;
+
+        match fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Configured dirs first, so they win over the defaults
+    pub fn search_dirs(&self) -> Vec<SearchDir> {
+        let mut dirs = self.search_dirs.clone();
+
+        if self.default_search_dirs {
+            dirs.extend(default_search_dirs());
+        }
+
+        dirs
+    }
+
+    pub fn columns(&self) -> Vec<ColumnConfig> {
+        if self.columns.is_empty() {
+            default_columns()
+        } else {
+            self.columns.clone()
+        }
+    }
+
+    /// `editor`, then $EDITOR, then vim
+    pub fn editor(&self) -> Vec<String> {
+        command(self.editor.as_deref(), "EDITOR", "vim")
+    }
+
+    /// `pager`, then $PAGER, then less
+    pub fn pager(&self) -> Vec<String> {
+        command(self.pager.as_deref(), "PAGER", "less")
+    }
+
+    /// `privileged_helper`, then $LAUNCHK_PRIVILEGED_HELPER, None to never elevate
+    pub fn privileged_helper(&self) -> Option<HelperCommand> {
+        self.privileged_helper
+            .clone()
+            .or_else(|| env::var(PRIVILEGED_HELPER_VAR).ok())
+            .and_then(|cmd| HelperCommand::parse(&cmd))
+            .map(HelperCommand::interactive)
+    }
+
+    /// `backup_dir` with ~ expanded, then Application Support
+    pub fn backup_dir(&self) -> PathBuf {
+        match &self.backup_dir {
+            Some(dir) => PathBuf::from(expand_home(dir, &env::var("HOME").unwrap_or_default())),
+            None => default_backup_dir(),
+        }
+    }
+
+    /// `theme`, then no-color if $NO_COLOR is set, then dark
+    pub fn theme(&self) -> String {
+        match &self.theme {
+            Some(theme) => theme.clone(),
+            None if env::var("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false) => {
+                NO_COLOR_THEME.to_string()
+            }
+            None => DEFAULT_THEME.to_string(),
+        }
+    }
+
+    /// Validated on load
+    pub fn keymap(&self) -> Keymap {
+        Keymap::new(&self.keys).unwrap_or_default()
+    }
+
+    pub fn default_preset(&self) -> Option<Preset> {
+        let name = self.default_preset.as_ref()?;
+        self.presets.iter().find(|p| p.name == *name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::launchd::edit::default_backup_dir;
+    use crate::launchd::job_type_filter::JobTypeFilter;
+    use crate::launchd::plist::{LaunchdEntryLocation, LaunchdEntryType};
+    use crate::launchd::privileged::HelperCommand;
+    use crate::launchd::search_dirs::{default_search_dirs, SearchDir};
+    use crate::tui::keymap::Keymap;
+    use crate::tui::service_list::columns::{default_columns, Column, ColumnConfig};
+    use crate::tui::service_list::filter::FilterMode;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn config_search_dirs() {
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+        assert_eq!(Config::default().search_dirs(), default_search_dirs());
+
+        let config = Config::from_toml(
+            r#"
+            default_search_dirs = false
+
+            [[search_dirs]]
+            path = "/fixtures/agents"
+            scope = "user"
+            type = "agent"
+
+            [[search_dirs]]
+            path = "~/src/*/daemons"
+            scope = "system"
+            type = "daemon"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.search_dirs(),
+            vec![
+                SearchDir::new(
+                    "/fixtures/agents",
+                    LaunchdEntryLocation::User,
+                    LaunchdEntryType::Agent
+                ),
+                SearchDir::new(
+                    "~/src/*/daemons",
+                    LaunchdEntryLocation::System,
+                    LaunchdEntryType::Daemon
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_rejects_bad_dirs() {
+        let dir = |body: &str| Config::from_toml(&format!("[[search_dirs]]\n{}", body));
+
+        assert!(dir("path = \"/a\"\nscope = \"user\"\ntype = \"agent\"").is_ok());
+        assert!(dir("path = \"/a\"\nscope = \"somewhere\"\ntype = \"agent\"").is_err());
+        assert!(dir("path = \"/a\"\nscope = \"user\"").is_err());
+        assert!(
+            dir("path = \"/a\"\nscope = \"user\"\ntype = \"agent\"\nrecursive = true").is_err()
+        );
+        assert!(Config::from_toml("search_dir = []").is_err());
+    }
+
+    #[test]
+    fn config_columns() {
+        assert_eq!(Config::default().columns(), default_columns());
+
+        let config = Config::from_toml(
+            r#"columns = [{ name = "program", width = 0 }, { name = "last_exit", width = 5 }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.columns(),
+            vec![
+                ColumnConfig {
+                    name: Column::Program,
+                    width: Some(0)
+                },
+                ColumnConfig {
+                    name: Column::LastExit,
+                    width: Some(5)
+                },
+            ]
+        );
+
+        assert!(Config::from_toml(r#"columns = [{ name = "uptime" }]"#).is_err());
+        assert!(Config::from_toml(r#"columns = [{ name = "pid" }, { name = "pid" }]"#).is_err());
+    }
+
+    #[test]
+    fn config_presets() {
+        let config = Config::from_toml(
+            r#"
+            default_preset = "crashed"
+
+            [[presets]]
+            name = "mine"
+            job_types = "ua"
+
+            [[presets]]
+            name = "crashed"
+            filter = "exit!=0"
+            filter_mode = "query"
+            sort = { column = "pid" }
+            "#,
+        )
+        .unwrap();
+
+        let preset = config.default_preset().unwrap();
+        assert_eq!(preset.name, "crashed");
+        assert_eq!(preset.filter_mode, FilterMode::Query);
+        assert_eq!(preset.sort.map(|s| s.column), Some(Column::Pid));
+        assert_eq!(
+            config.presets[0].job_type_filter(),
+            JobTypeFilter::USER | JobTypeFilter::AGENT
+        );
+        assert_eq!(Config::default().default_preset(), None);
+
+        assert!(Config::from_toml("default_preset = \"nope\"").is_err());
+        assert!(Config::from_toml("[[presets]]\nname = \"a\"\nfilter_mode = \"glob\"").is_err());
+    }
+
+    #[test]
+    fn config_keys() {
+        let config = Config::from_toml(
+            r#"
+            [keys]
+            "ctrl-x r" = "reload"
+            "s" = "none"
+            "#,
+        )
+        .unwrap();
+
+        assert_ne!(config.keymap(), Keymap::default());
+        assert!(Config::from_toml("[keys]\n\"g g\" = \"reload\"").is_err());
+        assert!(Config::from_toml("[keys]\n\"x\" = \"frobnicate\"").is_err());
+    }
+
+    #[test]
+    fn config_settings() {
+        let config = Config::from_toml(
+            r#"
+            editor = "code --wait"
+            theme = "light"
+            privileged_helper = "sudo -k tee"
+            backup_dir = "/tmp/launchk-backups"
+
+            [filters]
+            job_types = "sd"
+            filter_mode = "regex"
+
+            [poll]
+            jobs = 5000
+
+            [confirm]
+            unload = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.editor(), vec!["code", "--wait"]);
+        assert_eq!(
+            Config::from_toml("pager = \"'/opt/My Tools/pager' -R\"")
+                .unwrap()
+                .pager(),
+            vec!["/opt/My Tools/pager", "-R"]
+        );
+        assert_eq!(config.theme(), "light");
+        assert_eq!(config.backup_dir(), PathBuf::from("/tmp/launchk-backups"));
+        assert_eq!(Config::default().backup_dir(), default_backup_dir());
+        assert_eq!(
+            config.privileged_helper(),
+            HelperCommand::parse("sudo -k tee").map(HelperCommand::interactive)
+        );
+        assert_eq!(
+            config.filters.job_type_filter(),
+            JobTypeFilter::from_letters("sd").unwrap()
+        );
+        assert_eq!(config.filters.filter_mode, FilterMode::Regex);
+        assert_eq!(config.poll.jobs(), Duration::from_secs(5));
+        assert_eq!(config.poll.logs(), Duration::from_millis(500));
+        assert!(config.confirm.unload);
+        assert!(config.confirm.bulk);
+
+        assert!(Config::from_toml("[poll]\njobs = 10").is_err());
+        assert!(Config::from_toml("[filters]\njob_types = \"x\"").is_err());
+        assert!(Config::from_toml("editor = \"\"").is_err());
+        assert!(Config::from_toml("privileged_helper = \" \"").is_err());
+        assert!(Config::from_toml("backup_dir = \"\"").is_err());
+        assert!(Config::from_toml("editor = \"'code --wait\"").is_err());
+        assert!(Config::from_toml("[confirm]\nstop = true").is_err());
+    }
+
+    #[test]
+    fn config_doc_example() {
+        let source = include_str!("config.rs");
+        let start = source.find("/// ```toml\n").expect("Must have example") + 12;
+        let end = start + source[start..].find("/// ```\n").expect("Must end example");
+
+        let example = source[start..end]
+            .lines()
+            .map(|l| l.trim_start_matches("///").trim_start())
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        let config = Config::from_toml(&example).unwrap();
+
+        assert_eq!(config.default_preset, Some("crashed".to_string()));
+        assert_eq!(config.columns.len(), 3);
+        assert_eq!(config.search_dirs.len(), 1);
+    }
+}
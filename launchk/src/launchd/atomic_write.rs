@@ -0,0 +1,341 @@
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Attempts at finding an unused temp file name before giving up
+const TEMP_ATTEMPTS: usize = 8;
+
+/// A file only we can read or write, removed on drop unless persisted
+pub struct TempFile {
+    path: PathBuf,
+    file: File,
+    persisted: bool,
+}
+
+impl TempFile {
+    /// Create `{prefix}{random hex}{suffix}` in dir with mode 0600.
+    /// Fails rather than opening something that already exists.
+    pub fn new_in<P: AsRef<Path>>(dir: P, prefix: &str, suffix: &str) -> io::Result<Self> {
+        for _ in 0..TEMP_ATTEMPTS {
+            let path = dir
+                .as_ref()
+                .join(format!("{}{}{}", prefix, random_hex(8)?, suffix));
+
+            let opened = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path);
+
+            match opened {
+                Ok(file) => {
+                    return Ok(Self {
+                        path,
+                        file,
+                        persisted: false,
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "Cannot find an unused temp file name",
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Rename over target, after which the temp file is no longer ours to remove
+    pub fn persist<P: AsRef<Path>>(mut self, target: P) -> io::Result<()> {
+        fs::rename(&self.path, target)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Hex string from n bytes of /dev/urandom
+fn random_hex(n: usize) -> io::Result<String> {
+    let mut buf = vec![0u8; n];
+    File::open("/dev/urandom")?.read_exact(&mut buf)?;
+    Ok(buf.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Replace path with contents so readers see either the old or the new
+/// file, never a partial one. The new file is written next to the original
+/// (rename can't cross filesystems) and keeps its owner, mode and xattrs.
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<(), String> {
+    let path = path.as_ref();
+
+    // Write through symlinks rather than replacing them
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let display = path.display();
+
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("{} has no parent directory", display))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", display))?
+        .to_string_lossy();
+
+    let original = match fs::metadata(&path) {
+        Ok(meta) => Some(meta),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Cannot read {}: {}", display, e)),
+    };
+
+    let mut temp = TempFile::new_in(dir, &format!(".{}.", file_name), ".tmp")
+        .map_err(|e| format!("Cannot create temp file in {}: {}", dir.display(), e))?;
+
+    temp.file()
+        .write_all(contents)
+        .and_then(|_| temp.file().sync_all())
+        .map_err(|e| format!("Cannot write {}: {}", temp.path().display(), e))?;
+
+    if let Some(meta) = original {
+        copy_ownership(&meta, temp.file())
+            .map_err(|e| format!("Cannot keep owner of {}: {}", display, e))?;
+
+        temp.file()
+            .set_permissions(meta.permissions())
+            .map_err(|e| format!("Cannot keep mode of {}: {}", display, e))?;
+
+        xattr::copy(&path, temp.file())
+            .map_err(|e| format!("Cannot keep extended attributes of {}: {}", display, e))?;
+    }
+
+    temp.persist(&path)
+        .map_err(|e| format!("Cannot replace {}: {}", display, e))?;
+
+    // Make the rename itself durable, best effort
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+/// chown the new file to match the original, if it doesn't already
+fn copy_ownership(original: &fs::Metadata, file: &File) -> io::Result<()> {
+    let current = file.metadata()?;
+
+    if current.uid() == original.uid() && current.gid() == original.gid() {
+        return Ok(());
+    }
+
+    let ret = unsafe { libc::fchown(file.as_raw_fd(), original.uid(), original.gid()) };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Copying extended attributes (e.g. com.apple.quarantine) between files
+mod xattr {
+    use super::*;
+
+    pub fn copy(from: &Path, to: &File) -> io::Result<()> {
+        let from = CString::new(from.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        // Nothing to keep on filesystems without xattrs
+        let names = match list(&from) {
+            Ok(names) => names,
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        for name in names.split(|b| *b == 0).filter(|n| !n.is_empty()) {
+            let name =
+                CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let value = get(&from, &name)?;
+            set(to, &name, &value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Call a size-then-fill xattr function, retrying if the value grows in between
+    fn read_sized<F: Fn(*mut libc::c_void, libc::size_t) -> libc::ssize_t>(
+        call: F,
+    ) -> io::Result<Vec<u8>> {
+        loop {
+            let size = call(std::ptr::null_mut(), 0);
+            if size < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut buf = vec![0u8; size as usize];
+            let read = call(buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+
+            if read >= 0 {
+                buf.truncate(read as usize);
+                return Ok(buf);
+            }
+
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::ERANGE) {
+                return Err(err);
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn list(path: &CString) -> io::Result<Vec<u8>> {
+        read_sized(|buf, size| unsafe {
+            libc::listxattr(path.as_ptr(), buf as *mut libc::c_char, size, 0)
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    fn get(path: &CString, name: &CString) -> io::Result<Vec<u8>> {
+        read_sized(|buf, size| unsafe {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), buf, size, 0, 0)
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    fn set(file: &File, name: &CString, value: &[u8]) -> io::Result<()> {
+        let ret = unsafe {
+            libc::fsetxattr(
+                file.as_raw_fd(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+                0,
+            )
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn list(path: &CString) -> io::Result<Vec<u8>> {
+        read_sized(|buf, size| unsafe {
+            libc::listxattr(path.as_ptr(), buf as *mut libc::c_char, size)
+        })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn get(path: &CString, name: &CString) -> io::Result<Vec<u8>> {
+        read_sized(|buf, size| unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf, size) })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn set(file: &File, name: &CString, value: &[u8]) -> io::Result<()> {
+        let ret = unsafe {
+            libc::fsetxattr(
+                file.as_raw_fd(),
+                name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_atomic, TempFile};
+    use crate::launchd::test_util::test_dir;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn write_atomic_keeps_mode() {
+        let dir = test_dir("atomic-mode");
+        let path = dir.join("a.plist");
+
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_atomic_through_symlink() {
+        let dir = test_dir("atomic-link");
+        let target = dir.join("target.plist");
+        let link = dir.join("link.plist");
+
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, b"new").unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn temp_file_removed_unless_persisted() {
+        let dir = test_dir("atomic-temp");
+
+        let temp = TempFile::new_in(&dir, "a.", ".tmp").unwrap();
+        let other = TempFile::new_in(&dir, "a.", ".tmp").unwrap();
+        let temp_path = temp.path().to_path_buf();
+
+        assert_ne!(temp.path(), other.path());
+        assert_eq!(
+            fs::metadata(&temp_path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        drop(temp);
+        assert!(!temp_path.exists());
+
+        other.persist(dir.join("kept")).unwrap();
+        assert!(dir.join("kept").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
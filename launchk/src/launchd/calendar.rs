@@ -0,0 +1,360 @@
+use std::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// How many days ahead to look for a match. Day 29 of month 2 only
+/// comes around every 4 (sometimes 8) years, anything later never fires.
+const MAX_SEARCH_DAYS: usize = 366 * 8 + 1;
+
+/// Missing keys match anything
+fn wildcard_matches(field: Option<u32>, value: u32) -> bool {
+    field.is_none() || field == Some(value)
+}
+
+/// One StartCalendarInterval dictionary, None fields are wildcards
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CalendarInterval {
+    pub minute: Option<u32>,
+    pub hour: Option<u32>,
+    pub day: Option<u32>,
+    /// 0 and 7 are both Sunday
+    pub weekday: Option<u32>,
+    pub month: Option<u32>,
+}
+
+impl CalendarInterval {
+    pub fn from_dictionary(dict: &plist::Dictionary) -> Result<Self, String> {
+        let mut interval = Self::default();
+
+        for (key, value) in dict {
+            let (field, min, max) = match key.as_str() {
+                "Minute" => (&mut interval.minute, 0, 59),
+                "Hour" => (&mut interval.hour, 0, 23),
+                "Day" => (&mut interval.day, 1, 31),
+                "Weekday" => (&mut interval.weekday, 0, 7),
+                "Month" => (&mut interval.month, 1, 12),
+                k => return Err(format!("Unknown StartCalendarInterval key {}", k)),
+            };
+
+            let n = value
+                .as_unsigned_integer()
+                .filter(|n| (min..=max).contains(n))
+                .ok_or_else(|| format!("{} must be an integer from {} to {}", key, min, max))?;
+
+            *field = Some(n as u32);
+        }
+
+        Ok(interval)
+    }
+
+    pub fn to_dictionary(self) -> plist::Dictionary {
+        let fields = [
+            ("Minute", self.minute),
+            ("Hour", self.hour),
+            ("Day", self.day),
+            ("Weekday", self.weekday),
+            ("Month", self.month),
+        ];
+
+        fields
+            .iter()
+            .filter_map(|(k, v)| v.map(|v| (k.to_string(), plist::Value::from(v as u64))))
+            .collect()
+    }
+
+    /// Like crontab(5), if both Day and Weekday are set either may match
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        let month = wildcard_matches(self.month, date.month());
+        let day = self.day.map(|d| date.day() == d);
+        let weekday = self
+            .weekday
+            .map(|w| date.weekday().num_days_from_sunday() == w % 7);
+
+        let day_or_weekday = match (day, weekday) {
+            (Some(d), Some(w)) => d || w,
+            (Some(d), None) => d,
+            (None, Some(w)) => w,
+            (None, None) => true,
+        };
+
+        month && day_or_weekday
+    }
+
+    /// First fire time strictly after `after`, None if it never fires
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.date().and_hms(after.hour(), after.minute(), 0) + Duration::minutes(1);
+        let mut date = start.date();
+
+        for _ in 0..MAX_SEARCH_DAYS {
+            if self.matches_date(date) {
+                let from = if date == start.date() {
+                    (start.hour(), start.minute())
+                } else {
+                    (0, 0)
+                };
+
+                let time = (0..24)
+                    .filter(|h| wildcard_matches(self.hour, *h))
+                    .flat_map(|h| (0..60).map(move |m| (h, m)))
+                    .filter(|(_, m)| wildcard_matches(self.minute, *m))
+                    .find(|hm| *hm >= from);
+
+                if let Some((h, m)) = time {
+                    return Some(date.and_hms(h, m, 0));
+                }
+            }
+
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+}
+
+/// crontab(5) order: minute hour day month weekday
+impl fmt::Display for CalendarInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = [self.minute, self.hour, self.day, self.month, self.weekday]
+            .iter()
+            .map(|v| v.map(|n| n.to_string()).unwrap_or("*".to_string()))
+            .collect();
+
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+/// A job's StartCalendarInterval, which fires whenever any interval matches
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CalendarSchedule {
+    pub intervals: Vec<CalendarInterval>,
+}
+
+impl CalendarSchedule {
+    /// Parse either a single dictionary or an array of them
+    pub fn from_plist(value: &plist::Value) -> Result<Self, String> {
+        let intervals = match value {
+            plist::Value::Dictionary(d) => vec![CalendarInterval::from_dictionary(d)?],
+            plist::Value::Array(a) => a
+                .iter()
+                .map(|v| {
+                    v.as_dictionary()
+                        .ok_or_else(|| {
+                            "StartCalendarInterval items must be dictionaries".to_string()
+                        })
+                        .and_then(CalendarInterval::from_dictionary)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err("StartCalendarInterval must be a dictionary or array".to_string()),
+        };
+
+        Ok(Self { intervals })
+    }
+
+    /// A single interval is written as a dictionary, like most plists do
+    pub fn to_plist(&self) -> plist::Value {
+        match self.intervals.as_slice() {
+            [single] => plist::Value::Dictionary(single.to_dictionary()),
+            intervals => plist::Value::Array(
+                intervals
+                    .iter()
+                    .map(|i| plist::Value::Dictionary(i.to_dictionary()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Up to n fire times strictly after `after`, earliest first
+    pub fn next_runs(&self, after: NaiveDateTime, n: usize) -> Vec<NaiveDateTime> {
+        let mut runs = Vec::with_capacity(n);
+        let mut last = after;
+
+        while runs.len() < n {
+            let next = self
+                .intervals
+                .iter()
+                .filter_map(|i| i.next_after(last))
+                .min();
+
+            match next {
+                Some(next) => {
+                    runs.push(next);
+                    last = next;
+                }
+                None => break,
+            }
+        }
+
+        runs
+    }
+}
+
+impl fmt::Display for CalendarSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let intervals: Vec<String> = self.intervals.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}", intervals.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CalendarInterval, CalendarSchedule};
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, mo, d).and_hms(h, mi, 0)
+    }
+
+    fn dict(entries: &[(&str, u64)]) -> plist::Value {
+        let mut d = plist::Dictionary::new();
+        for (k, v) in entries {
+            d.insert(k.to_string(), plist::Value::from(*v));
+        }
+        plist::Value::Dictionary(d)
+    }
+
+    #[test]
+    fn calendar_parse_dictionary_and_array() {
+        let single = CalendarSchedule::from_plist(&dict(&[("Hour", 3), ("Minute", 15)])).unwrap();
+        assert_eq!(
+            single.intervals,
+            vec![CalendarInterval {
+                hour: Some(3),
+                minute: Some(15),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(single.to_string(), "15 3 * * *");
+
+        let many = CalendarSchedule::from_plist(&plist::Value::Array(vec![
+            dict(&[("Weekday", 1)]),
+            dict(&[("Day", 1), ("Month", 6)]),
+        ]))
+        .unwrap();
+        assert_eq!(many.intervals.len(), 2);
+        assert_eq!(many.to_string(), "* * * * 1, * * 1 6 *");
+    }
+
+    #[test]
+    fn calendar_parse_errors() {
+        assert!(CalendarSchedule::from_plist(&dict(&[("Hour", 24)])).is_err());
+        assert!(CalendarSchedule::from_plist(&dict(&[("Day", 0)])).is_err());
+        assert!(CalendarSchedule::from_plist(&dict(&[("Second", 1)])).is_err());
+        assert!(CalendarSchedule::from_plist(&plist::Value::from("daily")).is_err());
+        assert!(
+            CalendarSchedule::from_plist(&plist::Value::Array(vec![plist::Value::from(1)]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn calendar_daily() {
+        let s = CalendarSchedule::from_plist(&dict(&[("Hour", 3), ("Minute", 15)])).unwrap();
+
+        assert_eq!(
+            s.next_runs(at(2021, 5, 1, 3, 15), 2),
+            vec![at(2021, 5, 2, 3, 15), at(2021, 5, 3, 3, 15)]
+        );
+        assert_eq!(
+            s.next_runs(at(2021, 5, 1, 3, 14), 1),
+            vec![at(2021, 5, 1, 3, 15)]
+        );
+    }
+
+    #[test]
+    fn calendar_wildcards() {
+        // Only Minute: every hour at :30
+        let s = CalendarSchedule::from_plist(&dict(&[("Minute", 30)])).unwrap();
+        assert_eq!(
+            s.next_runs(at(2021, 12, 31, 23, 45), 2),
+            vec![at(2022, 1, 1, 0, 30), at(2022, 1, 1, 1, 30)]
+        );
+
+        // Empty dictionary: every minute
+        let s = CalendarSchedule::from_plist(&dict(&[])).unwrap();
+        assert_eq!(
+            s.next_runs(at(2021, 5, 1, 10, 59), 2),
+            vec![at(2021, 5, 1, 11, 0), at(2021, 5, 1, 11, 1)]
+        );
+
+        // Only Hour: every minute of that hour
+        let s = CalendarSchedule::from_plist(&dict(&[("Hour", 4)])).unwrap();
+        assert_eq!(
+            s.next_runs(at(2021, 5, 1, 4, 59), 1),
+            vec![at(2021, 5, 2, 4, 0)]
+        );
+    }
+
+    #[test]
+    fn calendar_weekday_and_day() {
+        // 2021-05-02 is a Sunday, 0 and 7 both mean Sunday
+        for sunday in &[0, 7] {
+            let s = CalendarSchedule::from_plist(&dict(&[
+                ("Weekday", *sunday),
+                ("Hour", 0),
+                ("Minute", 0),
+            ]))
+            .unwrap();
+            assert_eq!(
+                s.next_runs(at(2021, 5, 1, 12, 0), 2),
+                vec![at(2021, 5, 2, 0, 0), at(2021, 5, 9, 0, 0)]
+            );
+        }
+
+        // Day and Weekday: either matches
+        let s = CalendarSchedule::from_plist(&dict(&[
+            ("Day", 15),
+            ("Weekday", 1),
+            ("Hour", 0),
+            ("Minute", 0),
+        ]))
+        .unwrap();
+        assert_eq!(
+            s.next_runs(at(2021, 5, 11, 0, 0), 3),
+            vec![
+                at(2021, 5, 15, 0, 0),
+                at(2021, 5, 17, 0, 0),
+                at(2021, 5, 24, 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn calendar_sparse_and_never() {
+        let leap = CalendarSchedule::from_plist(&dict(&[
+            ("Month", 2),
+            ("Day", 29),
+            ("Hour", 0),
+            ("Minute", 0),
+        ]))
+        .unwrap();
+        assert_eq!(
+            leap.next_runs(at(2021, 3, 1, 0, 0), 2),
+            vec![at(2024, 2, 29, 0, 0), at(2028, 2, 29, 0, 0)]
+        );
+
+        let never = CalendarSchedule::from_plist(&dict(&[("Month", 2), ("Day", 30)])).unwrap();
+        assert!(never.next_runs(at(2021, 1, 1, 0, 0), 1).is_empty());
+
+        let empty = CalendarSchedule::from_plist(&plist::Value::Array(vec![])).unwrap();
+        assert!(empty.next_runs(at(2021, 1, 1, 0, 0), 1).is_empty());
+    }
+
+    #[test]
+    fn calendar_merges_intervals() {
+        let s = CalendarSchedule::from_plist(&plist::Value::Array(vec![
+            dict(&[("Hour", 12), ("Minute", 0)]),
+            dict(&[("Hour", 6), ("Minute", 0)]),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            s.next_runs(at(2021, 5, 1, 7, 0), 3),
+            vec![
+                at(2021, 5, 1, 12, 0),
+                at(2021, 5, 2, 6, 0),
+                at(2021, 5, 2, 12, 0)
+            ]
+        );
+    }
+}
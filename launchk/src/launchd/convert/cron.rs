@@ -0,0 +1,416 @@
+use crate::launchd::calendar::{CalendarInterval, CalendarSchedule};
+use crate::launchd::convert::{unmapped_other_keys, Conversion};
+use crate::launchd::job::{KeepAlive, LaunchdJob};
+
+/// More than this many StartCalendarInterval entries is probably a mistake
+const MAX_INTERVALS: usize = 1000;
+
+static MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+static WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Quote for /bin/sh, leaving simple words alone
+fn shell_quote(word: &str) -> String {
+    let simple = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c));
+
+    if simple {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
+
+/// The shell command cron should run, with `%` escaped
+fn command_line(job: &LaunchdJob, conversion: &mut Conversion<Vec<String>>) -> Option<String> {
+    let (program, args) = job.command()?;
+
+    if args.first() != Some(&program) {
+        conversion.unmapped("ProgramArguments", "argv[0] cannot differ from Program");
+    }
+
+    let mut command = std::iter::once(&program)
+        .chain(args.iter().skip(1))
+        .map(|a| shell_quote(a))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    if let Some(dir) = &job.working_directory {
+        command = format!("cd {} && {}", shell_quote(dir), command);
+    }
+
+    if let Some(out) = &job.standard_out_path {
+        command.push_str(&format!(" >> {}", shell_quote(out)));
+    }
+
+    if let Some(err) = &job.standard_error_path {
+        command.push_str(&format!(" 2>> {}", shell_quote(err)));
+    }
+
+    Some(command.replace('%', "\\%"))
+}
+
+/// crontab(5) schedule for StartInterval, if it divides evenly into the clock
+fn interval_schedule(seconds: u64) -> Option<String> {
+    let (minutes, hours) = (seconds / 60, seconds / 3600);
+
+    match seconds {
+        60 => Some("* * * * *".to_string()),
+        s if s % 60 == 0 && minutes > 0 && minutes < 60 && 60 % minutes == 0 => {
+            Some(format!("*/{} * * * *", minutes))
+        }
+        s if s % 3600 == 0 && hours > 0 && hours < 24 && 24 % hours == 0 => {
+            Some(format!("0 */{} * * *", hours))
+        }
+        86400 => Some("0 0 * * *".to_string()),
+        _ => None,
+    }
+}
+
+/// Convert to crontab lines: environment assignments, then one line per schedule
+pub fn to_cron(job: &LaunchdJob) -> Result<Conversion<Vec<String>>, String> {
+    let mut conversion = Conversion::new(vec![]);
+    let command = command_line(job, &mut conversion)
+        .ok_or_else(|| "Job has no Program or ProgramArguments".to_string())?;
+
+    let mut lines: Vec<String> = job
+        .environment_variables
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect();
+
+    let mut scheduled = false;
+
+    if let Some(schedule) = &job.start_calendar_interval {
+        for interval in schedule.intervals.iter() {
+            lines.push(format!("{} {}", interval, command));
+            scheduled = true;
+        }
+    }
+
+    if let Some(seconds) = job.start_interval {
+        match interval_schedule(seconds) {
+            Some(schedule) => {
+                lines.push(format!("{} {}", schedule, command));
+                scheduled = true;
+            }
+            None => conversion.unmapped(
+                "StartInterval",
+                format!("Every {}s does not line up with the clock", seconds),
+            ),
+        }
+    }
+
+    if job.run_at_load {
+        lines.push(format!("@reboot {}", command));
+        scheduled = true;
+    }
+
+    if !scheduled {
+        return Err("Job has no StartCalendarInterval, StartInterval or RunAtLoad".to_string());
+    }
+
+    if job.keep_alive != KeepAlive::Never {
+        conversion.unmapped("KeepAlive", "cron does not restart jobs");
+    }
+
+    for (key, value) in &[("UserName", &job.user_name), ("GroupName", &job.group_name)] {
+        if value.is_some() {
+            conversion.unmapped(*key, "User crontabs run as their owner");
+        }
+    }
+
+    unmapped_other_keys(job, &mut conversion);
+    conversion.output = lines;
+
+    Ok(conversion)
+}
+
+fn parse_value(value: &str, names: &[&str], offset: u32) -> Result<u32, String> {
+    value.parse::<u32>().or_else(|_| {
+        names
+            .iter()
+            .position(|n| *n == value.to_ascii_lowercase())
+            .map(|p| p as u32 + offset)
+            .ok_or_else(|| format!("Cannot parse {}", value))
+    })
+}
+
+/// One crontab field: `*`, lists, ranges and steps. None is a wildcard.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    offset: u32,
+) -> Result<Option<Vec<u32>>, String> {
+    if field == "*" {
+        return Ok(None);
+    }
+
+    let mut values = vec![];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("Bad step in {}", part))?,
+            ),
+            None => (part, 1),
+        };
+
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (
+                parse_value(from, names, offset)?,
+                parse_value(to, names, offset)?,
+            ),
+            None => {
+                let v = parse_value(range, names, offset)?;
+                (v, if step > 1 { max } else { v })
+            }
+        };
+
+        if from < min || to > max || from > to {
+            return Err(format!("{} is outside {}-{}", part, min, max));
+        }
+
+        values.extend((from..=to).step_by(step as usize));
+    }
+
+    values.sort_unstable();
+    values.dedup();
+    Ok(Some(values))
+}
+
+/// Split off n whitespace separated fields, returning them and the rest of the line
+fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(n);
+    let mut rest = line.trim_start();
+
+    for _ in 0..n {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+
+    Some((fields, rest))
+}
+
+/// Convert one crontab entry, run with /bin/sh -c like cron does
+pub fn from_cron(label: &str, line: &str) -> Result<Conversion<LaunchdJob>, String> {
+    let line = line.trim();
+    let mut conversion = Conversion::new(LaunchdJob::new(label));
+
+    let (schedule, command) = if let Some(rest) = line.strip_prefix('@') {
+        let (keyword, command) = split_fields(rest, 1)
+            .map(|(f, c)| (f[0], c))
+            .ok_or_else(|| format!("Missing command in {}", line))?;
+
+        let schedule = match keyword {
+            "reboot" => None,
+            "yearly" | "annually" => Some("0 0 1 1 *"),
+            "monthly" => Some("0 0 1 * *"),
+            "weekly" => Some("0 0 * * 0"),
+            "daily" | "midnight" => Some("0 0 * * *"),
+            "hourly" => Some("0 * * * *"),
+            k => return Err(format!("Unknown schedule @{}", k)),
+        };
+
+        conversion.output.run_at_load = schedule.is_none();
+        (
+            schedule.map(|s| s.split(' ').collect::<Vec<&str>>()),
+            command,
+        )
+    } else {
+        let (fields, command) = split_fields(line, 5)
+            .ok_or_else(|| format!("Expected 5 fields and a command in {}", line))?;
+        (Some(fields), command)
+    };
+
+    if command.is_empty() {
+        return Err(format!("Missing command in {}", line));
+    }
+
+    if let Some(fields) = schedule {
+        let minutes = parse_field(fields[0], 0, 59, &[], 0)?;
+        let hours = parse_field(fields[1], 0, 23, &[], 0)?;
+        let days = parse_field(fields[2], 1, 31, &[], 0)?;
+        let months = parse_field(fields[3], 1, 12, &MONTHS, 1)?;
+        let weekdays = parse_field(fields[4], 0, 7, &WEEKDAYS, 0)?;
+
+        // Wildcards stay wildcards, everything else is a cartesian product
+        let expand = |values: &Option<Vec<u32>>| -> Vec<Option<u32>> {
+            match values {
+                None => vec![None],
+                Some(v) => v.iter().map(|n| Some(*n)).collect(),
+            }
+        };
+
+        let count = [&minutes, &hours, &days, &months, &weekdays]
+            .iter()
+            .map(|v| expand(v).len())
+            .product::<usize>();
+
+        if count > MAX_INTERVALS {
+            return Err(format!(
+                "Schedule needs {} StartCalendarInterval entries (max {})",
+                count, MAX_INTERVALS
+            ));
+        }
+
+        let mut intervals = vec![];
+        for month in expand(&months) {
+            for day in expand(&days) {
+                for weekday in expand(&weekdays) {
+                    for hour in expand(&hours) {
+                        for minute in expand(&minutes) {
+                            intervals.push(CalendarInterval {
+                                minute,
+                                hour,
+                                day,
+                                weekday,
+                                month,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        conversion.output.start_calendar_interval = Some(CalendarSchedule { intervals });
+    }
+
+    // An unescaped % ends the command, the rest goes to stdin
+    let mut shell_command = String::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('%')) => {
+                shell_command.push('%');
+                chars.next();
+            }
+            ('%', _) => {
+                conversion.unmapped("command", "Text after % is sent to stdin");
+                break;
+            }
+            (c, _) => shell_command.push(c),
+        }
+    }
+
+    conversion.output.program_arguments =
+        vec!["/bin/sh".to_string(), "-c".to_string(), shell_command];
+
+    Ok(conversion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_cron, to_cron};
+    use crate::launchd::calendar::{CalendarInterval, CalendarSchedule};
+    use crate::launchd::job::{KeepAlive, LaunchdJob};
+
+    #[test]
+    fn to_cron_lines() {
+        let mut job = LaunchdJob::new("com.example.backup");
+        job.program_arguments = vec!["/usr/local/bin/backup".to_string(), "50% off".to_string()];
+        job.working_directory = Some("/Users/me".to_string());
+        job.environment_variables
+            .insert("MODE".to_string(), "full".to_string());
+        job.start_calendar_interval = Some(CalendarSchedule {
+            intervals: vec![CalendarInterval {
+                minute: Some(0),
+                hour: Some(2),
+                weekday: Some(6),
+                ..Default::default()
+            }],
+        });
+        job.start_interval = Some(900);
+        job.keep_alive = KeepAlive::Always;
+
+        let conversion = to_cron(&job).unwrap();
+        let command = "cd /Users/me && /usr/local/bin/backup '50\\% off'";
+
+        assert_eq!(
+            conversion.output,
+            vec![
+                "MODE=full".to_string(),
+                format!("0 2 * * 6 {}", command),
+                format!("*/15 * * * * {}", command),
+            ]
+        );
+        assert_eq!(
+            conversion.report(""),
+            "KeepAlive: cron does not restart jobs\n"
+        );
+    }
+
+    #[test]
+    fn to_cron_unschedulable() {
+        let mut job = LaunchdJob::new("com.example.daemon");
+        job.program = Some("/usr/sbin/daemon".to_string());
+        assert!(to_cron(&job).is_err());
+
+        job.start_interval = Some(45);
+        assert!(to_cron(&job).is_err());
+
+        job.start_interval = Some(0);
+        assert!(to_cron(&job).is_err());
+
+        job.run_at_load = true;
+        let conversion = to_cron(&job).unwrap();
+        assert_eq!(conversion.output, vec!["@reboot /usr/sbin/daemon"]);
+        assert_eq!(conversion.unmapped[0].key, "StartInterval");
+    }
+
+    #[test]
+    fn from_cron_expands_fields() {
+        let conversion = from_cron("job", "*/20 9-10 * jan mon,fri  echo hi \\% > /tmp/x").unwrap();
+        let job = conversion.output;
+
+        assert_eq!(
+            job.program_arguments,
+            vec!["/bin/sh", "-c", "echo hi % > /tmp/x"]
+        );
+
+        let intervals = job.start_calendar_interval.unwrap().intervals;
+        assert_eq!(intervals.len(), 3 * 2 * 2);
+        assert_eq!(
+            intervals[0],
+            CalendarInterval {
+                minute: Some(0),
+                hour: Some(9),
+                day: None,
+                weekday: Some(1),
+                month: Some(1),
+            }
+        );
+        assert!(conversion.unmapped.is_empty());
+    }
+
+    #[test]
+    fn from_cron_special() {
+        let reboot = from_cron("job", "@reboot /usr/bin/true").unwrap().output;
+        assert!(reboot.run_at_load);
+        assert_eq!(reboot.start_calendar_interval, None);
+
+        let weekly = from_cron("job", "@weekly cat %hello").unwrap();
+        assert_eq!(
+            weekly.output.program_arguments,
+            vec!["/bin/sh", "-c", "cat "]
+        );
+        assert_eq!(weekly.unmapped[0].key, "command");
+
+        assert!(from_cron("job", "* * * *").is_err());
+        assert!(from_cron("job", "60 * * * * true").is_err());
+        assert!(from_cron("job", "* * * * * ").is_err());
+        assert!(from_cron("job", "0-59 0-23 * * * true").is_err());
+    }
+}
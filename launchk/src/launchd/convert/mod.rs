@@ -0,0 +1,49 @@
+use crate::launchd::job::LaunchdJob;
+
+pub mod cron;
+pub mod systemd;
+
+/// A key or directive with no equivalent on the other side
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unmapped {
+    pub key: String,
+    pub reason: String,
+}
+
+/// The converted output and everything that was left behind
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion<T> {
+    pub output: T,
+    pub unmapped: Vec<Unmapped>,
+}
+
+impl<T> Conversion<T> {
+    pub fn new(output: T) -> Self {
+        Self {
+            output,
+            unmapped: vec![],
+        }
+    }
+
+    pub fn unmapped<K: Into<String>, R: Into<String>>(&mut self, key: K, reason: R) {
+        self.unmapped.push(Unmapped {
+            key: key.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// One "key: reason" line per unmapped item, each starting with prefix
+    pub fn report(&self, prefix: &str) -> String {
+        self.unmapped
+            .iter()
+            .map(|u| format!("{}{}: {}\n", prefix, u.key, u.reason))
+            .collect()
+    }
+}
+
+/// Keys without a typed field in LaunchdJob can't be converted
+fn unmapped_other_keys<T>(job: &LaunchdJob, conversion: &mut Conversion<T>) {
+    for key in job.other.keys() {
+        conversion.unmapped(key.clone(), "No equivalent");
+    }
+}
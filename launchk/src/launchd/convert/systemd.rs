@@ -0,0 +1,635 @@
+use std::fmt::Write;
+
+use crate::launchd::calendar::{CalendarInterval, CalendarSchedule};
+use crate::launchd::convert::{unmapped_other_keys, Conversion};
+use crate::launchd::job::{KeepAlive, LaunchdJob};
+
+static WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// A .service unit, and a .timer unit if the job is scheduled
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SystemdUnits {
+    pub service: String,
+    pub timer: Option<String>,
+}
+
+/// Quote an ExecStart/Environment word, escaping specifiers and variables
+fn quote_word(word: &str) -> String {
+    let escaped = word.replace('%', "%%").replace('$', "$$");
+    let needs_quotes = escaped.is_empty()
+        || escaped
+            .chars()
+            .any(|c| c.is_whitespace() || "\"'\\;".contains(c));
+
+    if needs_quotes {
+        format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        escaped
+    }
+}
+
+/// Split a command line the way systemd does: whitespace separated,
+/// single or double quotes, backslash escapes
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', _) => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| format!("Trailing backslash in {}", line))?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => word.get_or_insert_with(String::new).push(c),
+            ('"', None) | ('\'', None) => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (c, None) if c.is_whitespace() => words.extend(word.take()),
+            (c, None) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(format!("Unterminated quote in {}", line));
+    }
+
+    words.extend(word.take());
+    Ok(words)
+}
+
+/// `%%` and `$$` are literal, anything else is a specifier or variable we can't expand
+fn unescape_word(word: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(word.len());
+    let mut chars = word.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '%' || c == '$' {
+            if chars.peek() != Some(&c) {
+                return Err(format!("{} uses a specifier or variable", word));
+            }
+            chars.next();
+        }
+        out.push(c);
+    }
+
+    Ok(out)
+}
+
+/// OnCalendar expressions for an interval. systemd requires all fields
+/// to match, so a Day and Weekday (either matches) become two expressions.
+fn on_calendar(interval: &CalendarInterval) -> Vec<String> {
+    let field = |v: Option<u32>| v.map(|n| format!("{:02}", n)).unwrap_or("*".to_string());
+    let month = field(interval.month);
+    let time = format!("{}:{}:00", field(interval.hour), field(interval.minute));
+
+    let by_day = format!("*-{}-{} {}", month, field(interval.day), time);
+    let by_weekday = interval
+        .weekday
+        .map(|w| format!("{} *-{}-* {}", WEEKDAYS[(w % 7) as usize], month, time));
+
+    match (interval.day, by_weekday) {
+        (Some(_), Some(by_weekday)) => vec![by_weekday, by_day],
+        (None, Some(by_weekday)) => vec![by_weekday],
+        _ => vec![by_day],
+    }
+}
+
+/// Convert to a .service (and .timer) named after the label
+pub fn to_systemd(job: &LaunchdJob) -> Result<Conversion<SystemdUnits>, String> {
+    let (program, args) = job
+        .command()
+        .ok_or_else(|| "Job has no Program or ProgramArguments".to_string())?;
+
+    let mut conversion = Conversion::new(SystemdUnits {
+        service: String::new(),
+        timer: None,
+    });
+    let mut service = String::new();
+
+    // @ lets argv[0] differ from the executable, like Program + ProgramArguments
+    let exec_start = if args.first() == Some(&program) {
+        args.iter()
+            .map(|a| quote_word(a))
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        format!(
+            "@{}",
+            std::iter::once(&program)
+                .chain(args.iter())
+                .map(|a| quote_word(a))
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    };
+
+    writeln!(service, "[Unit]\nDescription={}\n", job.label).unwrap();
+    writeln!(service, "[Service]\nExecStart={}", exec_start).unwrap();
+
+    if let Some(dir) = &job.working_directory {
+        writeln!(service, "WorkingDirectory={}", dir).unwrap();
+    }
+
+    if let Some(user) = &job.user_name {
+        writeln!(service, "User={}", user).unwrap();
+    }
+
+    if let Some(group) = &job.group_name {
+        writeln!(service, "Group={}", group).unwrap();
+    }
+
+    for (k, v) in job.environment_variables.iter() {
+        writeln!(
+            service,
+            "Environment={}",
+            quote_word(&format!("{}={}", k, v))
+        )
+        .unwrap();
+    }
+
+    if let Some(out) = &job.standard_out_path {
+        writeln!(service, "StandardOutput=append:{}", out).unwrap();
+    }
+
+    if let Some(err) = &job.standard_error_path {
+        writeln!(service, "StandardError=append:{}", err).unwrap();
+    }
+
+    match &job.keep_alive {
+        KeepAlive::Never => {}
+        KeepAlive::Always => writeln!(service, "Restart=always").unwrap(),
+        KeepAlive::OnFailure => writeln!(service, "Restart=on-failure").unwrap(),
+        KeepAlive::OnSuccess => writeln!(service, "Restart=on-success").unwrap(),
+        KeepAlive::Conditions(_) => {
+            conversion.unmapped("KeepAlive", "Only SuccessfulExit maps to Restart=")
+        }
+    }
+
+    // launchd starts KeepAlive jobs at load even without RunAtLoad
+    if job.run_at_load || job.keep_alive == KeepAlive::Always {
+        writeln!(service, "\n[Install]\nWantedBy=default.target").unwrap();
+    }
+
+    let mut timer = String::new();
+
+    if let Some(schedule) = &job.start_calendar_interval {
+        for interval in schedule.intervals.iter() {
+            for expr in on_calendar(interval) {
+                writeln!(timer, "OnCalendar={}", expr).unwrap();
+            }
+        }
+
+        // launchd runs intervals missed while asleep on wake
+        writeln!(timer, "Persistent=true").unwrap();
+    }
+
+    if let Some(interval) = job.start_interval {
+        writeln!(
+            timer,
+            "OnActiveSec={}s\nOnUnitActiveSec={}s",
+            interval, interval
+        )
+        .unwrap();
+    }
+
+    if !timer.is_empty() {
+        conversion.output.timer = Some(format!(
+            "[Unit]\nDescription={} timer\n\n[Timer]\n{}\n[Install]\nWantedBy=timers.target\n",
+            job.label, timer
+        ));
+    }
+
+    unmapped_other_keys(job, &mut conversion);
+    conversion.output.service = service;
+
+    Ok(conversion)
+}
+
+/// Parse unit file text into (section, key, value) in file order
+fn parse_unit(text: &str) -> Result<Vec<(String, String, String)>, String> {
+    let mut entries = vec![];
+    let mut section = String::new();
+    let mut continued = String::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if continued.is_empty()
+            && (line.is_empty() || line.starts_with('#') || line.starts_with(';'))
+        {
+            continue;
+        }
+
+        if let Some(rest) = line.strip_suffix('\\') {
+            continued.push_str(rest);
+            continued.push(' ');
+            continue;
+        }
+
+        let line = format!("{}{}", continued, line);
+        continued.clear();
+
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Expected Key=Value, got {}", line))?;
+
+        entries.push((
+            section.clone(),
+            key.trim().to_string(),
+            value.trim().to_string(),
+        ));
+    }
+
+    Ok(entries)
+}
+
+fn parse_weekday(name: &str) -> Result<u32, String> {
+    let prefix: String = name
+        .chars()
+        .take(3)
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    WEEKDAYS
+        .iter()
+        .position(|w| w.to_ascii_lowercase() == prefix)
+        .map(|p| p as u32)
+        .ok_or_else(|| format!("Unknown weekday {}", name))
+}
+
+/// `Mon,Wed..Fri` -> [1, 3, 4, 5]
+fn parse_weekdays(spec: &str) -> Result<Vec<u32>, String> {
+    let mut days = vec![];
+
+    for part in spec.split(',') {
+        match part.split_once("..") {
+            Some((from, to)) => {
+                let (from, to) = (parse_weekday(from)?, parse_weekday(to)?);
+                if from > to {
+                    return Err(format!("Weekday range {} wraps around", part));
+                }
+                days.extend(from..=to);
+            }
+            None => days.push(parse_weekday(part)?),
+        }
+    }
+
+    Ok(days)
+}
+
+/// `*` or a single number
+fn parse_calendar_field(field: &str) -> Result<Option<u32>, String> {
+    if field == "*" {
+        return Ok(None);
+    }
+
+    field
+        .parse::<u32>()
+        .map(Some)
+        .map_err(|_| format!("Only * or single values are supported, got {}", field))
+}
+
+/// Single values and wildcards of `[Weekdays] [[*-]M-D] [H:M[:00]]`
+/// (and the shorthands) as calendar intervals
+fn parse_on_calendar(expr: &str) -> Result<Vec<CalendarInterval>, String> {
+    let expr = match expr.trim() {
+        "minutely" => "*-*-* *:*:00",
+        "hourly" => "*-*-* *:00:00",
+        "daily" => "*-*-* 00:00:00",
+        "weekly" => "Mon *-*-* 00:00:00",
+        "monthly" => "*-*-01 00:00:00",
+        "yearly" | "annually" => "*-01-01 00:00:00",
+        expr => expr,
+    };
+
+    let mut weekdays = vec![None];
+    let mut interval = CalendarInterval {
+        hour: Some(0),
+        minute: Some(0),
+        ..Default::default()
+    };
+
+    for token in expr.split_whitespace() {
+        if token.contains(':') {
+            let parts: Vec<&str> = token.split(':').collect();
+            match parts.as_slice() {
+                [h, m] | [h, m, "00"] | [h, m, "0"] => {
+                    interval.hour = parse_calendar_field(h)?;
+                    interval.minute = parse_calendar_field(m)?;
+                }
+                _ => return Err(format!("Seconds must be 00 in {}", token)),
+            }
+        } else if token.contains('-') {
+            let parts: Vec<&str> = token.split('-').collect();
+            let (month, day) = match parts.as_slice() {
+                ["*", m, d] | [m, d] => (m, d),
+                _ => return Err(format!("Years are not supported in {}", token)),
+            };
+            interval.month = parse_calendar_field(month)?;
+            interval.day = parse_calendar_field(day)?;
+        } else {
+            weekdays = parse_weekdays(token)?.into_iter().map(Some).collect();
+        }
+    }
+
+    let intervals = weekdays
+        .into_iter()
+        .map(|weekday| CalendarInterval {
+            weekday,
+            ..interval
+        })
+        .collect::<Vec<_>>();
+
+    // Validate ranges by round tripping through the plist parser
+    for i in intervals.iter() {
+        CalendarInterval::from_dictionary(&i.to_dictionary())?;
+    }
+
+    Ok(intervals)
+}
+
+/// `90`, `5min`, `1h 30min` -> seconds
+fn parse_timespan(span: &str) -> Result<u64, String> {
+    let mut total = 0;
+    let mut rest = span.trim();
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let n: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("Cannot parse timespan {}", span))?;
+        rest = rest[digits..].trim_start();
+
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let multiplier = match &rest[..unit_len] {
+            "" | "s" | "sec" | "second" | "seconds" => 1,
+            "m" | "min" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hour" | "hours" => 60 * 60,
+            "d" | "day" | "days" => 60 * 60 * 24,
+            "w" | "week" | "weeks" => 60 * 60 * 24 * 7,
+            u => return Err(format!("Unsupported timespan unit {}", u)),
+        };
+
+        total += n * multiplier;
+        rest = rest[unit_len..].trim_start();
+    }
+
+    Ok(total)
+}
+
+/// Convert a .service (and optional .timer) back into a job
+pub fn from_systemd(
+    label: &str,
+    service: &str,
+    timer: Option<&str>,
+) -> Result<Conversion<LaunchdJob>, String> {
+    let mut conversion = Conversion::new(LaunchdJob::new(label));
+    let mut exec_start = false;
+
+    for (section, key, value) in parse_unit(service)? {
+        let job = &mut conversion.output;
+        let path = format!("[{}] {}", section, key);
+
+        match (section.as_str(), key.as_str()) {
+            ("Service", "ExecStart") if !exec_start => {
+                exec_start = true;
+
+                let prefix_len = value.find(|c| !"@-:+!".contains(c)).unwrap_or(0);
+                let (prefix, command) = value.split_at(prefix_len);
+                let words = split_words(command)?
+                    .iter()
+                    .map(|w| unescape_word(w))
+                    .collect::<Result<Vec<String>, String>>()?;
+
+                if prefix.contains('@') && words.len() > 1 {
+                    job.program = Some(words[0].clone());
+                    job.program_arguments = words[1..].to_vec();
+                } else {
+                    job.program_arguments = words;
+                }
+
+                if prefix.chars().any(|c| c != '@') {
+                    conversion.unmapped(path, format!("Prefix {} has no equivalent", prefix));
+                }
+            }
+            ("Service", "WorkingDirectory") => job.working_directory = Some(value),
+            ("Service", "User") => job.user_name = Some(value),
+            ("Service", "Group") => job.group_name = Some(value),
+            ("Service", "Environment") => {
+                for pair in split_words(&value)? {
+                    let pair = unescape_word(&pair)?;
+                    let (k, v) = pair
+                        .split_once('=')
+                        .ok_or_else(|| format!("Expected K=V in Environment, got {}", pair))?;
+                    job.environment_variables
+                        .insert(k.to_string(), v.to_string());
+                }
+            }
+            ("Service", "StandardOutput") | ("Service", "StandardError") => {
+                let file = value
+                    .strip_prefix("append:")
+                    .or_else(|| value.strip_prefix("file:"));
+
+                match (file, key.as_str()) {
+                    (Some(f), "StandardOutput") => job.standard_out_path = Some(f.to_string()),
+                    (Some(f), _) => job.standard_error_path = Some(f.to_string()),
+                    (None, _) if value == "journal" || value == "inherit" => {}
+                    (None, _) => conversion.unmapped(path, "Only files can be redirected to"),
+                }
+            }
+            ("Service", "Restart") => match value.as_str() {
+                "no" => job.keep_alive = KeepAlive::Never,
+                "always" => job.keep_alive = KeepAlive::Always,
+                "on-failure" => job.keep_alive = KeepAlive::OnFailure,
+                "on-success" => job.keep_alive = KeepAlive::OnSuccess,
+                _ => conversion.unmapped(path, format!("Restart={} has no equivalent", value)),
+            },
+            ("Service", "Type") if value == "simple" || value == "exec" => {}
+            ("Install", "WantedBy") | ("Install", "RequiredBy") => job.run_at_load = true,
+            _ => conversion.unmapped(path, "No equivalent"),
+        }
+    }
+
+    if !exec_start {
+        return Err("Service has no ExecStart".to_string());
+    }
+
+    let timer_entries = timer.map(parse_unit).transpose()?.unwrap_or_default();
+    let mut intervals = vec![];
+    let mut on_active = None;
+
+    for (section, key, value) in timer_entries {
+        let path = format!("[{}] {}", section, key);
+
+        match (section.as_str(), key.as_str()) {
+            ("Timer", "OnCalendar") => match parse_on_calendar(&value) {
+                Ok(parsed) => intervals.extend(parsed),
+                Err(e) => conversion.unmapped(path, e),
+            },
+            ("Timer", "OnUnitActiveSec") => {
+                conversion.output.start_interval = Some(parse_timespan(&value)?)
+            }
+            ("Timer", "OnActiveSec") => on_active = Some(parse_timespan(&value)?),
+            // launchd already catches up on missed intervals, and
+            // the service's Description was already reported
+            ("Timer", "Persistent") | ("Install", "WantedBy") | ("Unit", "Description") => {}
+            _ => conversion.unmapped(path, "No equivalent"),
+        }
+    }
+
+    // StartInterval waits an interval before the first run too
+    if on_active.is_some() && on_active != conversion.output.start_interval {
+        conversion.unmapped(
+            "[Timer] OnActiveSec",
+            "Only supported alongside an equal OnUnitActiveSec",
+        );
+    }
+
+    if !intervals.is_empty() {
+        conversion.output.start_calendar_interval = Some(CalendarSchedule { intervals });
+    }
+
+    Ok(conversion)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_systemd, parse_on_calendar, split_words, to_systemd};
+    use crate::launchd::calendar::{CalendarInterval, CalendarSchedule};
+    use crate::launchd::job::{KeepAlive, LaunchdJob};
+
+    fn job() -> LaunchdJob {
+        let mut job = LaunchdJob::new("com.example.sync");
+        job.program_arguments = vec![
+            "/usr/local/bin/sync".to_string(),
+            "--to".to_string(),
+            "my dir".to_string(),
+        ];
+        job.environment_variables
+            .insert("RATE".to_string(), "50%".to_string());
+        job.keep_alive = KeepAlive::OnFailure;
+        job.run_at_load = true;
+        job.standard_out_path = Some("/var/log/sync.log".to_string());
+        job
+    }
+
+    #[test]
+    fn to_systemd_service() {
+        let conversion = to_systemd(&job()).unwrap();
+
+        assert_eq!(
+            conversion.output.service,
+            "[Unit]\nDescription=com.example.sync\n\n\
+             [Service]\nExecStart=/usr/local/bin/sync --to \"my dir\"\n\
+             Environment=RATE=50%%\n\
+             StandardOutput=append:/var/log/sync.log\n\
+             Restart=on-failure\n\n\
+             [Install]\nWantedBy=default.target\n"
+        );
+        assert_eq!(conversion.output.timer, None);
+        assert!(conversion.unmapped.is_empty());
+    }
+
+    #[test]
+    fn to_systemd_timer_and_unmapped() {
+        let mut job = job();
+        job.start_calendar_interval = Some(CalendarSchedule {
+            intervals: vec![CalendarInterval {
+                minute: Some(30),
+                hour: Some(4),
+                day: Some(1),
+                weekday: Some(7),
+                month: None,
+            }],
+        });
+        job.other
+            .insert("ThrottleInterval".to_string(), plist::Value::from(10));
+
+        let conversion = to_systemd(&job).unwrap();
+        let timer = conversion.output.timer.clone().unwrap();
+
+        assert!(timer.contains("OnCalendar=Sun *-*-* 04:30:00\nOnCalendar=*-*-01 04:30:00\n"));
+        assert_eq!(
+            conversion.report("# "),
+            "# ThrottleInterval: No equivalent\n"
+        );
+    }
+
+    #[test]
+    fn from_systemd_round_trip() {
+        let mut job = job();
+        job.start_calendar_interval = Some(CalendarSchedule {
+            intervals: vec![CalendarInterval {
+                minute: Some(0),
+                hour: Some(3),
+                weekday: Some(1),
+                ..Default::default()
+            }],
+        });
+
+        let units = to_systemd(&job).unwrap().output;
+        let conversion =
+            from_systemd("com.example.sync", &units.service, units.timer.as_deref()).unwrap();
+
+        // Description has nowhere to go
+        assert_eq!(conversion.output, job);
+        assert_eq!(conversion.unmapped.len(), 1);
+        assert_eq!(conversion.unmapped[0].key, "[Unit] Description");
+    }
+
+    #[test]
+    fn from_systemd_argv0_and_unmapped() {
+        let service = "[Service]\n\
+                       Type=forking\n\
+                       ExecStart=@/usr/bin/python3 worker \\\n  -v '%%done'\n\
+                       Restart=on-abort\n";
+        let timer = "[Timer]\nOnCalendar=Mon..Wed 09:15\nOnUnitActiveSec=1h 30min\n";
+
+        let conversion = from_systemd("worker", service, Some(timer)).unwrap();
+        let job = conversion.output;
+
+        assert_eq!(job.program, Some("/usr/bin/python3".to_string()));
+        assert_eq!(job.program_arguments, vec!["worker", "-v", "%done"]);
+        assert_eq!(job.start_interval, Some(5400));
+        assert_eq!(job.start_calendar_interval.unwrap().intervals.len(), 3);
+
+        let keys: Vec<&str> = conversion.unmapped.iter().map(|u| u.key.as_str()).collect();
+        assert_eq!(keys, vec!["[Service] Type", "[Service] Restart"]);
+    }
+
+    #[test]
+    fn systemd_parsing() {
+        assert_eq!(
+            split_words(r#"a "b c" 'd e' f\ g"#).unwrap(),
+            vec!["a", "b c", "d e", "f g"]
+        );
+        assert!(split_words("\"open").is_err());
+
+        assert_eq!(
+            parse_on_calendar("daily").unwrap(),
+            vec![CalendarInterval {
+                minute: Some(0),
+                hour: Some(0),
+                ..Default::default()
+            }]
+        );
+        assert!(parse_on_calendar("*-*-* *:0/15").is_err());
+        assert!(parse_on_calendar("2021-*-* 00:00").is_err());
+        assert!(parse_on_calendar("*-*-* 25:00").is_err());
+    }
+}
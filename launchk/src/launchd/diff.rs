@@ -0,0 +1,169 @@
+use std::cmp::max;
+
+/// Lines of context around each change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line based LCS, plenty for plists that are at most a few hundred lines
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<DiffLine<'a>> {
+    // lcs[i][j] is the LCS length of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::with_capacity(max(old.len(), new.len()));
+
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(old[i..].iter().map(|l| DiffLine::Removed(l)));
+    lines.extend(new[j..].iter().map(|l| DiffLine::Added(l)));
+    lines
+}
+
+/// Unified diff of old and new, empty if they are the same
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    // Line numbers in old and new before each diff line
+    let mut positions = Vec::with_capacity(lines.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+
+    for line in lines.iter() {
+        positions.push((old_pos, new_pos));
+        match line {
+            DiffLine::Same(_) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            DiffLine::Removed(_) => old_pos += 1,
+            DiffLine::Added(_) => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    // Merge changes closer than 2 * CONTEXT into one hunk
+    let mut hunks: Vec<(usize, usize)> = vec![];
+
+    for (i, _) in lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| !matches!(l, DiffLine::Same(_)))
+    {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    for (start, end) in hunks {
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        let (old_count, new_count) = (old_end - old_start, new_end - new_start);
+
+        // Empty ranges point at the line before, like diff(1)
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + old_count.min(1),
+            old_count,
+            new_start + new_count.min(1),
+            new_count
+        ));
+
+        for line in &lines[start..end] {
+            let (prefix, text) = match line {
+                DiffLine::Same(t) => (' ', t),
+                DiffLine::Removed(t) => ('-', t),
+                DiffLine::Added(t) => ('+', t),
+            };
+
+            out.push(prefix);
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn unified_diff_same() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), "");
+    }
+
+    #[test]
+    fn unified_diff_change() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n";
+
+        assert_eq!(
+            unified_diff(old, new, "old", "new"),
+            "--- old\n+++ new\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn unified_diff_separate_hunks() {
+        let old = (1..=20).map(|i| format!("{}\n", i)).collect::<String>();
+        let new = (1..=20)
+            .filter(|i| *i != 19)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                i => format!("{}\n", i),
+            })
+            .collect::<String>();
+        let diff = unified_diff(&old, &new, "old", "new");
+
+        assert!(diff.contains("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n"));
+        assert!(diff.contains("@@ -16,5 +16,4 @@\n 16\n 17\n 18\n-19\n 20\n"));
+    }
+
+    #[test]
+    fn unified_diff_append() {
+        assert_eq!(
+            unified_diff("a\n", "a\nb\n", "old", "new"),
+            "--- old\n+++ new\n@@ -1,1 +1,2 @@\n a\n+b\n"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+
+/// Where launchd records `launchctl enable` and `launchctl disable`
+const OVERRIDES_DIR: &str = "/private/var/db/com.apple.xpc.launchd";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EnableState {
+    Enabled,
+    Disabled,
+    /// The overrides could not be read
+    Unknown,
+}
+
+impl fmt::Display for EnableState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_ascii_lowercase())
+    }
+}
+
+/// System daemons are overridden in disabled.plist, a user's
+/// agents in disabled.<uid>.plist
+pub fn overrides_path(is_daemon: bool) -> PathBuf {
+    let file = if is_daemon {
+        "disabled.plist".to_string()
+    } else {
+        format!("disabled.{}.plist", unsafe { libc::getuid() })
+    };
+
+    Path::new(OVERRIDES_DIR).join(file)
+}
+
+/// An override wins over the plist's own Disabled key
+pub fn enable_state(label: &str, overrides: &Path, plist_disabled: bool) -> EnableState {
+    let overridden = match fs::read(overrides) {
+        Ok(bytes) => match plist::Value::from_reader(Cursor::new(bytes)) {
+            Ok(overrides) => overrides
+                .as_dictionary()
+                .and_then(|d| d.get(label))
+                .and_then(|v| v.as_boolean()),
+            Err(_) => return EnableState::Unknown,
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(_) => return EnableState::Unknown,
+    };
+
+    if overridden.unwrap_or(plist_disabled) {
+        EnableState::Disabled
+    } else {
+        EnableState::Enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enable_state, EnableState};
+    use crate::launchd::test_util::test_dir;
+    use std::fs;
+
+    #[test]
+    fn disabled_overrides() {
+        let dir = test_dir("disabled");
+        let overrides = dir.join("disabled.501.plist");
+
+        // Nothing overridden yet
+        assert_eq!(
+            enable_state("com.example.a", &overrides, false),
+            EnableState::Enabled
+        );
+        assert_eq!(
+            enable_state("com.example.a", &overrides, true),
+            EnableState::Disabled
+        );
+
+        let mut dict = plist::Dictionary::new();
+        dict.insert("com.example.a".to_string(), true.into());
+        dict.insert("com.example.b".to_string(), false.into());
+        plist::Value::Dictionary(dict)
+            .to_file_xml(&overrides)
+            .unwrap();
+
+        assert_eq!(
+            enable_state("com.example.a", &overrides, false),
+            EnableState::Disabled
+        );
+        assert_eq!(
+            enable_state("com.example.b", &overrides, true),
+            EnableState::Enabled
+        );
+        assert_eq!(
+            enable_state("com.example.c", &overrides, false),
+            EnableState::Enabled
+        );
+
+        fs::write(&overrides, "garbage").unwrap();
+        assert_eq!(
+            enable_state("com.example.a", &overrides, false),
+            EnableState::Unknown
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
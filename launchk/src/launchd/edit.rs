@@ -0,0 +1,243 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::launchd::diff::unified_diff;
+use crate::launchd::plist::{encode_plist, read_plist, LaunchdPlist};
+use crate::launchd::privileged::{can_write, privileged_helper, readonly_error, replace_file};
+
+lazy_static! {
+    /// Set from the config at startup
+    static ref BACKUP_DIR: RwLock<PathBuf> = RwLock::new(default_backup_dir());
+    /// (plist path, backup path) for each committed edit, most recent last
+    static ref EDIT_BACKUPS: Mutex<Vec<(String, PathBuf)>> = Mutex::new(vec![]);
+}
+
+/// An edited plist waiting to be reviewed. Both sides are kept
+/// as XML, which is what the user sees in the diff.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlistEdit {
+    pub plist_meta: LaunchdPlist,
+    pub is_binary: bool,
+    og_xml: String,
+    xml: String,
+}
+
+pub fn plist_to_xml(plist: &plist::Value) -> Result<String, String> {
+    let mut buf: Vec<u8> = vec![];
+    plist.to_writer_xml(&mut buf).map_err(|e| e.to_string())?;
+    String::from_utf8(buf).map_err(|e| e.to_string())
+}
+
+/// Ok if we can write the plist, or have a helper that can
+pub fn check_writable(plist_meta: &LaunchdPlist) -> Result<(), String> {
+    if plist_meta.readonly && privileged_helper().is_none() {
+        return Err(readonly_error());
+    }
+
+    Ok(())
+}
+
+impl PlistEdit {
+    pub fn new(
+        plist_meta: &LaunchdPlist,
+        og_plist: &plist::Value,
+        plist: &plist::Value,
+        is_binary: bool,
+    ) -> Result<Self, String> {
+        check_writable(plist_meta)?;
+
+        let og_xml = plist_to_xml(og_plist)?;
+        let xml = plist_to_xml(plist)?;
+
+        if og_xml == xml {
+            return Err("No changes made".to_string());
+        }
+
+        Ok(Self {
+            plist_meta: plist_meta.clone(),
+            is_binary,
+            og_xml,
+            xml,
+        })
+    }
+
+    pub fn diff(&self) -> String {
+        unified_diff(
+            &self.og_xml,
+            &self.xml,
+            &format!("{} (original)", self.plist_meta.plist_path),
+            &format!("{} (edited)", self.plist_meta.plist_path),
+        )
+    }
+
+    /// Back up the original, then replace it keeping its encoding.
+    /// Read-only plists go through the privileged helper. Returns the backup path.
+    pub fn commit(&self) -> Result<PathBuf, String> {
+        let plist_path = &self.plist_meta.plist_path;
+
+        // Don't clobber something else's changes made while we were editing
+        let (on_disk, _) = read_plist(plist_path)?;
+        if plist_to_xml(&on_disk)? != self.og_xml {
+            return Err(format!("{} changed on disk, edit not saved", plist_path));
+        }
+
+        let plist = plist::Value::from_reader_xml(self.xml.as_bytes())
+            .map_err(|e| format!("Changes not saved: {}", e))?;
+
+        let contents = encode_plist(&plist, self.is_binary)?;
+        let backup_path = backup_plist(plist_path)?;
+        replace_file(
+            Path::new(plist_path),
+            &contents,
+            self.plist_meta.readonly,
+            privileged_helper().as_deref(),
+        )?;
+
+        // Only once the plist changed, otherwise undo would restore nothing
+        EDIT_BACKUPS
+            .lock()
+            .expect("Must record backup")
+            .push((plist_path.to_string(), backup_path.clone()));
+
+        Ok(backup_path)
+    }
+}
+
+/// Where plists are backed up unless configured
+pub fn default_backup_dir() -> PathBuf {
+    Path::new(&env::var("HOME").expect("Must read $HOME"))
+        .join("Library/Application Support/launchk/backups")
+}
+
+pub fn set_backup_dir(dir: PathBuf) {
+    *BACKUP_DIR.write().expect("Must set backup dir") = dir;
+}
+
+/// Copy a plist into the backup directory, suffixed with the time in ms
+pub fn backup_plist(plist_path: &str) -> Result<PathBuf, String> {
+    let backup_dir = BACKUP_DIR.read().expect("Must read backup dir").clone();
+
+    fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Cannot create {}: {}", backup_dir.display(), e))?;
+
+    let file_name = Path::new(plist_path)
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", plist_path))?
+        .to_string_lossy()
+        .to_string();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Must get ts");
+
+    let backup_path = backup_dir.join(format!("{}.{}", file_name, now.as_millis()));
+
+    fs::copy(plist_path, &backup_path)
+        .map_err(|e| format!("Cannot back up {}: {}", plist_path, e))?;
+
+    Ok(backup_path)
+}
+
+/// Most recent (plist path, backup path), if anything was edited
+pub fn last_backup() -> Option<(String, PathBuf)> {
+    EDIT_BACKUPS.lock().ok()?.last().cloned()
+}
+
+/// Restore the most recent backup over the plist it was taken from
+pub fn undo_last_edit() -> Result<(String, PathBuf), String> {
+    let mut backups = EDIT_BACKUPS
+        .lock()
+        .map_err(|_| "Cannot read backups".to_string())?;
+
+    let (plist_path, backup_path) = backups
+        .last()
+        .cloned()
+        .ok_or_else(|| "No edits to undo".to_string())?;
+
+    let backup = fs::read(&backup_path)
+        .map_err(|e| format!("Cannot read {}: {}", backup_path.display(), e))?;
+    replace_file(
+        Path::new(&plist_path),
+        &backup,
+        !can_write(&plist_path),
+        privileged_helper().as_deref(),
+    )
+    .map_err(|e| format!("Cannot restore {}: {}", plist_path, e))?;
+
+    backups.pop();
+    Ok((plist_path, backup_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{last_backup, set_backup_dir, undo_last_edit, PlistEdit};
+    use crate::launchd::plist::{read_plist, LaunchdPlist};
+    use crate::launchd::test_util::{agents, test_dir, write_job};
+    use std::fs;
+
+    fn edited(plist: &plist::Value) -> plist::Value {
+        let mut plist = plist.clone();
+        plist
+            .as_dictionary_mut()
+            .unwrap()
+            .insert("RunAtLoad".to_string(), plist::Value::Boolean(true));
+        plist
+    }
+
+    // One test, backups and the backup dir are global
+    #[test]
+    fn edit_commit_backup_undo() {
+        let dir = test_dir("edit");
+        let backups = dir.join("backups");
+        set_backup_dir(backups.clone());
+
+        let path = dir.join("com.example.job.plist");
+        write_job(&path, "com.example.job");
+        let original = fs::read(&path).unwrap();
+        let meta = LaunchdPlist::new(&agents(&dir)[0], &path, None);
+        let (og_plist, is_binary) = read_plist(&path).unwrap();
+
+        assert!(PlistEdit::new(&meta, &og_plist, &og_plist, is_binary).is_err());
+
+        let edit = PlistEdit::new(&meta, &og_plist, &edited(&og_plist), is_binary).unwrap();
+        assert!(edit.diff().contains("+\t<key>RunAtLoad</key>"));
+
+        // Changed by something else since it was read
+        write_job(&path, "com.example.other");
+        let err = edit.commit().unwrap_err();
+        assert!(err.contains("changed on disk"), "{}", err);
+        assert_eq!(last_backup(), None);
+        fs::write(&path, &original).unwrap();
+
+        // A failed write leaves nothing to undo
+        let mut readonly = edit.clone();
+        readonly.plist_meta.readonly = true;
+        assert!(readonly.commit().is_err());
+        assert_eq!(last_backup(), None);
+        assert_eq!(fs::read(&path).unwrap(), original);
+
+        let backup_path = edit.commit().unwrap();
+        assert_eq!(backup_path.parent(), Some(backups.as_path()));
+        assert!(backup_path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("com.example.job.plist."));
+        assert_eq!(fs::read(&backup_path).unwrap(), original);
+        assert_eq!(read_plist(&path).unwrap().0, edited(&og_plist));
+        assert_eq!(
+            last_backup(),
+            Some((path.to_string_lossy().to_string(), backup_path.clone()))
+        );
+
+        assert_eq!(undo_last_edit().unwrap().1, backup_path);
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert_eq!(last_backup(), None);
+        assert!(undo_last_edit().is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,7 +3,10 @@ use std::convert::TryInto;
 use std::sync::Mutex;
 use std::time::{Duration, SystemTime};
 
-use crate::launchd::plist::LaunchdPlist;
+use crate::launchd::disabled::{enable_state, overrides_path, EnableState};
+use crate::launchd::health::{check_plist, HealthProblem};
+use crate::launchd::job::LaunchdJob;
+use crate::launchd::plist::{read_plist, LaunchdEntryType, LaunchdPlist};
 use crate::launchd::query::find_in_all;
 
 use xpc_sys::enums::{DomainType, SessionType};
@@ -16,13 +19,20 @@ lazy_static! {
         Mutex::new(HashMap::new());
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LaunchdEntryStatus {
     pub plist: Option<LaunchdPlist>,
+    /// The plist's contents, if it parses
+    pub job: Option<LaunchdJob>,
+    pub enabled: EnableState,
     pub limit_load_to_session_type: SessionType,
     pub domain: DomainType,
     // So, there is a pid_t, but it's i32, and the XPC response has an i64?
     pub pid: i64,
+    /// Raw wait(2) status from the job's last run, if launchd knows it
+    pub last_exit_status: Option<i64>,
+    /// Empty if nothing is wrong, or there is no plist to check
+    pub health: Vec<HealthProblem>,
     tick: SystemTime,
 }
 
@@ -32,7 +42,11 @@ impl Default for LaunchdEntryStatus {
             limit_load_to_session_type: SessionType::Unknown,
             domain: DomainType::Unknown,
             plist: None,
+            job: None,
+            enabled: EnableState::Unknown,
             pid: 0,
+            last_exit_status: None,
+            health: vec![],
             tick: SystemTime::now(),
         }
     }
@@ -71,6 +85,13 @@ fn build_entry_status<S: Into<String>>(label: S) -> LaunchdEntryStatus {
         .and_then(|o| o.xpc_value())
         .unwrap_or(0);
 
+    let last_exit_status: Option<i64> = response
+        .as_ref()
+        .map_err(|e| e.clone())
+        .and_then(|(_, r)| r.get(&["service", "LastExitStatus"]))
+        .and_then(|o| o.xpc_value())
+        .ok();
+
     let limit_load_to_session_type = response
         .as_ref()
         .map_err(|e| e.clone())
@@ -84,12 +105,43 @@ fn build_entry_status<S: Into<String>>(label: S) -> LaunchdEntryStatus {
         .unwrap_or(DomainType::Unknown);
 
     let entry_config = crate::launchd::plist::for_label(label_string.clone());
+    let plist = entry_config
+        .as_ref()
+        .map(|ec| read_plist(&ec.plist_path).map(|(p, _)| p));
+
+    let health = match &plist {
+        Some(Ok(plist)) => check_plist(plist),
+        Some(Err(e)) => vec![HealthProblem::Unreadable(e.clone())],
+        None => vec![],
+    };
+
+    let job = plist
+        .and_then(|p| p.ok())
+        .and_then(|p| LaunchdJob::from_plist(&p).ok());
+
+    let enabled = match (&entry_config, &job) {
+        (Some(ec), Some(job)) => {
+            let disabled = job
+                .other
+                .get("Disabled")
+                .and_then(|v| v.as_boolean())
+                .unwrap_or(false);
+            let overrides = overrides_path(ec.entry_type == LaunchdEntryType::Daemon);
+
+            enable_state(&label_string, &overrides, disabled)
+        }
+        _ => EnableState::Unknown,
+    };
 
     LaunchdEntryStatus {
         limit_load_to_session_type,
         domain,
         plist: entry_config,
+        job,
+        enabled,
         pid,
+        last_exit_status,
+        health,
         tick: SystemTime::now(),
     }
 }
@@ -0,0 +1,314 @@
+use std::fmt;
+use std::path::Path;
+use std::time::SystemTime;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Single key objects standing in for the plist types JSON and YAML lack.
+/// TOML has native dates, so only needs DATA_KEY.
+static DATE_KEY: &str = "$date";
+static DATA_KEY: &str = "$data";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+pub static EXPORT_FORMATS: [ExportFormat; 3] =
+    [ExportFormat::Json, ExportFormat::Yaml, ExportFormat::Toml];
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Toml => "toml",
+        }
+    }
+
+    /// Format for a file name, by extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path
+            .as_ref()
+            .extension()?
+            .to_string_lossy()
+            .to_ascii_lowercase();
+
+        match ext.as_str() {
+            "yml" => Some(ExportFormat::Yaml),
+            ext => EXPORT_FORMATS
+                .iter()
+                .find(|f| f.extension() == ext)
+                .copied(),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension().to_ascii_uppercase())
+    }
+}
+
+fn date_to_string(date: &plist::Date) -> String {
+    let time: SystemTime = (*date).into();
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn date_from_string(date: &str) -> Result<plist::Date, String> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|d| plist::Date::from(SystemTime::from(d)))
+        .map_err(|e| format!("Cannot parse date {}: {}", date, e))
+}
+
+fn data_from_string(data: &str) -> Result<plist::Value, String> {
+    base64::decode(data)
+        .map(plist::Value::Data)
+        .map_err(|e| format!("Cannot decode data: {}", e))
+}
+
+fn to_json_value(plist: &plist::Value) -> Result<serde_json::Value, String> {
+    let tagged = |key: &str, value: String| {
+        let mut map = serde_json::Map::new();
+        map.insert(key.to_string(), serde_json::Value::String(value));
+        serde_json::Value::Object(map)
+    };
+
+    let value = match plist {
+        plist::Value::Dictionary(d) => serde_json::Value::Object(
+            d.iter()
+                .map(|(k, v)| to_json_value(v).map(|v| (k.clone(), v)))
+                .collect::<Result<serde_json::Map<String, serde_json::Value>, String>>()?,
+        ),
+        plist::Value::Array(a) => serde_json::Value::Array(
+            a.iter()
+                .map(to_json_value)
+                .collect::<Result<Vec<serde_json::Value>, String>>()?,
+        ),
+        plist::Value::Boolean(b) => serde_json::Value::Bool(*b),
+        plist::Value::String(s) => serde_json::Value::String(s.clone()),
+        plist::Value::Integer(i) => i
+            .as_signed()
+            .map(serde_json::Number::from)
+            .or_else(|| i.as_unsigned().map(serde_json::Number::from))
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| "Cannot export integer".to_string())?,
+        plist::Value::Real(r) => serde_json::Number::from_f64(*r)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| format!("Cannot export {}", r))?,
+        plist::Value::Date(d) => tagged(DATE_KEY, date_to_string(d)),
+        plist::Value::Data(d) => tagged(DATA_KEY, base64::encode(d)),
+        _ => return Err("UIDs cannot be exported".to_string()),
+    };
+
+    Ok(value)
+}
+
+fn from_json_value(value: serde_json::Value) -> Result<plist::Value, String> {
+    let value = match value {
+        serde_json::Value::Null => return Err("null has no plist equivalent".to_string()),
+        serde_json::Value::Bool(b) => plist::Value::Boolean(b),
+        serde_json::Value::String(s) => plist::Value::String(s),
+        // Whole numbers (no decimal point) are integers, like in the plist
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => plist::Value::from(i),
+            (_, Some(u), _) => plist::Value::from(u),
+            (_, _, Some(f)) => plist::Value::Real(f),
+            _ => return Err(format!("Cannot import {}", n)),
+        },
+        serde_json::Value::Array(a) => plist::Value::Array(
+            a.into_iter()
+                .map(from_json_value)
+                .collect::<Result<Vec<plist::Value>, String>>()?,
+        ),
+        serde_json::Value::Object(o) => {
+            let tag = match (o.len(), o.iter().next()) {
+                (1, Some((k, serde_json::Value::String(s)))) => Some((k.as_str(), s.as_str())),
+                _ => None,
+            };
+
+            match tag {
+                Some((k, s)) if k == DATE_KEY => plist::Value::Date(date_from_string(s)?),
+                Some((k, s)) if k == DATA_KEY => data_from_string(s)?,
+                _ => plist::Value::Dictionary(
+                    o.into_iter()
+                        .map(|(k, v)| from_json_value(v).map(|v| (k, v)))
+                        .collect::<Result<plist::Dictionary, String>>()?,
+                ),
+            }
+        }
+    };
+
+    Ok(value)
+}
+
+fn to_toml_value(plist: &plist::Value) -> Result<toml::Value, String> {
+    let value = match plist {
+        plist::Value::Dictionary(d) => toml::Value::Table(
+            d.iter()
+                .map(|(k, v)| to_toml_value(v).map(|v| (k.clone(), v)))
+                .collect::<Result<toml::map::Map<String, toml::Value>, String>>()?,
+        ),
+        plist::Value::Array(a) => toml::Value::Array(
+            a.iter()
+                .map(to_toml_value)
+                .collect::<Result<Vec<toml::Value>, String>>()?,
+        ),
+        plist::Value::Boolean(b) => toml::Value::Boolean(*b),
+        plist::Value::String(s) => toml::Value::String(s.clone()),
+        plist::Value::Integer(i) => i
+            .as_signed()
+            .map(toml::Value::Integer)
+            .ok_or_else(|| format!("{} is too large for TOML", i))?,
+        plist::Value::Real(r) => toml::Value::Float(*r),
+        plist::Value::Date(d) => toml::Value::Datetime(
+            date_to_string(d)
+                .parse()
+                .map_err(|e| format!("Cannot export date: {}", e))?,
+        ),
+        plist::Value::Data(d) => {
+            let mut table = toml::map::Map::new();
+            table.insert(DATA_KEY.to_string(), toml::Value::String(base64::encode(d)));
+            toml::Value::Table(table)
+        }
+        _ => return Err("UIDs cannot be exported".to_string()),
+    };
+
+    Ok(value)
+}
+
+fn from_toml_value(value: toml::Value) -> Result<plist::Value, String> {
+    let value = match value {
+        toml::Value::Boolean(b) => plist::Value::Boolean(b),
+        toml::Value::String(s) => plist::Value::String(s),
+        toml::Value::Integer(i) => plist::Value::from(i),
+        toml::Value::Float(f) => plist::Value::Real(f),
+        toml::Value::Datetime(d) => plist::Value::Date(date_from_string(&d.to_string())?),
+        toml::Value::Array(a) => plist::Value::Array(
+            a.into_iter()
+                .map(from_toml_value)
+                .collect::<Result<Vec<plist::Value>, String>>()?,
+        ),
+        toml::Value::Table(t) => match (t.len(), t.get(DATA_KEY)) {
+            (1, Some(toml::Value::String(s))) => data_from_string(s)?,
+            _ => plist::Value::Dictionary(
+                t.into_iter()
+                    .map(|(k, v)| from_toml_value(v).map(|v| (k, v)))
+                    .collect::<Result<plist::Dictionary, String>>()?,
+            ),
+        },
+    };
+
+    Ok(value)
+}
+
+/// Text for a plist in the given format, keeping key order
+pub fn export_plist(plist: &plist::Value, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&to_json_value(plist)?)
+            .map(|s| s + "\n")
+            .map_err(|e| e.to_string()),
+        ExportFormat::Yaml => {
+            serde_yaml::to_string(&to_json_value(plist)?).map_err(|e| e.to_string())
+        }
+        ExportFormat::Toml => {
+            if plist.as_dictionary().is_none() {
+                return Err("Only dictionaries can be exported to TOML".to_string());
+            }
+
+            toml::to_string_pretty(&to_toml_value(plist)?).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Parse text written by export_plist (or by hand) back into a plist
+pub fn import_plist(text: &str, format: ExportFormat) -> Result<plist::Value, String> {
+    match format {
+        ExportFormat::Json => serde_json::from_str(text)
+            .map_err(|e| e.to_string())
+            .and_then(from_json_value),
+        ExportFormat::Yaml => serde_yaml::from_str(text)
+            .map_err(|e| e.to_string())
+            .and_then(from_json_value),
+        ExportFormat::Toml => text
+            .parse::<toml::Value>()
+            .map_err(|e| e.to_string())
+            .and_then(from_toml_value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_plist, import_plist, ExportFormat, EXPORT_FORMATS};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn job() -> plist::Value {
+        let mut calendar = plist::Dictionary::new();
+        calendar.insert("Hour".to_string(), plist::Value::from(3));
+        calendar.insert("Minute".to_string(), plist::Value::from(0));
+
+        let mut job = plist::Dictionary::new();
+        job.insert("Label".to_string(), plist::Value::from("com.example.job"));
+        job.insert(
+            "ProgramArguments".to_string(),
+            plist::Value::Array(vec!["/bin/echo".into(), "hi".into()]),
+        );
+        job.insert("RunAtLoad".to_string(), plist::Value::Boolean(true));
+        job.insert("Nice".to_string(), plist::Value::from(-5));
+        job.insert("Ratio".to_string(), plist::Value::Real(2.0));
+        job.insert(
+            "Since".to_string(),
+            plist::Value::Date((UNIX_EPOCH + Duration::from_secs(1_600_000_000)).into()),
+        );
+        job.insert("Blob".to_string(), plist::Value::Data(vec![0, 1, 254, 255]));
+        job.insert(
+            "StartCalendarInterval".to_string(),
+            plist::Value::Dictionary(calendar),
+        );
+
+        plist::Value::Dictionary(job)
+    }
+
+    #[test]
+    fn export_round_trip() {
+        for format in EXPORT_FORMATS.iter() {
+            let text = export_plist(&job(), *format).unwrap();
+            assert_eq!(import_plist(&text, *format).unwrap(), job(), "{}", format);
+        }
+    }
+
+    #[test]
+    fn export_json_shape() {
+        let json = export_plist(&job(), ExportFormat::Json).unwrap();
+
+        assert!(json.starts_with("{\n  \"Label\": \"com.example.job\""));
+        assert!(json.contains("\"Ratio\": 2.0"));
+        assert!(json.contains("\"Since\": {\n    \"$date\": \"2020-09-13T12:26:40Z\"\n  }"));
+        assert!(json.contains("\"$data\": \"AAH+/w==\""));
+    }
+
+    #[test]
+    fn export_toml_dates() {
+        let toml = export_plist(&job(), ExportFormat::Toml).unwrap();
+        assert!(toml.contains("Since = 2020-09-13T12:26:40Z"));
+
+        // Local dates have no offset, so no single point in time
+        assert!(import_plist("Since = 2020-09-13T12:26:40", ExportFormat::Toml).is_err());
+    }
+
+    #[test]
+    fn export_format_from_path() {
+        assert_eq!(
+            ExportFormat::from_path("a/job.YML"),
+            Some(ExportFormat::Yaml)
+        );
+        assert_eq!(
+            ExportFormat::from_path("job.toml"),
+            Some(ExportFormat::Toml)
+        );
+        assert_eq!(ExportFormat::from_path("job.plist"), None);
+        assert!(import_plist("{\"a\": null}", ExportFormat::Json).is_err());
+    }
+}
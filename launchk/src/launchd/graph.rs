@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::launchd::plist::read_plist;
+
+/// Something a job provides, watches or runs, that other jobs may share
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Resource {
+    MachService(String),
+    /// SockPathName, or [SockNodeName:]SockServiceName
+    Socket(String),
+    WatchPath(String),
+    QueueDirectory(String),
+    /// Program, or ProgramArguments[0]
+    Program(String),
+}
+
+impl Resource {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Resource::MachService(_) => "MachService",
+            Resource::Socket(_) => "Socket",
+            Resource::WatchPath(_) => "WatchPath",
+            Resource::QueueDirectory(_) => "QueueDirectory",
+            Resource::Program(_) => "Program",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Resource::MachService(n)
+            | Resource::Socket(n)
+            | Resource::WatchPath(n)
+            | Resource::QueueDirectory(n)
+            | Resource::Program(n) => n,
+        }
+    }
+
+    /// How a job relates to the resource
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Resource::MachService(_) | Resource::Socket(_) => "provides",
+            Resource::WatchPath(_) | Resource::QueueDirectory(_) => "watches",
+            Resource::Program(_) => "runs",
+        }
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind(), self.name())
+    }
+}
+
+fn strings(value: Option<&plist::Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_string())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// One Sockets entry is a dictionary, or an array of them
+fn sockets(value: &plist::Value) -> Vec<String> {
+    let dicts = match value {
+        plist::Value::Dictionary(d) => vec![d],
+        plist::Value::Array(a) => a.iter().filter_map(|v| v.as_dictionary()).collect(),
+        _ => vec![],
+    };
+
+    let get = |d: &plist::Dictionary, k: &str| {
+        d.get(k).and_then(|v| {
+            v.as_string()
+                .map(|s| s.to_string())
+                .or_else(|| v.as_signed_integer().map(|i| i.to_string()))
+        })
+    };
+
+    dicts
+        .into_iter()
+        .filter_map(
+            |d| match (get(d, "SockPathName"), get(d, "SockServiceName")) {
+                (Some(path), _) => Some(path),
+                (None, Some(service)) => Some(match get(d, "SockNodeName") {
+                    Some(node) => format!("{}:{}", node, service),
+                    None => service,
+                }),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Everything in a job plist that can link it to other jobs
+pub fn resources(plist: &plist::Value) -> BTreeSet<Resource> {
+    let dict = match plist.as_dictionary() {
+        Some(d) => d,
+        None => return BTreeSet::new(),
+    };
+
+    let mut found = BTreeSet::new();
+
+    if let Some(services) = dict.get("MachServices").and_then(|v| v.as_dictionary()) {
+        found.extend(services.keys().cloned().map(Resource::MachService));
+    }
+
+    if let Some(socks) = dict.get("Sockets").and_then(|v| v.as_dictionary()) {
+        found.extend(socks.values().flat_map(sockets).map(Resource::Socket));
+    }
+
+    found.extend(
+        strings(dict.get("WatchPaths"))
+            .into_iter()
+            .map(Resource::WatchPath),
+    );
+    found.extend(
+        strings(dict.get("QueueDirectories"))
+            .into_iter()
+            .map(Resource::QueueDirectory),
+    );
+
+    let program = dict
+        .get("Program")
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string())
+        .or_else(|| strings(dict.get("ProgramArguments")).into_iter().next());
+
+    found.extend(program.map(Resource::Program));
+
+    found
+}
+
+/// Jobs and the resources they share, both ways round
+#[derive(Debug, Clone, Default)]
+pub struct JobGraph {
+    jobs_by_resource: BTreeMap<Resource, BTreeSet<String>>,
+    resources_by_job: BTreeMap<String, BTreeSet<Resource>>,
+}
+
+impl JobGraph {
+    pub fn add_job<S: Into<String>>(&mut self, label: S, plist: &plist::Value) {
+        let label = label.into();
+        let found = resources(plist);
+
+        for resource in found.iter() {
+            self.jobs_by_resource
+                .entry(resource.clone())
+                .or_default()
+                .insert(label.clone());
+        }
+
+        self.resources_by_job.insert(label, found);
+    }
+
+    /// Read each (label, plist path), skipping ones that no longer parse.
+    /// Blocks on every file, so build it off the UI thread.
+    pub fn from_plists(plists: &[(String, String)]) -> Self {
+        let mut graph = Self::default();
+
+        for (label, path) in plists {
+            if let Ok((plist, _)) = read_plist(path) {
+                graph.add_job(label.as_str(), &plist);
+            }
+        }
+
+        graph
+    }
+
+    pub fn contains_job(&self, label: &str) -> bool {
+        self.resources_by_job.contains_key(label)
+    }
+
+    /// Each of the job's resources, with the other jobs sharing it
+    pub fn related(&self, label: &str) -> Vec<(&Resource, Vec<&str>)> {
+        self.resources_by_job
+            .get(label)
+            .into_iter()
+            .flatten()
+            .map(|r| {
+                let others = self.jobs_by_resource[r]
+                    .iter()
+                    .map(|l| l.as_str())
+                    .filter(|l| *l != label)
+                    .collect();
+
+                (r, others)
+            })
+            .collect()
+    }
+
+    /// Graphviz digraph of jobs (boxes) pointing at resources (ellipses).
+    /// With focus, only that job, its resources and their other jobs.
+    pub fn to_dot(&self, focus: Option<&str>) -> String {
+        let jobs: BTreeSet<&str> = match focus {
+            Some(label) => self
+                .related(label)
+                .into_iter()
+                .flat_map(|(_, others)| others)
+                .chain(Some(label).filter(|l| self.contains_job(l)))
+                .collect(),
+            None => self.resources_by_job.keys().map(|l| l.as_str()).collect(),
+        };
+
+        let mut dot = String::from("digraph launchk {\n    rankdir=LR;\n    node [shape=box];\n");
+
+        for job in jobs.iter() {
+            dot.push_str(&format!("    {};\n", dot_id(job)));
+        }
+
+        let resources: BTreeSet<&Resource> = jobs
+            .iter()
+            .flat_map(|j| self.resources_by_job[*j].iter())
+            .filter(|r| match focus {
+                Some(label) => self.jobs_by_resource[*r].contains(label),
+                None => true,
+            })
+            .collect();
+
+        for resource in resources.iter() {
+            dot.push_str(&format!(
+                "    {} [shape=ellipse];\n",
+                dot_id(&resource.to_string())
+            ));
+        }
+
+        for job in jobs.iter() {
+            for resource in self.resources_by_job[*job]
+                .iter()
+                .filter(|r| resources.contains(r))
+            {
+                dot.push_str(&format!(
+                    "    {} -> {} [label={}];\n",
+                    dot_id(job),
+                    dot_id(&resource.to_string()),
+                    dot_id(resource.verb())
+                ));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Quoted DOT identifier
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resources, JobGraph, Resource};
+    use crate::launchd::test_util::job;
+
+    fn graph() -> JobGraph {
+        let mut graph = JobGraph::default();
+        graph.add_job(
+            "com.example.server",
+            &job("<key>Program</key><string>/usr/libexec/server</string>\
+                  <key>MachServices</key><dict><key>com.example.xpc</key><true/></dict>\
+                  <key>WatchPaths</key><array><string>/etc/example.conf</string></array>"),
+        );
+        graph.add_job(
+            "com.example.watcher",
+            &job(
+                "<key>ProgramArguments</key><array><string>/usr/libexec/server</string>\
+                  <string>--watch</string></array>\
+                  <key>WatchPaths</key><array><string>/etc/example.conf</string></array>",
+            ),
+        );
+        graph.add_job(
+            "com.example.alone",
+            &job("<key>Program</key><string>/bin/true</string>"),
+        );
+        graph
+    }
+
+    #[test]
+    fn graph_resources() {
+        let found = resources(&job("<key>Program</key><string>/bin/sshd</string>\
+             <key>ProgramArguments</key><array><string>sshd</string></array>\
+             <key>Sockets</key><dict>\
+               <key>Listeners</key><dict><key>SockServiceName</key><string>ssh</string></dict>\
+               <key>Local</key><array>\
+                 <dict><key>SockPathName</key><string>/var/run/a.sock</string></dict>\
+                 <dict><key>SockNodeName</key><string>localhost</string>\
+                       <key>SockServiceName</key><integer>8080</integer></dict>\
+               </array>\
+             </dict>\
+             <key>QueueDirectories</key><array><string>/var/spool/q</string></array>"));
+
+        assert_eq!(
+            found.into_iter().collect::<Vec<Resource>>(),
+            vec![
+                Resource::Socket("/var/run/a.sock".to_string()),
+                Resource::Socket("localhost:8080".to_string()),
+                Resource::Socket("ssh".to_string()),
+                Resource::QueueDirectory("/var/spool/q".to_string()),
+                Resource::Program("/bin/sshd".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_related() {
+        let graph = graph();
+        let related: Vec<(String, Vec<&str>)> = graph
+            .related("com.example.server")
+            .into_iter()
+            .map(|(r, others)| (r.to_string(), others))
+            .collect();
+
+        assert_eq!(
+            related,
+            vec![
+                ("MachService com.example.xpc".to_string(), vec![]),
+                (
+                    "WatchPath /etc/example.conf".to_string(),
+                    vec!["com.example.watcher"]
+                ),
+                (
+                    "Program /usr/libexec/server".to_string(),
+                    vec!["com.example.watcher"]
+                ),
+            ]
+        );
+        assert!(graph.related("com.example.missing").is_empty());
+
+        // Plists that can't be read are left out
+        let read = JobGraph::from_plists(&[(
+            "com.example.gone".to_string(),
+            "/nonexistent/com.example.gone.plist".to_string(),
+        )]);
+        assert!(!read.contains_job("com.example.gone"));
+    }
+
+    #[test]
+    fn graph_dot() {
+        let graph = graph();
+        let dot = graph.to_dot(Some("com.example.watcher"));
+
+        assert!(dot.starts_with("digraph launchk {\n"));
+        assert!(dot.contains(
+            "    \"com.example.watcher\" -> \"WatchPath /etc/example.conf\" [label=\"watches\"];\n"
+        ));
+        assert!(dot.contains(
+            "    \"com.example.server\" -> \"Program /usr/libexec/server\" [label=\"runs\"];\n"
+        ));
+        // Not shared with the focused job
+        assert!(!dot.contains("MachService"));
+        assert!(!dot.contains("com.example.alone"));
+
+        let all = graph.to_dot(None);
+        assert!(all.contains("\"com.example.alone\" -> \"Program /bin/true\""));
+        assert!(all.contains("\"MachService com.example.xpc\" [shape=ellipse];"));
+    }
+}
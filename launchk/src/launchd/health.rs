@@ -0,0 +1,246 @@
+use std::env;
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// Where launchd looks for a ProgramArguments[0] without a slash,
+/// unless the job sets PATH in EnvironmentVariables
+static LAUNCHD_PATH: &str = "/usr/bin:/bin:/usr/sbin:/sbin";
+
+/// Something in a job plist that will keep the job from running as intended
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum HealthProblem {
+    Unreadable(String),
+    NoProgram,
+    MissingProgram(String),
+    NotExecutable(String),
+    MissingWorkingDirectory(String),
+    /// (key, path whose directory is missing)
+    MissingLogDirectory(&'static str, String),
+    MissingWatchPath(String),
+}
+
+impl fmt::Display for HealthProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthProblem::Unreadable(e) => write!(f, "Cannot read plist: {}", e),
+            HealthProblem::NoProgram => write!(f, "Neither Program nor ProgramArguments is set"),
+            HealthProblem::MissingProgram(p) => write!(f, "Program {} does not exist", p),
+            HealthProblem::NotExecutable(p) => write!(f, "Program {} is not executable", p),
+            HealthProblem::MissingWorkingDirectory(p) => {
+                write!(f, "WorkingDirectory {} does not exist", p)
+            }
+            HealthProblem::MissingLogDirectory(key, p) => {
+                write!(f, "{} {} is in a directory that does not exist", key, p)
+            }
+            HealthProblem::MissingWatchPath(p) => write!(f, "WatchPaths {} does not exist", p),
+        }
+    }
+}
+
+/// Exists as far as we can tell: paths we are not allowed to
+/// look at are given the benefit of the doubt
+fn missing(path: &Path) -> bool {
+    match path.metadata() {
+        Ok(_) => false,
+        Err(e) => e.kind() == io::ErrorKind::NotFound,
+    }
+}
+
+fn is_dir(path: &Path) -> bool {
+    match path.metadata() {
+        Ok(meta) => meta.is_dir(),
+        Err(e) => e.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+fn executable(path: &Path) -> bool {
+    path.is_file()
+        && CString::new(path.as_os_str().as_bytes())
+            .map(|c| unsafe { libc::access(c.as_ptr(), libc::X_OK) } == 0)
+            .unwrap_or(false)
+}
+
+/// Resolve a bare program name the way execvp would
+fn find_program(program: &str, search_path: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(PathBuf::from(program));
+    }
+
+    env::split_paths(search_path)
+        .map(|dir| dir.join(program))
+        .find(|p| p.is_file())
+}
+
+fn check_program(dict: &plist::Dictionary) -> Option<HealthProblem> {
+    let program = dict.get("Program").and_then(|v| v.as_string()).or_else(|| {
+        dict.get("ProgramArguments")
+            .and_then(|v| v.as_array())
+            .and_then(|a| a.first())
+            .and_then(|v| v.as_string())
+    });
+
+    let program = match program {
+        Some(program) => program,
+        None => return Some(HealthProblem::NoProgram),
+    };
+
+    let search_path = dict
+        .get("EnvironmentVariables")
+        .and_then(|v| v.as_dictionary())
+        .and_then(|env| env.get("PATH"))
+        .and_then(|v| v.as_string())
+        .unwrap_or(LAUNCHD_PATH);
+
+    match find_program(program, search_path) {
+        Some(path) if missing(&path) => Some(HealthProblem::MissingProgram(program.to_string())),
+        Some(path) if path.metadata().is_ok() && !executable(&path) => {
+            Some(HealthProblem::NotExecutable(program.to_string()))
+        }
+        Some(_) => None,
+        None => Some(HealthProblem::MissingProgram(program.to_string())),
+    }
+}
+
+/// Problems with the files and directories a job plist refers to
+pub fn check_plist(plist: &plist::Value) -> Vec<HealthProblem> {
+    let dict = match plist.as_dictionary() {
+        Some(d) => d,
+        None => return vec![HealthProblem::Unreadable("not a dictionary".to_string())],
+    };
+
+    let string = |key: &str| dict.get(key).and_then(|v| v.as_string());
+    let mut problems: Vec<HealthProblem> = check_program(dict).into_iter().collect();
+
+    if let Some(dir) = string("WorkingDirectory") {
+        if !is_dir(Path::new(dir)) {
+            problems.push(HealthProblem::MissingWorkingDirectory(dir.to_string()));
+        }
+    }
+
+    // launchd creates the log files, but not their directories
+    for key in &["StandardOutPath", "StandardErrorPath"] {
+        let dir_missing = string(key)
+            .and_then(|p| Path::new(p).parent())
+            .map(|dir| !dir.as_os_str().is_empty() && !is_dir(dir))
+            .unwrap_or(false);
+
+        if dir_missing {
+            problems.push(HealthProblem::MissingLogDirectory(
+                key,
+                string(key).unwrap_or_default().to_string(),
+            ));
+        }
+    }
+
+    let watch_paths = dict
+        .get("WatchPaths")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_string());
+
+    for path in watch_paths {
+        if missing(Path::new(path)) {
+            problems.push(HealthProblem::MissingWatchPath(path.to_string()));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_plist, HealthProblem};
+    use crate::launchd::test_util::{job, test_dir};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn health_program() {
+        let dir = test_dir("health-program");
+        let tool = dir.join("tool");
+        let data = dir.join("data");
+        fs::write(&tool, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(&data, "").unwrap();
+        fs::set_permissions(&data, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let program =
+            |p: &str| check_plist(&job(&format!("<key>Program</key><string>{}</string>", p)));
+
+        assert!(program(&tool.to_string_lossy()).is_empty());
+        assert_eq!(
+            program(&data.to_string_lossy()),
+            vec![HealthProblem::NotExecutable(
+                data.to_string_lossy().to_string()
+            )]
+        );
+        assert_eq!(
+            program("/Applications/Gone.app/Contents/MacOS/helper"),
+            vec![HealthProblem::MissingProgram(
+                "/Applications/Gone.app/Contents/MacOS/helper".to_string()
+            )]
+        );
+        assert_eq!(check_plist(&job("")), vec![HealthProblem::NoProgram]);
+
+        // Bare names are looked up in the job's PATH
+        let args = |path: &str| {
+            check_plist(&job(&format!(
+                "<key>ProgramArguments</key><array><string>tool</string><string>-v</string></array>\
+                 <key>EnvironmentVariables</key><dict><key>PATH</key><string>{}</string></dict>",
+                path
+            )))
+        };
+        assert!(args(&format!("/nonexistent:{}", dir.display())).is_empty());
+        assert_eq!(
+            args("/nonexistent"),
+            vec![HealthProblem::MissingProgram("tool".to_string())]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn health_paths() {
+        let dir = test_dir("health-paths");
+        let dir_s = dir.to_string_lossy();
+
+        let problems = check_plist(&job(&format!(
+            "<key>Program</key><string>/bin/sh</string>\
+             <key>WorkingDirectory</key><string>{dir}/gone</string>\
+             <key>StandardOutPath</key><string>{dir}/out.log</string>\
+             <key>StandardErrorPath</key><string>{dir}/logs/err.log</string>\
+             <key>WatchPaths</key><array><string>{dir}</string><string>{dir}/missing.conf</string></array>",
+            dir = dir_s
+        )));
+
+        assert_eq!(
+            problems,
+            vec![
+                HealthProblem::MissingWorkingDirectory(format!("{}/gone", dir_s)),
+                HealthProblem::MissingLogDirectory(
+                    "StandardErrorPath",
+                    format!("{}/logs/err.log", dir_s)
+                ),
+                HealthProblem::MissingWatchPath(format!("{}/missing.conf", dir_s)),
+            ]
+        );
+
+        fs::create_dir(dir.join("logs")).unwrap();
+        fs::create_dir(dir.join("gone")).unwrap();
+        fs::write(dir.join("missing.conf"), "").unwrap();
+        assert!(check_plist(&job(&format!(
+            "<key>Program</key><string>/bin/sh</string>\
+             <key>WorkingDirectory</key><string>{dir}/gone</string>\
+             <key>StandardErrorPath</key><string>{dir}/logs/err.log</string>\
+             <key>WatchPaths</key><array><string>{dir}/missing.conf</string></array>",
+            dir = dir_s
+        )))
+        .is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
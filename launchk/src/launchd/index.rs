@@ -0,0 +1,311 @@
+use std::collections::hash_map::Keys;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use notify::DebouncedEvent;
+
+use crate::launchd::plist::{build_label_map_entry, readdir_filter_plists, LaunchdPlist};
+use crate::launchd::search_dirs::SearchDir;
+
+/// Job plists by label, kept up to date one file at a time from fsnotify.
+/// Also tracks which label every plist path was indexed under, so deleted
+/// files lose their label and relabelled ones move to the new label.
+/// Every file claiming a label is kept, the last one written wins, and
+/// removing it falls back to the one before.
+#[derive(Debug, Default)]
+pub struct PlistIndex {
+    by_label: HashMap<String, Vec<LaunchdPlist>>,
+    by_path: HashMap<String, String>,
+}
+
+/// Plists count if they are directly inside a search dir
+pub fn search_dir_holding<'a>(dirs: &'a [SearchDir], path: &Path) -> Option<&'a SearchDir> {
+    let parent = path.parent()?;
+    dirs.iter().find(|d| Path::new(&d.path) == parent)
+}
+
+impl PlistIndex {
+    /// Index every plist in the (resolved) search dirs
+    pub fn scan(dirs: &[SearchDir]) -> Self {
+        let mut index = Self::default();
+        dirs.iter().for_each(|dir| index.insert_dir(dir));
+        index
+    }
+
+    fn insert_dir(&mut self, dir: &SearchDir) {
+        let plists = match fs::read_dir(Path::new(&dir.path)) {
+            Ok(rd) => readdir_filter_plists(rd),
+            Err(_) => return,
+        };
+
+        for plist in plists {
+            if let Some((label, entry)) = build_label_map_entry(dir, &plist.path()) {
+                self.insert(label, entry);
+            }
+        }
+    }
+
+    pub fn get(&self, label: &str) -> Option<&LaunchdPlist> {
+        self.by_label
+            .get(label)
+            .and_then(|candidates| candidates.last())
+    }
+
+    pub fn contains_key(&self, label: &str) -> bool {
+        self.by_label.contains_key(label)
+    }
+
+    pub fn keys(&self) -> Keys<'_, String, Vec<LaunchdPlist>> {
+        self.by_label.keys()
+    }
+
+    /// (label, plist path) of every job, to read without holding the index
+    pub fn plist_paths(&self) -> Vec<(String, String)> {
+        self.by_label
+            .keys()
+            .filter_map(|label| Some((label.clone(), self.get(label)?.plist_path.clone())))
+            .collect()
+    }
+
+    /// Add or replace a plist, dropping the label its path had before.
+    /// It wins the label over any other file claiming it.
+    pub fn insert(&mut self, label: String, entry: LaunchdPlist) {
+        let path = entry.plist_path.clone();
+
+        if let Some(old_label) = self.by_path.insert(path.clone(), label.clone()) {
+            self.remove_candidate(&old_label, &path);
+        }
+
+        self.by_label.entry(label).or_default().push(entry);
+    }
+
+    fn remove_path(&mut self, path: &str) {
+        if let Some(label) = self.by_path.remove(path) {
+            self.remove_candidate(&label, path);
+        }
+    }
+
+    /// Drop path from the files claiming label, and the label with the last
+    fn remove_candidate(&mut self, label: &str, path: &str) {
+        if let Some(candidates) = self.by_label.get_mut(label) {
+            candidates.retain(|e| e.plist_path != path);
+
+            if candidates.is_empty() {
+                self.by_label.remove(label);
+            }
+        }
+    }
+
+    /// Forget path and anything indexed below it (a removed directory)
+    fn remove_tree(&mut self, path: &Path) {
+        let removed: Vec<String> = self
+            .by_path
+            .keys()
+            .filter(|p| Path::new(p).starts_with(path))
+            .cloned()
+            .collect();
+
+        for p in removed {
+            self.remove_path(&p);
+        }
+    }
+
+    /// Re-read whatever is at path now: a plist file is (re)indexed
+    /// or dropped if it no longer parses, a directory has its plists
+    /// refreshed if it is a search dir, anything missing is removed
+    pub fn refresh(&mut self, dirs: &[SearchDir], path: &Path) {
+        if path.is_dir() {
+            self.remove_tree(path);
+
+            dirs.iter()
+                .filter(|d| Path::new(&d.path).starts_with(path))
+                .for_each(|dir| self.insert_dir(dir));
+
+            return;
+        }
+
+        let is_plist = path.extension().map(|e| e == "plist").unwrap_or(false);
+
+        let entry = search_dir_holding(dirs, path)
+            .filter(|_| is_plist && path.is_file())
+            .and_then(|dir| build_label_map_entry(dir, path));
+
+        self.update(path, entry);
+    }
+
+    /// Index the job at path, or forget path if it has none
+    pub fn update(&mut self, path: &Path, entry: Option<(String, LaunchdPlist)>) {
+        match entry {
+            Some((label, entry)) => self.insert(label, entry),
+            None => self.remove_tree(path),
+        }
+    }
+
+    /// Apply one debounced fsnotify event
+    pub fn apply(&mut self, dirs: &[SearchDir], event: &DebouncedEvent) {
+        match event {
+            DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Chmod(path)
+            | DebouncedEvent::Remove(path) => self.refresh(dirs, path),
+            DebouncedEvent::Rename(old, new) => {
+                self.remove_tree(old);
+                self.refresh(dirs, new);
+            }
+            DebouncedEvent::Rescan => *self = Self::scan(dirs),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PlistIndex;
+    use crate::launchd::test_util::{agents, test_dir, write_job};
+    use notify::DebouncedEvent;
+    use std::fs;
+
+    fn labels(index: &PlistIndex) -> Vec<String> {
+        let mut labels: Vec<String> = index.keys().cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn index_create_modify_delete() {
+        let dir = test_dir("index-files");
+        let dirs = agents(&dir);
+        let a = dir.join("a.plist");
+
+        let mut index = PlistIndex::scan(&dirs);
+        assert!(index.by_label.is_empty());
+
+        write_job(&a, "com.example.a");
+        index.apply(&dirs, &DebouncedEvent::Create(a.clone()));
+        assert_eq!(labels(&index), vec!["com.example.a"]);
+
+        // Label changed in place: re-keyed, old label gone
+        write_job(&a, "com.example.renamed");
+        index.apply(&dirs, &DebouncedEvent::Write(a.clone()));
+        assert_eq!(labels(&index), vec!["com.example.renamed"]);
+        assert_eq!(
+            index
+                .get("com.example.renamed")
+                .map(|p| p.plist_path.clone()),
+            Some(a.to_string_lossy().to_string())
+        );
+
+        // No longer parses: dropped
+        fs::write(&a, "garbage").unwrap();
+        index.apply(&dirs, &DebouncedEvent::Write(a.clone()));
+        assert!(index.by_label.is_empty());
+
+        write_job(&a, "com.example.a");
+        index.apply(&dirs, &DebouncedEvent::Write(a.clone()));
+        fs::remove_file(&a).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Remove(a.clone()));
+        assert!(index.by_label.is_empty());
+        assert_eq!(index.by_path.get(&*a.to_string_lossy()), None);
+
+        // Not a plist, or not directly in a search dir
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        write_job(&dir.join("sub/b.plist"), "com.example.b");
+        index.apply(&dirs, &DebouncedEvent::Create(dir.join("notes.txt")));
+        index.apply(&dirs, &DebouncedEvent::Create(dir.join("sub/b.plist")));
+        assert!(index.by_label.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn index_rename() {
+        let dir = test_dir("index-rename");
+        let outside = test_dir("index-rename-outside");
+        let dirs = agents(&dir);
+        let a = dir.join("a.plist");
+        let b = dir.join("b.plist");
+
+        write_job(&a, "com.example.a");
+        let mut index = PlistIndex::scan(&dirs);
+        assert_eq!(labels(&index), vec!["com.example.a"]);
+
+        fs::rename(&a, &b).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Rename(a.clone(), b.clone()));
+        assert_eq!(
+            index.get("com.example.a").map(|p| p.plist_path.clone()),
+            Some(b.to_string_lossy().to_string())
+        );
+        assert_eq!(index.by_path.get(&*a.to_string_lossy()), None);
+
+        // Moved out of the search dir
+        let moved = outside.join("b.plist");
+        fs::rename(&b, &moved).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Rename(b.clone(), moved));
+        assert!(index.by_label.is_empty());
+
+        // Editors that save by renaming a temp file over the plist
+        let temp = dir.join(".a.plist.swp");
+        write_job(&a, "com.example.a");
+        index.apply(&dirs, &DebouncedEvent::Create(a.clone()));
+        write_job(&temp, "com.example.saved");
+        fs::rename(&temp, &a).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Rename(temp, a.clone()));
+        assert_eq!(labels(&index), vec!["com.example.saved"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+
+    #[test]
+    fn index_shared_labels_and_dirs() {
+        let root = test_dir("index-dirs");
+        let dir = root.join("LaunchAgents");
+        fs::create_dir(&dir).unwrap();
+        let dirs = agents(&dir);
+        let a = dir.join("a.plist");
+        let b = dir.join("b.plist");
+
+        // Two files claiming one label: removing the loser keeps the winner
+        write_job(&a, "com.example.same");
+        write_job(&b, "com.example.same");
+        let mut index = PlistIndex::scan(&dirs);
+        index.apply(&dirs, &DebouncedEvent::Write(b.clone()));
+        fs::remove_file(&a).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Remove(a.clone()));
+        assert_eq!(
+            index.get("com.example.same").map(|p| p.plist_path.clone()),
+            Some(b.to_string_lossy().to_string())
+        );
+
+        // Removing the winner falls back to the other file
+        write_job(&a, "com.example.same");
+        index.apply(&dirs, &DebouncedEvent::Create(a.clone()));
+        assert_eq!(
+            index.get("com.example.same").map(|p| p.plist_path.clone()),
+            Some(a.to_string_lossy().to_string())
+        );
+        fs::remove_file(&a).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Remove(a));
+        assert_eq!(
+            index.get("com.example.same").map(|p| p.plist_path.clone()),
+            Some(b.to_string_lossy().to_string())
+        );
+
+        // Whole search dir removed, then recreated
+        fs::remove_dir_all(&dir).unwrap();
+        index.apply(&dirs, &DebouncedEvent::Remove(dir.clone()));
+        assert!(index.by_label.is_empty());
+
+        fs::create_dir(&dir).unwrap();
+        write_job(&b, "com.example.b");
+        index.apply(&dirs, &DebouncedEvent::Create(dir.clone()));
+        assert_eq!(labels(&index), vec!["com.example.b"]);
+
+        index.apply(&dirs, &DebouncedEvent::Rescan);
+        assert_eq!(labels(&index), vec!["com.example.b"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use crate::launchd::atomic_write::write_atomic;
+use crate::launchd::calendar::CalendarSchedule;
+use crate::launchd::index::{search_dir_holding, PlistIndex};
+use crate::launchd::plist::{read_label_and_schedule, readdir_filter_plists, LaunchdPlist};
+use crate::launchd::privileged::can_write;
+use crate::launchd::search_dirs::SearchDir;
+
+/// Bump when CachedPlist changes, older caches are then ignored
+const CACHE_VERSION: u32 = 1;
+
+lazy_static! {
+    pub static ref INDEX_CACHE_PATH: PathBuf =
+        Path::new(&env::var("HOME").expect("Must read $HOME"))
+            .join("Library/Caches/launchk/index.json");
+}
+
+/// Enough of a file's metadata to tell it changed without reading it
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        Some(Self {
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: meta.len(),
+        })
+    }
+}
+
+/// A plist as it was when last parsed. Files without a label are kept
+/// too (label None), so they are not parsed again every startup.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct CachedPlist {
+    stamp: Stamp,
+    readonly: bool,
+    label: Option<String>,
+    schedule: Option<CalendarSchedule>,
+}
+
+impl CachedPlist {
+    fn entry(&self, dir: &SearchDir, path: &str) -> Option<(String, LaunchdPlist)> {
+        let label = self.label.clone()?;
+
+        Some((
+            label,
+            LaunchdPlist {
+                entry_location: dir.scope.clone(),
+                entry_type: dir.entry_type.clone(),
+                plist_path: path.to_string(),
+                readonly: self.readonly,
+                schedule: self.schedule.clone(),
+            },
+        ))
+    }
+}
+
+/// The label index from the last run, keyed by plist path
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IndexCache {
+    version: u32,
+    plists: HashMap<String, CachedPlist>,
+}
+
+impl IndexCache {
+    /// Empty if there is no usable cache
+    pub fn load(path: &Path) -> Self {
+        let cache = fs::read(path).map_err(|e| e.to_string()).and_then(|bytes| {
+            serde_json::from_slice::<IndexCache>(&bytes).map_err(|e| e.to_string())
+        });
+
+        match cache {
+            Ok(cache) if cache.version == CACHE_VERSION => cache,
+            Ok(_) => Self::default(),
+            Err(e) => {
+                log::info!("Not using index cache {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let dir = path
+            .parent()
+            .ok_or_else(|| format!("{} has no parent directory", path.display()))?;
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+        let cache = IndexCache {
+            version: CACHE_VERSION,
+            plists: self.plists.clone(),
+        };
+        let bytes = serde_json::to_vec(&cache).map_err(|e| e.to_string())?;
+
+        write_atomic(path, &bytes)
+    }
+
+    /// Index from the cache alone, without touching any plist
+    pub fn to_index(&self, dirs: &[SearchDir]) -> PlistIndex {
+        let mut index = PlistIndex::default();
+
+        for (path, cached) in self.plists.iter() {
+            let entry =
+                search_dir_holding(dirs, Path::new(path)).and_then(|dir| cached.entry(dir, path));
+
+            if let Some((label, entry)) = entry {
+                index.insert(label, entry);
+            }
+        }
+
+        index
+    }
+
+    /// Whether path is as it was when last refreshed (or still gone). If
+    /// not, it changed since and the watcher has the newer version.
+    pub fn is_current(&self, path: &Path) -> bool {
+        let cached = self
+            .plists
+            .get(path.to_string_lossy().as_ref())
+            .map(|c| c.stamp);
+
+        cached == Stamp::of(path)
+    }
+
+    /// Stat every plist in dirs and parse only new or changed ones.
+    /// Returns the paths whose job changed, None where it went away,
+    /// for PlistIndex::update.
+    pub fn refresh(
+        &mut self,
+        dirs: &[SearchDir],
+    ) -> Vec<(PathBuf, Option<(String, LaunchdPlist)>)> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut changed = vec![];
+
+        for dir in dirs {
+            let plists = match fs::read_dir(Path::new(&dir.path)) {
+                Ok(rd) => readdir_filter_plists(rd),
+                Err(_) => continue,
+            };
+
+            for plist in plists {
+                let path = plist.path();
+                let key = path.to_string_lossy().to_string();
+
+                let stamp = match Stamp::of(&path) {
+                    Some(stamp) => stamp,
+                    None => continue,
+                };
+                let readonly = !can_write(&path);
+
+                seen.insert(key.clone());
+
+                let unchanged = self
+                    .plists
+                    .get(&key)
+                    .map(|c| c.stamp == stamp && c.readonly == readonly)
+                    .unwrap_or(false);
+
+                if unchanged {
+                    continue;
+                }
+
+                let (label, schedule) = match read_label_and_schedule(&path) {
+                    Some((label, schedule)) => (Some(label), schedule),
+                    None => (None, None),
+                };
+
+                let cached = CachedPlist {
+                    stamp,
+                    readonly,
+                    label,
+                    schedule,
+                };
+
+                changed.push((path, cached.entry(dir, &key)));
+                self.plists.insert(key, cached);
+            }
+        }
+
+        let gone: Vec<String> = self
+            .plists
+            .keys()
+            .filter(|p| !seen.contains(*p))
+            .cloned()
+            .collect();
+
+        for path in gone {
+            self.plists.remove(&path);
+            changed.push((PathBuf::from(path), None));
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IndexCache, CACHE_VERSION};
+    use crate::launchd::test_util::{agents, test_dir, write_job};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn index_cache_refresh() {
+        let dir = test_dir("cache-refresh");
+        let dirs = agents(&dir);
+        let a = dir.join("a.plist");
+        let key = a.to_string_lossy().to_string();
+
+        write_job(&a, "com.example.a");
+        fs::write(dir.join("broken.plist"), "not a plist").unwrap();
+
+        let mut cache = IndexCache::default();
+        let changed = cache.refresh(&dirs);
+        assert_eq!(changed.len(), 2);
+        assert!(cache.to_index(&dirs).contains_key("com.example.a"));
+
+        // Nothing changed on disk: nothing parsed, even the broken one
+        assert!(cache.refresh(&dirs).is_empty());
+
+        // Same mtime and size: the cached label is trusted
+        cache.plists.get_mut(&key).unwrap().label = Some("com.example.cached".to_string());
+        assert!(cache.refresh(&dirs).is_empty());
+        assert!(cache.to_index(&dirs).contains_key("com.example.cached"));
+
+        write_job(&a, "com.example.a.changed");
+        let changed = cache.refresh(&dirs);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(
+            changed[0].1.as_ref().map(|(label, _)| label.as_str()),
+            Some("com.example.a.changed")
+        );
+
+        // Written again after it was read: the refresh is stale
+        assert!(cache.is_current(&a));
+        write_job(&a, "com.example.a.changed.again");
+        assert!(!cache.is_current(&a));
+        cache.refresh(&dirs);
+
+        fs::remove_file(&a).unwrap();
+        let changed = cache.refresh(&dirs);
+        assert_eq!(changed, vec![(a.clone(), None)]);
+        assert!(!cache.plists.contains_key(&key));
+        assert!(cache.is_current(&a));
+
+        // Entries outside the configured dirs are not indexed
+        write_job(&a, "com.example.a");
+        cache.refresh(&dirs);
+        assert!(cache
+            .to_index(&agents(Path::new("/elsewhere")))
+            .keys()
+            .next()
+            .is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn index_cache_save_load() {
+        let dir = test_dir("cache-save");
+        let dirs = agents(&dir);
+        let cache_path = dir.join("cache/index.json");
+
+        write_job(&dir.join("a.plist"), "com.example.a");
+
+        let mut cache = IndexCache::default();
+        cache.refresh(&dirs);
+        cache.save(&cache_path).unwrap();
+
+        let mut loaded = IndexCache::load(&cache_path);
+        assert_eq!(loaded.version, CACHE_VERSION);
+        assert_eq!(loaded.plists, cache.plists);
+        assert!(loaded.refresh(&dirs).is_empty());
+
+        fs::write(&cache_path, "{\"version\": 0, \"plists\": {}}").unwrap();
+        assert!(IndexCache::load(&cache_path).plists.is_empty());
+
+        fs::write(&cache_path, "garbage").unwrap();
+        assert!(IndexCache::load(&cache_path).plists.is_empty());
+        assert!(IndexCache::load(&dir.join("missing.json"))
+            .plists
+            .is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
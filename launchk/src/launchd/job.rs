@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+
+use crate::launchd::calendar::CalendarSchedule;
+
+/// When launchd restarts a job after it exits
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeepAlive {
+    Never,
+    Always,
+    /// { SuccessfulExit = false }: restart after a non-zero exit
+    OnFailure,
+    /// { SuccessfulExit = true }: restart after a zero exit
+    OnSuccess,
+    /// Any other conditions (PathState, NetworkState, ...), kept as-is
+    Conditions(plist::Dictionary),
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        KeepAlive::Never
+    }
+}
+
+/// A job plist with the keys launchk understands typed out,
+/// everything else is carried along untouched in `other`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchdJob {
+    pub label: String,
+    pub program: Option<String>,
+    pub program_arguments: Vec<String>,
+    pub working_directory: Option<String>,
+    pub environment_variables: BTreeMap<String, String>,
+    pub user_name: Option<String>,
+    pub group_name: Option<String>,
+    pub run_at_load: bool,
+    pub keep_alive: KeepAlive,
+    pub start_interval: Option<u64>,
+    pub start_calendar_interval: Option<CalendarSchedule>,
+    pub standard_out_path: Option<String>,
+    pub standard_error_path: Option<String>,
+    pub other: plist::Dictionary,
+}
+
+fn take_string(dict: &mut plist::Dictionary, key: &str) -> Result<Option<String>, String> {
+    match dict.remove(key) {
+        None => Ok(None),
+        Some(plist::Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(format!("{} must be a string", key)),
+    }
+}
+
+fn take_bool(dict: &mut plist::Dictionary, key: &str) -> Result<Option<bool>, String> {
+    match dict.remove(key) {
+        None => Ok(None),
+        Some(plist::Value::Boolean(b)) => Ok(Some(b)),
+        Some(_) => Err(format!("{} must be a boolean", key)),
+    }
+}
+
+fn take_strings(dict: &mut plist::Dictionary, key: &str) -> Result<Vec<String>, String> {
+    match dict.remove(key) {
+        None => Ok(vec![]),
+        Some(plist::Value::Array(a)) => a
+            .into_iter()
+            .map(|v| v.into_string())
+            .collect::<Option<Vec<String>>>()
+            .ok_or_else(|| format!("{} must only contain strings", key)),
+        Some(_) => Err(format!("{} must be an array", key)),
+    }
+}
+
+fn insert_string(dict: &mut plist::Dictionary, key: &str, value: &Option<String>) {
+    if let Some(v) = value {
+        dict.insert(key.to_string(), plist::Value::String(v.clone()));
+    }
+}
+
+impl LaunchdJob {
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        Self {
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_plist(plist: &plist::Value) -> Result<Self, String> {
+        let mut dict = plist
+            .as_dictionary()
+            .cloned()
+            .ok_or_else(|| "plist must be a dictionary".to_string())?;
+
+        let label = take_string(&mut dict, "Label")?.ok_or_else(|| "Missing Label".to_string())?;
+
+        let environment_variables = match dict.remove("EnvironmentVariables") {
+            None => BTreeMap::new(),
+            Some(plist::Value::Dictionary(d)) => d
+                .into_iter()
+                .map(|(k, v)| v.into_string().map(|v| (k, v)))
+                .collect::<Option<BTreeMap<String, String>>>()
+                .ok_or_else(|| "EnvironmentVariables must only contain strings".to_string())?,
+            Some(_) => return Err("EnvironmentVariables must be a dictionary".to_string()),
+        };
+
+        let keep_alive = match dict.remove("KeepAlive") {
+            None | Some(plist::Value::Boolean(false)) => KeepAlive::Never,
+            Some(plist::Value::Boolean(true)) => KeepAlive::Always,
+            Some(plist::Value::Dictionary(d)) => {
+                match (
+                    d.len(),
+                    d.get("SuccessfulExit").and_then(|v| v.as_boolean()),
+                ) {
+                    (1, Some(false)) => KeepAlive::OnFailure,
+                    (1, Some(true)) => KeepAlive::OnSuccess,
+                    _ => KeepAlive::Conditions(d),
+                }
+            }
+            Some(_) => return Err("KeepAlive must be a boolean or dictionary".to_string()),
+        };
+
+        let start_interval = match dict.remove("StartInterval") {
+            None => None,
+            Some(v) => Some(
+                v.as_unsigned_integer()
+                    .ok_or_else(|| "StartInterval must be a positive integer".to_string())?,
+            ),
+        };
+
+        let start_calendar_interval = dict
+            .remove("StartCalendarInterval")
+            .map(|v| CalendarSchedule::from_plist(&v))
+            .transpose()?;
+
+        Ok(Self {
+            label,
+            program: take_string(&mut dict, "Program")?,
+            program_arguments: take_strings(&mut dict, "ProgramArguments")?,
+            working_directory: take_string(&mut dict, "WorkingDirectory")?,
+            environment_variables,
+            user_name: take_string(&mut dict, "UserName")?,
+            group_name: take_string(&mut dict, "GroupName")?,
+            run_at_load: take_bool(&mut dict, "RunAtLoad")?.unwrap_or(false),
+            keep_alive,
+            start_interval,
+            start_calendar_interval,
+            standard_out_path: take_string(&mut dict, "StandardOutPath")?,
+            standard_error_path: take_string(&mut dict, "StandardErrorPath")?,
+            other: dict,
+        })
+    }
+
+    pub fn to_plist(&self) -> plist::Value {
+        let mut dict = plist::Dictionary::new();
+
+        dict.insert(
+            "Label".to_string(),
+            plist::Value::String(self.label.clone()),
+        );
+        insert_string(&mut dict, "Program", &self.program);
+
+        if !self.program_arguments.is_empty() {
+            dict.insert(
+                "ProgramArguments".to_string(),
+                plist::Value::Array(
+                    self.program_arguments
+                        .iter()
+                        .map(|a| plist::Value::String(a.clone()))
+                        .collect(),
+                ),
+            );
+        }
+
+        insert_string(&mut dict, "WorkingDirectory", &self.working_directory);
+
+        if !self.environment_variables.is_empty() {
+            dict.insert(
+                "EnvironmentVariables".to_string(),
+                plist::Value::Dictionary(
+                    self.environment_variables
+                        .iter()
+                        .map(|(k, v)| (k.clone(), plist::Value::String(v.clone())))
+                        .collect(),
+                ),
+            );
+        }
+
+        insert_string(&mut dict, "UserName", &self.user_name);
+        insert_string(&mut dict, "GroupName", &self.group_name);
+
+        if self.run_at_load {
+            dict.insert("RunAtLoad".to_string(), plist::Value::Boolean(true));
+        }
+
+        let successful_exit = |b: bool| {
+            let mut d = plist::Dictionary::new();
+            d.insert("SuccessfulExit".to_string(), plist::Value::Boolean(b));
+            plist::Value::Dictionary(d)
+        };
+
+        let keep_alive = match &self.keep_alive {
+            KeepAlive::Never => None,
+            KeepAlive::Always => Some(plist::Value::Boolean(true)),
+            KeepAlive::OnFailure => Some(successful_exit(false)),
+            KeepAlive::OnSuccess => Some(successful_exit(true)),
+            KeepAlive::Conditions(d) => Some(plist::Value::Dictionary(d.clone())),
+        };
+
+        if let Some(keep_alive) = keep_alive {
+            dict.insert("KeepAlive".to_string(), keep_alive);
+        }
+
+        if let Some(interval) = self.start_interval {
+            dict.insert("StartInterval".to_string(), plist::Value::from(interval));
+        }
+
+        if let Some(schedule) = &self.start_calendar_interval {
+            dict.insert("StartCalendarInterval".to_string(), schedule.to_plist());
+        }
+
+        insert_string(&mut dict, "StandardOutPath", &self.standard_out_path);
+        insert_string(&mut dict, "StandardErrorPath", &self.standard_error_path);
+
+        for (k, v) in self.other.iter() {
+            dict.insert(k.clone(), v.clone());
+        }
+
+        plist::Value::Dictionary(dict)
+    }
+
+    /// The executable and its full argv, like launchd does:
+    /// Program wins over ProgramArguments[0] when both are set
+    pub fn command(&self) -> Option<(String, Vec<String>)> {
+        match (&self.program, self.program_arguments.first()) {
+            (Some(p), Some(_)) => Some((p.clone(), self.program_arguments.clone())),
+            (Some(p), None) => Some((p.clone(), vec![p.clone()])),
+            (None, Some(p)) => Some((p.clone(), self.program_arguments.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeepAlive, LaunchdJob};
+    use crate::launchd::test_util::job;
+
+    fn keep_alive(xml: &str) -> KeepAlive {
+        LaunchdJob::from_plist(&job(&format!(
+            "<key>Label</key><string>a</string><key>KeepAlive</key>{}",
+            xml
+        )))
+        .unwrap()
+        .keep_alive
+    }
+
+    #[test]
+    fn job_keep_alive() {
+        assert_eq!(keep_alive("<false/>"), KeepAlive::Never);
+        assert_eq!(keep_alive("<true/>"), KeepAlive::Always);
+        assert_eq!(
+            keep_alive("<dict><key>SuccessfulExit</key><false/></dict>"),
+            KeepAlive::OnFailure
+        );
+        assert_eq!(
+            keep_alive("<dict><key>SuccessfulExit</key><true/></dict>"),
+            KeepAlive::OnSuccess
+        );
+
+        // Anything else is kept whole, even alongside SuccessfulExit
+        let conditions = "<dict><key>SuccessfulExit</key><false/><key>Crashed</key><true/></dict>";
+        match keep_alive(conditions) {
+            KeepAlive::Conditions(d) => {
+                assert_eq!(d.len(), 2);
+                assert_eq!(d.get("Crashed"), Some(&plist::Value::Boolean(true)));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn job_from_plist_errors() {
+        let invalid = [
+            "<key>Program</key><string>/bin/true</string>",
+            "<key>Label</key><integer>1</integer>",
+            "<key>Label</key><string>a</string><key>RunAtLoad</key><string>yes</string>",
+            "<key>Label</key><string>a</string><key>ProgramArguments</key><string>/bin/true</string>",
+            "<key>Label</key><string>a</string><key>ProgramArguments</key><array><integer>1</integer></array>",
+            "<key>Label</key><string>a</string><key>StartInterval</key><integer>-5</integer>",
+            "<key>Label</key><string>a</string><key>KeepAlive</key><string>always</string>",
+            "<key>Label</key><string>a</string><key>EnvironmentVariables</key><dict><key>N</key><integer>1</integer></dict>",
+        ];
+
+        for xml in invalid.iter() {
+            assert!(LaunchdJob::from_plist(&job(xml)).is_err(), "{}", xml);
+        }
+
+        let err = LaunchdJob::from_plist(&job(invalid[2])).unwrap_err();
+        assert_eq!(err, "RunAtLoad must be a boolean");
+        assert!(LaunchdJob::from_plist(&plist::Value::from("a")).is_err());
+    }
+
+    #[test]
+    fn job_round_trip() {
+        let plist = job("<key>Label</key><string>com.example.job</string>\
+             <key>ProgramArguments</key><array><string>/bin/echo</string><string>hi</string></array>\
+             <key>WorkingDirectory</key><string>/tmp</string>\
+             <key>EnvironmentVariables</key><dict><key>LANG</key><string>C</string></dict>\
+             <key>RunAtLoad</key><true/>\
+             <key>KeepAlive</key><dict><key>SuccessfulExit</key><false/></dict>\
+             <key>StartInterval</key><integer>300</integer>\
+             <key>StandardOutPath</key><string>/tmp/out.log</string>\
+             <key>ThrottleInterval</key><integer>10</integer>\
+             <key>Sockets</key><dict><key>Listener</key><dict/></dict>");
+
+        let parsed = LaunchdJob::from_plist(&plist).unwrap();
+
+        assert_eq!(parsed.label, "com.example.job");
+        assert_eq!(parsed.program_arguments, vec!["/bin/echo", "hi"]);
+        assert_eq!(parsed.environment_variables.get("LANG").unwrap(), "C");
+        assert!(parsed.run_at_load);
+        assert_eq!(parsed.keep_alive, KeepAlive::OnFailure);
+        assert_eq!(parsed.start_interval, Some(300));
+
+        // Keys launchk doesn't model pass through
+        assert_eq!(parsed.other.len(), 2);
+        assert!(parsed.other.contains_key("ThrottleInterval"));
+        assert!(parsed.other.contains_key("Sockets"));
+
+        assert_eq!(parsed.to_plist(), plist);
+        assert_eq!(LaunchdJob::from_plist(&parsed.to_plist()).unwrap(), parsed);
+    }
+
+    #[test]
+    fn job_command() {
+        let mut job = LaunchdJob::new("a");
+        assert_eq!(job.command(), None);
+
+        job.program_arguments = vec!["/bin/echo".to_string(), "hi".to_string()];
+        assert_eq!(
+            job.command(),
+            Some(("/bin/echo".to_string(), job.program_arguments.clone()))
+        );
+
+        // Program wins, argv stays as given
+        job.program = Some("/usr/bin/printf".to_string());
+        assert_eq!(
+            job.command(),
+            Some(("/usr/bin/printf".to_string(), job.program_arguments.clone()))
+        );
+
+        job.program_arguments.clear();
+        assert_eq!(
+            job.command(),
+            Some((
+                "/usr/bin/printf".to_string(),
+                vec!["/usr/bin/printf".to_string()]
+            ))
+        );
+    }
+}
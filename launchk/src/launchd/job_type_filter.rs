@@ -12,6 +12,7 @@ bitflags! {
         const AGENT  = (1 << 4);
         const DAEMON = (1 << 5);
         const LOADED = (1 << 6);
+        const BROKEN = (1 << 7);
     }
 }
 
@@ -21,6 +22,31 @@ impl JobTypeFilter {
         jtf.toggle(JobTypeFilter::LOADED);
         jtf
     }
+
+    /// Parse the `sguadlb` hotkeys, the reverse of Display
+    pub fn from_letters(letters: &str) -> Result<Self, String> {
+        let mut jtf = JobTypeFilter::default();
+
+        for letter in letters.chars() {
+            jtf |= match letter {
+                's' => JobTypeFilter::SYSTEM,
+                'g' => JobTypeFilter::GLOBAL,
+                'u' => JobTypeFilter::USER,
+                'a' => JobTypeFilter::AGENT,
+                'd' => JobTypeFilter::DAEMON,
+                'l' => JobTypeFilter::LOADED,
+                'b' => JobTypeFilter::BROKEN,
+                _ => {
+                    return Err(format!(
+                        "Unknown job type {}, expected one of sguadlb",
+                        letter
+                    ))
+                }
+            };
+        }
+
+        Ok(jtf)
+    }
 }
 
 /// Represent the bitmask as a string for easy TUI check for styling
@@ -53,6 +79,10 @@ impl fmt::Display for JobTypeFilter {
             display.push('l');
         }
 
+        if (*self & JobTypeFilter::BROKEN) == JobTypeFilter::BROKEN {
+            display.push('b');
+        }
+
         write!(f, "{}", display)
     }
 }
@@ -0,0 +1,269 @@
+use std::fmt;
+use std::time::SystemTime;
+
+/// The value types a plist can hold, used to pick an input
+/// when editing and a default when adding keys
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PlistKind {
+    String,
+    Boolean,
+    Integer,
+    Real,
+    Date,
+    Data,
+    Array,
+    Dictionary,
+}
+
+impl PlistKind {
+    /// Kind of an existing value, None for UIDs (keyed archives only)
+    pub fn of(value: &plist::Value) -> Option<Self> {
+        match value {
+            plist::Value::String(_) => Some(PlistKind::String),
+            plist::Value::Boolean(_) => Some(PlistKind::Boolean),
+            plist::Value::Integer(_) => Some(PlistKind::Integer),
+            plist::Value::Real(_) => Some(PlistKind::Real),
+            plist::Value::Date(_) => Some(PlistKind::Date),
+            plist::Value::Data(_) => Some(PlistKind::Data),
+            plist::Value::Array(_) => Some(PlistKind::Array),
+            plist::Value::Dictionary(_) => Some(PlistKind::Dictionary),
+            _ => None,
+        }
+    }
+
+    pub fn is_container(&self) -> bool {
+        *self == PlistKind::Array || *self == PlistKind::Dictionary
+    }
+
+    /// Empty value inserted when a key of this kind is added
+    pub fn default_value(&self) -> plist::Value {
+        match self {
+            PlistKind::String => plist::Value::String("".to_string()),
+            PlistKind::Boolean => plist::Value::Boolean(false),
+            PlistKind::Integer => plist::Value::from(0_i64),
+            PlistKind::Real => plist::Value::Real(0.0),
+            PlistKind::Date => plist::Value::Date(SystemTime::now().into()),
+            PlistKind::Data => plist::Value::Data(vec![]),
+            PlistKind::Array => plist::Value::Array(vec![]),
+            PlistKind::Dictionary => plist::Value::Dictionary(plist::Dictionary::new()),
+        }
+    }
+}
+
+impl fmt::Display for PlistKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_ascii_lowercase())
+    }
+}
+
+pub static PLIST_KINDS: [PlistKind; 8] = [
+    PlistKind::String,
+    PlistKind::Boolean,
+    PlistKind::Integer,
+    PlistKind::Real,
+    PlistKind::Date,
+    PlistKind::Data,
+    PlistKind::Array,
+    PlistKind::Dictionary,
+];
+
+/// Top level job keys from launchd.plist(5): (key, kind, description)
+/// Some keys (e.g. KeepAlive) accept more than one kind, the simplest is listed
+pub static KNOWN_KEYS: [(&str, PlistKind, &str); 52] = [
+    ("Label", PlistKind::String, "Unique job identifier"),
+    ("Disabled", PlistKind::Boolean, "Do not load the job"),
+    ("UserName", PlistKind::String, "Run as user"),
+    ("GroupName", PlistKind::String, "Run as group"),
+    (
+        "InitGroups",
+        PlistKind::Boolean,
+        "Call initgroups(3) before running",
+    ),
+    (
+        "inetdCompatibility",
+        PlistKind::Dictionary,
+        "Run as if launched by inetd",
+    ),
+    (
+        "LimitLoadToHosts",
+        PlistKind::Array,
+        "Only load on these hosts",
+    ),
+    (
+        "LimitLoadFromHosts",
+        PlistKind::Array,
+        "Do not load on these hosts",
+    ),
+    (
+        "LimitLoadToSessionType",
+        PlistKind::String,
+        "Only load in this session type",
+    ),
+    (
+        "LimitLoadToHardware",
+        PlistKind::Dictionary,
+        "Only load on matching hardware",
+    ),
+    (
+        "LimitLoadFromHardware",
+        PlistKind::Dictionary,
+        "Do not load on matching hardware",
+    ),
+    ("Program", PlistKind::String, "Path to the executable"),
+    (
+        "ProgramArguments",
+        PlistKind::Array,
+        "argv passed to execvp(3)",
+    ),
+    (
+        "EnableGlobbing",
+        PlistKind::Boolean,
+        "Expand wildcards in arguments",
+    ),
+    (
+        "EnableTransactions",
+        PlistKind::Boolean,
+        "Track XPC transactions",
+    ),
+    (
+        "EnablePressuredExit",
+        PlistKind::Boolean,
+        "Allow exit under memory pressure",
+    ),
+    ("OnDemand", PlistKind::Boolean, "Deprecated, see KeepAlive"),
+    (
+        "ServiceIPC",
+        PlistKind::Boolean,
+        "Deprecated, has no effect",
+    ),
+    (
+        "KeepAlive",
+        PlistKind::Boolean,
+        "Keep the job running (or conditions)",
+    ),
+    (
+        "RunAtLoad",
+        PlistKind::Boolean,
+        "Start the job when it is loaded",
+    ),
+    (
+        "RootDirectory",
+        PlistKind::String,
+        "chroot(2) before running",
+    ),
+    (
+        "WorkingDirectory",
+        PlistKind::String,
+        "chdir(2) before running",
+    ),
+    (
+        "EnvironmentVariables",
+        PlistKind::Dictionary,
+        "Extra environment",
+    ),
+    ("Umask", PlistKind::Integer, "umask(2) before running"),
+    ("TimeOut", PlistKind::Integer, "Idle timeout in seconds"),
+    (
+        "ExitTimeOut",
+        PlistKind::Integer,
+        "Seconds between SIGTERM and SIGKILL",
+    ),
+    (
+        "ThrottleInterval",
+        PlistKind::Integer,
+        "Minimum seconds between spawns",
+    ),
+    (
+        "WatchPaths",
+        PlistKind::Array,
+        "Start when any of these paths change",
+    ),
+    (
+        "QueueDirectories",
+        PlistKind::Array,
+        "Keep alive while directories are non-empty",
+    ),
+    (
+        "StartOnMount",
+        PlistKind::Boolean,
+        "Start when a filesystem is mounted",
+    ),
+    ("StartInterval", PlistKind::Integer, "Start every N seconds"),
+    (
+        "StartCalendarInterval",
+        PlistKind::Dictionary,
+        "Start on a cron-like schedule",
+    ),
+    ("StandardInPath", PlistKind::String, "stdin file"),
+    ("StandardOutPath", PlistKind::String, "stdout file"),
+    ("StandardErrorPath", PlistKind::String, "stderr file"),
+    ("Debug", PlistKind::Boolean, "Deprecated, has no effect"),
+    (
+        "WaitForDebugger",
+        PlistKind::Boolean,
+        "Wait for a debugger to attach",
+    ),
+    (
+        "SoftResourceLimits",
+        PlistKind::Dictionary,
+        "setrlimit(2) soft limits",
+    ),
+    (
+        "HardResourceLimits",
+        PlistKind::Dictionary,
+        "setrlimit(2) hard limits",
+    ),
+    ("Nice", PlistKind::Integer, "Scheduling priority"),
+    (
+        "ProcessType",
+        PlistKind::String,
+        "Background, Standard, Adaptive or Interactive",
+    ),
+    (
+        "AbandonProcessGroup",
+        PlistKind::Boolean,
+        "Do not kill children on exit",
+    ),
+    (
+        "LowPriorityIO",
+        PlistKind::Boolean,
+        "Low priority filesystem I/O",
+    ),
+    (
+        "LowPriorityBackgroundIO",
+        PlistKind::Boolean,
+        "Low priority I/O when backgrounded",
+    ),
+    (
+        "MaterializeDatalessFiles",
+        PlistKind::Boolean,
+        "Materialize dataless files",
+    ),
+    ("LaunchOnlyOnce", PlistKind::Boolean, "Run once per boot"),
+    (
+        "MachServices",
+        PlistKind::Dictionary,
+        "Mach services to register",
+    ),
+    ("Sockets", PlistKind::Dictionary, "Sockets to listen on"),
+    (
+        "LaunchEvents",
+        PlistKind::Dictionary,
+        "XPC events that start the job",
+    ),
+    (
+        "SessionCreate",
+        PlistKind::Boolean,
+        "Create a new security session",
+    ),
+    (
+        "LegacyTimers",
+        PlistKind::Boolean,
+        "Disable timer coalescing",
+    ),
+    (
+        "AssociatedBundleIdentifiers",
+        PlistKind::Array,
+        "Apps shown in Login Items",
+    ),
+];
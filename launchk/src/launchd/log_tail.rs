@@ -0,0 +1,216 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+/// How far back from the end to look for the lines shown on open
+const BACKLOG_BYTES: u64 = 64 * 1024;
+
+/// The job's log files, (stream, path), stderr left out if it shares stdout's file
+pub fn log_paths(plist: &plist::Value) -> Vec<(&'static str, String)> {
+    let dict = match plist.as_dictionary() {
+        Some(d) => d,
+        None => return vec![],
+    };
+
+    let mut paths: Vec<(&'static str, String)> = vec![];
+
+    for (stream, key) in &[("out", "StandardOutPath"), ("err", "StandardErrorPath")] {
+        if let Some(path) = dict.get(key).and_then(|v| v.as_string()) {
+            if !paths.iter().any(|(_, p)| p == path) {
+                paths.push((stream, path.to_string()));
+            }
+        }
+    }
+
+    paths
+}
+
+/// Follows a file like `tail -F`: new lines as they are appended, starting
+/// over when the file is truncated, replaced (rotated) or recreated
+#[derive(Debug)]
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    inode: Option<u64>,
+    /// Bytes after the last newline, held until the line is finished
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    /// Start following path, with up to `last` of the lines already in it
+    pub fn open<P: Into<PathBuf>>(path: P, last: usize) -> Result<(Self, Vec<String>), String> {
+        let path = path.into();
+        let start = fs::metadata(&path)
+            .map(|m| m.len().saturating_sub(BACKLOG_BYTES))
+            .unwrap_or(0);
+
+        let mut tail = Self {
+            path,
+            offset: start,
+            inode: None,
+            partial: vec![],
+        };
+
+        let mut lines = tail.poll()?;
+
+        // Started mid-file, so the first line is probably cut off
+        if start > 0 && !lines.is_empty() {
+            lines.remove(0);
+        }
+
+        let skip = lines.len().saturating_sub(last);
+        lines.drain(..skip);
+
+        Ok((tail, lines))
+    }
+
+    /// Complete lines written since the last poll. A missing file is
+    /// not an error, launchd creates it when the job first writes.
+    pub fn poll(&mut self) -> Result<Vec<String>, String> {
+        let meta = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.restart(None);
+                return Ok(vec![]);
+            }
+            Err(e) => return Err(format!("{}: {}", self.path.display(), e)),
+        };
+
+        match self.inode {
+            Some(inode) if inode != meta.ino() => self.restart(Some(meta.ino())),
+            None => self.inode = Some(meta.ino()),
+            _ => {}
+        }
+
+        if meta.len() < self.offset {
+            self.restart(Some(meta.ino()));
+        }
+
+        if meta.len() == self.offset {
+            return Ok(vec![]);
+        }
+
+        let mut file =
+            File::open(&self.path).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        file.seek(SeekFrom::Start(self.offset))
+            .map_err(|e| e.to_string())?;
+
+        let read = file
+            .read_to_end(&mut self.partial)
+            .map_err(|e| e.to_string())?;
+        self.offset += read as u64;
+
+        let complete = match self.partial.iter().rposition(|b| *b == b'\n') {
+            Some(at) => at + 1,
+            None => return Ok(vec![]),
+        };
+
+        let rest = self.partial.split_off(complete);
+        let lines = String::from_utf8_lossy(&self.partial)
+            .lines()
+            .map(|l| l.trim_end_matches('\r').to_string())
+            .collect();
+        self.partial = rest;
+
+        Ok(lines)
+    }
+
+    fn restart(&mut self, inode: Option<u64>) {
+        self.inode = inode;
+        self.offset = 0;
+        self.partial.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{log_paths, LogTail};
+    use crate::launchd::test_util::test_dir;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::path::Path;
+
+    fn append(path: &Path, text: &str) {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn log_tail_follows_appends() {
+        let dir = test_dir("log-tail-append");
+        let log = dir.join("job.log");
+
+        append(&log, "one\ntwo\nthree\n");
+        let (mut tail, backlog) = LogTail::open(&log, 2).unwrap();
+        assert_eq!(backlog, vec!["two", "three"]);
+        assert!(tail.poll().unwrap().is_empty());
+
+        // Held until the newline arrives
+        append(&log, "fo");
+        assert!(tail.poll().unwrap().is_empty());
+        append(&log, "ur\r\nfive\nsi");
+        assert_eq!(tail.poll().unwrap(), vec!["four", "five"]);
+        append(&log, "x\n");
+        assert_eq!(tail.poll().unwrap(), vec!["six"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn log_tail_truncate_rotate_and_missing() {
+        let dir = test_dir("log-tail-rotate");
+        let log = dir.join("job.log");
+
+        // Not written yet
+        let (mut tail, backlog) = LogTail::open(&log, 10).unwrap();
+        assert!(backlog.is_empty());
+        append(&log, "started\n");
+        assert_eq!(tail.poll().unwrap(), vec!["started"]);
+
+        append(&log, "a long line before truncation\n");
+        tail.poll().unwrap();
+        fs::write(&log, "after\n").unwrap();
+        assert_eq!(tail.poll().unwrap(), vec!["after"]);
+
+        // newsyslog style: moved aside and a new file started
+        fs::rename(&log, dir.join("job.log.0")).unwrap();
+        append(&log, "rotated and much longer than before\n");
+        assert_eq!(
+            tail.poll().unwrap(),
+            vec!["rotated and much longer than before"]
+        );
+
+        fs::remove_file(&log).unwrap();
+        assert!(tail.poll().unwrap().is_empty());
+        append(&log, "back\n");
+        assert_eq!(tail.poll().unwrap(), vec!["back"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn log_tail_paths() {
+        let mut job = plist::Dictionary::new();
+        job.insert("StandardOutPath".to_string(), "/tmp/job.log".into());
+        job.insert("StandardErrorPath".to_string(), "/tmp/job.log".into());
+
+        let mut plist = plist::Value::Dictionary(job.clone());
+        assert_eq!(log_paths(&plist), vec![("out", "/tmp/job.log".to_string())]);
+
+        job.insert("StandardErrorPath".to_string(), "/tmp/job.err".into());
+        plist = plist::Value::Dictionary(job);
+        assert_eq!(
+            log_paths(&plist),
+            vec![
+                ("out", "/tmp/job.log".to_string()),
+                ("err", "/tmp/job.err".to_string())
+            ]
+        );
+    }
+}
@@ -3,8 +3,43 @@ pub mod message;
 /// queries (sorta?)
 pub mod query;
 
+pub mod disabled;
 pub mod entry_status;
 pub mod job_type_filter;
 
 /// plist management
+pub mod index;
+pub mod index_cache;
 pub mod plist;
+pub mod search_dirs;
+
+/// Reviewing, backing up and undoing plist edits
+pub mod atomic_write;
+pub mod diff;
+pub mod edit;
+pub mod privileged;
+
+/// launchd.plist(5) keys and plist value kinds
+pub mod known_keys;
+
+/// StartCalendarInterval parsing and next fire times
+pub mod calendar;
+
+/// Typed job model, and converting it to and from systemd units and crontabs
+pub mod convert;
+pub mod job;
+
+/// Jobs linked by the Mach services, sockets, paths and programs they share
+pub mod graph;
+
+/// Job plists as JSON, YAML or TOML, and back
+pub mod export;
+
+/// Checking that the files and directories jobs refer to exist
+pub mod health;
+
+/// Following job log files
+pub mod log_tail;
+
+#[cfg(test)]
+pub mod test_util;
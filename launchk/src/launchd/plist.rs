@@ -1,30 +1,35 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
 use std::env;
 use std::fmt;
-use std::fs;
 use std::path::Path;
-use std::sync::{Once, RwLock};
+use std::sync::{Arc, Mutex, Once, RwLock};
 
+use crate::launchd::atomic_write::{write_atomic, TempFile};
+use crate::launchd::calendar::CalendarSchedule;
+use crate::launchd::edit::{check_writable, PlistEdit};
+use crate::launchd::index::PlistIndex;
+use crate::launchd::index_cache::{IndexCache, INDEX_CACHE_PATH};
 use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::launchd::privileged::can_write;
+use crate::launchd::search_dirs::{resolve_search_dirs, watch_roots, SearchDir};
 use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use serde::Deserialize;
 use std::fs::{DirEntry, File, ReadDir};
 use std::io::Read;
 use std::iter::FilterMap;
 use std::process::Command;
-use std::sync::mpsc::{channel, Receiver, Sender};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
 use tokio::runtime::Handle;
 
 pub static PLIST_MAP_INIT: Once = Once::new();
 
+/// How long the watcher waits for changes to settle before saving the cache
+const CACHE_SAVE_DELAY: Duration = Duration::from_secs(30);
+
 lazy_static! {
-    pub static ref LABEL_TO_ENTRY_CONFIG: RwLock<HashMap<String, LaunchdPlist>> =
-        RwLock::new(HashMap::new());
-    static ref EDITOR: String = env::var("EDITOR").unwrap_or("vim".to_string());
+    pub static ref LABEL_TO_ENTRY_CONFIG: RwLock<PlistIndex> = RwLock::new(PlistIndex::default());
     static ref TMP_DIR: String = env::var("TMPDIR").unwrap_or("/tmp".to_string());
-    static ref USER_LAUNCH_AGENTS: String =
-        env::var("HOME").expect("Must read $HOME") + "/Library/LaunchAgents";
 }
 
 /*
@@ -34,7 +39,8 @@ od -xc binary.plist
 */
 static PLIST_MAGIC: &str = "bplist00";
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LaunchdEntryType {
     /// Runs on behalf of currently logged in user
     Agent,
@@ -48,7 +54,8 @@ impl fmt::Display for LaunchdEntryType {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LaunchdEntryLocation {
     /// macOS system provided agent or daemon
     System,
@@ -71,6 +78,8 @@ pub struct LaunchdPlist {
     pub entry_location: LaunchdEntryLocation,
     pub plist_path: String,
     pub readonly: bool,
+    /// Parsed StartCalendarInterval, if any
+    pub schedule: Option<CalendarSchedule>,
 }
 
 // TODO: This should be somewhere else
@@ -103,90 +112,122 @@ pub const SYSTEM_LAUNCH_AGENTS: &str = "/System/Library/LaunchAgents";
 pub const ADMIN_LAUNCH_DAEMONS: &str = "/Library/LaunchDaemons";
 pub const GLOBAL_LAUNCH_DAEMONS: &str = "/System/Library/LaunchDaemons";
 
-async fn fsnotify_subscriber() {
+/// Watch above any wildcards, so bundles and dirs created later are
+/// found: a new directory re-resolves the search dirs before it is indexed.
+/// Once changes settle, the cache catches up with them and is saved.
+async fn fsnotify_subscriber(
+    search_dirs: Vec<SearchDir>,
+    mut dirs: Vec<SearchDir>,
+    cache: Arc<Mutex<IndexCache>>,
+) {
     let (tx, rx): (Sender<DebouncedEvent>, Receiver<DebouncedEvent>) = channel();
     let mut watcher = watcher(tx, Duration::from_secs(5)).expect("Must make fsnotify watcher");
 
     // Register plist paths
-    let watchers = [
-        watcher.watch(Path::new(&*USER_LAUNCH_AGENTS), RecursiveMode::Recursive),
-        watcher.watch(Path::new(GLOBAL_LAUNCH_AGENTS), RecursiveMode::Recursive),
-        watcher.watch(Path::new(SYSTEM_LAUNCH_AGENTS), RecursiveMode::Recursive),
-        watcher.watch(Path::new(ADMIN_LAUNCH_DAEMONS), RecursiveMode::Recursive),
-        watcher.watch(Path::new(GLOBAL_LAUNCH_DAEMONS), RecursiveMode::Recursive),
-    ];
-
-    for sub in watchers.iter() {
-        sub.as_ref().expect("Must subscribe to fs events");
+    for root in watch_roots(&search_dirs) {
+        if let Err(e) = watcher.watch(&root, RecursiveMode::Recursive) {
+            log::warn!("Cannot watch {}: {}", root.display(), e);
+        }
     }
 
+    let mut unsaved = false;
+
     loop {
-        let event = rx.recv();
-        if event.is_err() {
-            continue;
-        }
+        let event = match rx.recv_timeout(CACHE_SAVE_DELAY) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) if unsaved => {
+                sync_cache(&cache, &dirs);
+                unsaved = false;
+                continue;
+            }
+            Err(_) => continue,
+        };
 
-        let event = event.unwrap();
+        let mut index = LABEL_TO_ENTRY_CONFIG.write().expect("Must update");
+        apply_event(&search_dirs, &mut dirs, &mut index, &event);
+        unsaved = true;
+    }
+}
 
-        let reload_plists = match event {
-            DebouncedEvent::Create(pb) => fs::read_dir(pb),
-            DebouncedEvent::Write(pb) => fs::read_dir(pb),
-            DebouncedEvent::Remove(pb) => fs::read_dir(pb),
-            DebouncedEvent::Rename(_, new) => fs::read_dir(new),
-            _ => continue,
-        };
+/// Parse whatever changed since the cache was last synced, merge it into
+/// the index and save the cache. Files changed again since they were
+/// read are left to the watcher, which may already have indexed them.
+fn sync_cache(cache: &Mutex<IndexCache>, dirs: &[SearchDir]) {
+    let mut cache = cache.lock().expect("Must lock cache");
+    let changed = cache.refresh(dirs);
+
+    if !changed.is_empty() {
+        let mut index = LABEL_TO_ENTRY_CONFIG.write().expect("Must update");
 
-        if reload_plists.is_err() {
-            continue;
+        for (path, entry) in changed {
+            if cache.is_current(&path) {
+                index.update(&path, entry);
+            }
         }
+    }
 
-        insert_plists(readdir_filter_plists(reload_plists.unwrap()));
+    if let Err(e) = cache.save(&INDEX_CACHE_PATH) {
+        log::warn!("Cannot save index cache: {}", e);
     }
 }
 
-fn build_label_map_entry(plist_path: DirEntry) -> Option<(String, LaunchdPlist)> {
-    let path = plist_path.path();
-    let path_string = path.to_string_lossy().to_string();
+/// Index one event. A new directory may match a wildcard, so the
+/// search dirs are resolved again first.
+fn apply_event(
+    search_dirs: &[SearchDir],
+    dirs: &mut Vec<SearchDir>,
+    index: &mut PlistIndex,
+    event: &DebouncedEvent,
+) {
+    match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Rename(_, path) if path.is_dir() => {
+            *dirs = resolve_search_dirs(search_dirs)
+        }
+        DebouncedEvent::Rescan => *dirs = resolve_search_dirs(search_dirs),
+        _ => {}
+    }
 
-    let label = plist::Value::from_file(path.clone()).ok()?;
-    let label = label
-        .as_dictionary()
+    index.apply(dirs, event);
+}
+
+/// The parts of a job plist the index keeps: its label and schedule
+pub fn read_label_and_schedule(path: &Path) -> Option<(String, Option<CalendarSchedule>)> {
+    let plist = plist::Value::from_file(path).ok()?;
+    let dict = plist.as_dictionary();
+    let label = dict
         .and_then(|d| d.get("Label"))
         .and_then(|v| v.as_string());
 
-    let entry_type = if path_string.starts_with(ADMIN_LAUNCH_DAEMONS)
-        || path_string.starts_with(GLOBAL_LAUNCH_DAEMONS)
-    {
-        LaunchdEntryType::Daemon
-    } else {
-        LaunchdEntryType::Agent
-    };
-
-    let entry_location = if path_string.starts_with(&*USER_LAUNCH_AGENTS) {
-        LaunchdEntryLocation::User
-    } else if path_string.starts_with(GLOBAL_LAUNCH_AGENTS)
-        || path_string.starts_with(ADMIN_LAUNCH_DAEMONS)
-    {
-        LaunchdEntryLocation::Global
-    } else {
-        LaunchdEntryLocation::System
-    };
-
-    Some((
-        label?.to_string(),
-        LaunchdPlist {
-            entry_location,
-            entry_type,
-            plist_path: path_string,
-            readonly: path
-                .metadata()
-                .map(|m| m.permissions().readonly())
-                .unwrap_or(true),
-        },
-    ))
+    let schedule = dict
+        .and_then(|d| d.get("StartCalendarInterval"))
+        .and_then(|v| {
+            CalendarSchedule::from_plist(v)
+                .map_err(|e| log::warn!("{}: {}", path.display(), e))
+                .ok()
+        });
+
+    Some((label?.to_string(), schedule))
 }
 
-fn readdir_filter_plists(
+impl LaunchdPlist {
+    /// A plist found in dir, classified by it
+    pub fn new(dir: &SearchDir, path: &Path, schedule: Option<CalendarSchedule>) -> Self {
+        Self {
+            entry_location: dir.scope.clone(),
+            entry_type: dir.entry_type.clone(),
+            plist_path: path.to_string_lossy().to_string(),
+            readonly: !can_write(path),
+            schedule,
+        }
+    }
+}
+
+pub fn build_label_map_entry(dir: &SearchDir, path: &Path) -> Option<(String, LaunchdPlist)> {
+    let (label, schedule) = read_label_and_schedule(path)?;
+    Some((label, LaunchdPlist::new(dir, path, schedule)))
+}
+
+pub fn readdir_filter_plists(
     rd: ReadDir,
 ) -> FilterMap<ReadDir, fn(futures::io::Result<DirEntry>) -> Option<DirEntry>> {
     rd.filter_map(|e| {
@@ -209,41 +250,24 @@ fn readdir_filter_plists(
     })
 }
 
-fn insert_plists(plists: impl Iterator<Item = DirEntry>) {
-    let mut label_map = LABEL_TO_ENTRY_CONFIG.write().expect("Must update");
-
-    for plist_path in plists {
-        let entry = build_label_map_entry(plist_path);
-        if entry.is_none() {
-            continue;
-        }
-        let (label, entry) = entry.unwrap();
-        label_map.insert(label, entry);
-    }
-}
-
 /// Unsure if this is overkill, since the filenames
 /// usually match the label property. Still looking for
 /// a way to do dumpstate, dumpjpcategory without parsing the string
-pub fn init_plist_map(runtime_handle: &Handle) {
-    let dirs = [
-        &USER_LAUNCH_AGENTS,
-        GLOBAL_LAUNCH_AGENTS,
-        SYSTEM_LAUNCH_AGENTS,
-        ADMIN_LAUNCH_DAEMONS,
-        GLOBAL_LAUNCH_DAEMONS,
-    ];
-
-    // Get all the plists from everywhere into one stream
-    let plists = dirs
-        .iter()
-        .filter_map(|&dirname| fs::read_dir(Path::new(dirname)).ok())
-        .flat_map(readdir_filter_plists);
-
-    insert_plists(plists);
+pub fn init_plist_map(runtime_handle: &Handle, search_dirs: &[SearchDir]) {
+    let dirs = resolve_search_dirs(search_dirs);
+
+    // Start from what we found last time, so there is no wait for parsing
+    let cache = IndexCache::load(&INDEX_CACHE_PATH);
+    *LABEL_TO_ENTRY_CONFIG.write().expect("Must update") = cache.to_index(&dirs);
+    let cache = Arc::new(Mutex::new(cache));
+
+    // Then catch up with whatever changed since, in the background
+    let refresh_cache = cache.clone();
+    let refresh_dirs = dirs.clone();
+    runtime_handle.spawn_blocking(move || sync_cache(&refresh_cache, &refresh_dirs));
 
     // Spawn fsnotify subscriber
-    runtime_handle.spawn(fsnotify_subscriber());
+    runtime_handle.spawn(fsnotify_subscriber(search_dirs.to_vec(), dirs, cache));
 }
 
 /// Get plist for a label
@@ -252,16 +276,10 @@ pub fn for_label<S: Into<String>>(label: S) -> Option<LaunchdPlist> {
     label_map.get(label.into().as_str()).map(|c| c.clone())
 }
 
-/// Given a LaunchdPlist, start editor pointing to temporary file
-/// and replace on exit. Uses plist crate to validate changes and
-/// help show contents for binary encoded files
-pub fn edit_and_replace(plist_meta: &LaunchdPlist) -> Result<(), String> {
-    if plist_meta.readonly {
-        return Err("plist is read-only!".to_string());
-    }
-
-    let mut file =
-        File::open(&plist_meta.plist_path).map_err(|_| "Couldn't read file".to_string())?;
+/// Read a plist from disk and note if it was binary encoded,
+/// so it can be written back in the same format
+pub fn read_plist<P: AsRef<Path>>(plist_path: P) -> Result<(plist::Value, bool), String> {
+    let mut file = File::open(&plist_path).map_err(|_| "Couldn't read file".to_string())?;
 
     // We want to write back in the correct format,
     // can't assume we can safely write XML everywhere?
@@ -272,41 +290,158 @@ pub fn edit_and_replace(plist_meta: &LaunchdPlist) -> Result<(), String> {
         .map_err(|_| "Couldn't read magic".to_string())?
         == PLIST_MAGIC;
 
+    let plist = plist::Value::from_file(&plist_path).map_err(|e| e.to_string())?;
+
+    Ok((plist, is_binary))
+}
+
+/// Atomically replace a plist on disk in binary or XML encoding
+pub fn write_plist<P: AsRef<Path>>(
+    plist_path: P,
+    plist: &plist::Value,
+    is_binary: bool,
+) -> Result<(), String> {
+    write_atomic(plist_path, &encode_plist(plist, is_binary)?)
+}
+
+/// Serialize a plist in binary or XML encoding
+pub fn encode_plist(plist: &plist::Value, is_binary: bool) -> Result<Vec<u8>, String> {
+    let mut buf: Vec<u8> = vec![];
+
+    if is_binary {
+        plist.to_writer_binary(&mut buf)
+    } else {
+        plist.to_writer_xml(&mut buf)
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(buf)
+}
+
+/// Given a LaunchdPlist, start editor (program and arguments) pointing to
+/// temporary file and return the changes for review on exit. Uses plist
+/// crate to validate changes and help show contents for binary encoded files
+pub fn edit_with_editor(plist_meta: &LaunchdPlist, editor: &[String]) -> Result<PlistEdit, String> {
+    check_writable(plist_meta)?;
+
+    let (program, args) = editor
+        .split_first()
+        .ok_or_else(|| "No editor set".to_string())?;
+
     // plist -> validate with crate -> temp file
-    let og_plist = plist::Value::from_file(&plist_meta.plist_path).map_err(|e| e.to_string())?;
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Must get ts");
-    let temp_path = Path::new(&*TMP_DIR).join(format!("{}", now.as_secs()));
+    let (og_plist, is_binary) = read_plist(&plist_meta.plist_path)?;
+    // Removed when we return, however that happens
+    let mut temp = TempFile::new_in(&*TMP_DIR, "launchk-", ".plist")
+        .map_err(|e| format!("Cannot create temp file: {}", e))?;
     og_plist
-        .to_file_xml(&temp_path)
+        .to_writer_xml(temp.file())
         .map_err(|e| e.to_string())?;
 
-    // Start $EDITOR
-    let exit = Command::new(&*EDITOR)
-        .arg(&temp_path)
+    // Start the editor
+    let exit = Command::new(program)
+        .args(args)
+        .arg(temp.path())
         .status()
-        .map_err(|e| format!("{} failed: {}", &*EDITOR, e.to_string()))?;
+        .map_err(|e| format!("{} failed: {}", program, e.to_string()))?;
 
     if !exit.success() {
-        return Err(format!("{} did not exit successfully", &*EDITOR));
+        return Err(format!("{} did not exit successfully", program));
     }
 
-    // temp file -> validate with crate -> original
+    // temp file -> validate with crate -> review
     let plist =
-        plist::Value::from_file(&temp_path).map_err(|e| format!("Changes not saved: {}", e))?;
+        plist::Value::from_file(temp.path()).map_err(|e| format!("Changes not saved: {}", e))?;
 
-    if og_plist == plist {
-        return Err("No changes made".to_string());
-    }
-
-    let writer = if is_binary {
-        plist::Value::to_file_binary
-    } else {
-        plist::Value::to_file_xml
-    };
-
-    writer(&plist, &plist_meta.plist_path).map_err(|e| e.to_string())?;
+    PlistEdit::new(plist_meta, &og_plist, &plist, is_binary)
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::{apply_event, LaunchdEntryLocation, LaunchdEntryType};
+    use crate::launchd::index::PlistIndex;
+    use crate::launchd::search_dirs::{resolve_search_dirs, watch_roots, SearchDir};
+    use crate::launchd::test_util::{test_dir, write_job};
+    use notify::DebouncedEvent;
+    use std::fs;
+
+    #[test]
+    fn plist_scan_fixture_tree() {
+        let root = test_dir("scan");
+
+        let agents = root.join("Projects/app/launchd");
+        let daemons = root.join("Daemons");
+        fs::create_dir_all(&agents).unwrap();
+        fs::create_dir_all(daemons.join("nested.plist")).unwrap();
+
+        write_job(&agents.join("com.example.agent.plist"), "com.example.agent");
+        write_job(
+            &daemons.join("com.example.daemon.plist"),
+            "com.example.daemon",
+        );
+        fs::write(daemons.join("notes.txt"), "not a plist").unwrap();
+        fs::write(daemons.join("broken.plist"), "not a plist").unwrap();
+
+        let search_dirs = [
+            SearchDir::new(
+                root.join("Projects/*/launchd").to_string_lossy(),
+                LaunchdEntryLocation::User,
+                LaunchdEntryType::Agent,
+            ),
+            SearchDir::new(
+                daemons.to_string_lossy(),
+                LaunchdEntryLocation::Global,
+                LaunchdEntryType::Daemon,
+            ),
+        ];
+        let mut dirs = resolve_search_dirs(&search_dirs);
+
+        let mut index = PlistIndex::scan(&dirs);
+        let mut labels: Vec<&String> = index.keys().collect();
+        labels.sort();
+
+        let found: Vec<(&str, &LaunchdEntryLocation, &LaunchdEntryType)> = labels
+            .into_iter()
+            .filter_map(|label| index.get(label).map(|p| (label.as_str(), p)))
+            .map(|(label, p)| (label, &p.entry_location, &p.entry_type))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "com.example.agent",
+                    &LaunchdEntryLocation::User,
+                    &LaunchdEntryType::Agent
+                ),
+                (
+                    "com.example.daemon",
+                    &LaunchdEntryLocation::Global,
+                    &LaunchdEntryType::Daemon
+                ),
+            ]
+        );
+
+        // A project created later matches the wildcard, under its watch root
+        assert_eq!(
+            watch_roots(&search_dirs),
+            vec![daemons.clone(), root.join("Projects")]
+        );
+
+        let later = root.join("Projects/later");
+        fs::create_dir_all(later.join("launchd")).unwrap();
+        write_job(
+            &later.join("launchd/com.example.later.plist"),
+            "com.example.later",
+        );
+
+        apply_event(
+            &search_dirs,
+            &mut dirs,
+            &mut index,
+            &DebouncedEvent::Create(later),
+        );
+        assert!(index.contains_key("com.example.later"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
 }
@@ -0,0 +1,282 @@
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+
+use crate::launchd::atomic_write::write_atomic;
+
+/// Env var naming the helper, e.g. "sudo tee", if the config doesn't
+pub static PRIVILEGED_HELPER_VAR: &str = "LAUNCHK_PRIVILEGED_HELPER";
+
+lazy_static! {
+    /// Set from the config at startup. None to never elevate.
+    static ref PRIVILEGED_HELPER: RwLock<Option<HelperCommand>> = RwLock::new(None);
+}
+
+/// Writes files we don't have permission to write ourselves
+pub trait PrivilegedWriter {
+    /// Shown to the user before anything is written
+    fn describe(&self) -> String;
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String>;
+}
+
+/// Runs `program args... <path>` with contents on stdin, like `sudo tee`.
+/// The helper owns the write, so it decides whether it is atomic.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HelperCommand {
+    program: String,
+    args: Vec<String>,
+    /// Hand the terminal over while it runs, it may ask for a password
+    interactive: bool,
+}
+
+impl HelperCommand {
+    /// Split a command line like a shell would, None if empty or badly quoted
+    pub fn parse(cmd: &str) -> Option<Self> {
+        let mut words = shell_words::split(cmd).ok()?.into_iter();
+
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+            interactive: false,
+        })
+    }
+
+    /// Clear the screen and give the helper a cooked terminal to prompt on
+    pub fn interactive(self) -> Self {
+        Self {
+            interactive: true,
+            ..self
+        }
+    }
+}
+
+/// The controlling terminal, cleared and out of raw mode until dropped,
+/// so a helper like sudo can prompt on it. Cursive redraws afterwards.
+struct TerminalHandoff {
+    tty: File,
+    saved: libc::termios,
+}
+
+impl TerminalHandoff {
+    /// None without a terminal, e.g. when tests run
+    fn new() -> Option<Self> {
+        let mut tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .ok()?;
+
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(tty.as_raw_fd(), &mut saved) } != 0 {
+            return None;
+        }
+
+        let mut cooked = saved;
+        cooked.c_iflag |= libc::ICRNL;
+        cooked.c_oflag |= libc::OPOST | libc::ONLCR;
+        cooked.c_lflag |= libc::ICANON | libc::ECHO | libc::ISIG;
+        unsafe { libc::tcsetattr(tty.as_raw_fd(), libc::TCSANOW, &cooked) };
+
+        // Clear, cursor home, cursor visible
+        tty.write_all(b"\x1b[2J\x1b[H\x1b[?25h").unwrap_or(());
+
+        Some(Self { tty, saved })
+    }
+}
+
+impl Drop for TerminalHandoff {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.saved) };
+    }
+}
+
+impl PrivilegedWriter for HelperCommand {
+    fn describe(&self) -> String {
+        shell_words::join(std::iter::once(&self.program).chain(self.args.iter()))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let _handoff = if self.interactive {
+            TerminalHandoff::new()
+        } else {
+            None
+        };
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{} failed: {}", self.describe(), e))?;
+
+        // Close stdin before waiting so the helper sees EOF
+        let written = child
+            .stdin
+            .take()
+            .expect("Must have helper stdin")
+            .write_all(contents);
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("{} failed: {}", self.describe(), e))?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                self.describe(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        written.map_err(|e| format!("{} failed: {}", self.describe(), e))
+    }
+}
+
+/// Use helper for plists we can't write, None to never elevate
+pub fn set_privileged_helper(helper: Option<HelperCommand>) {
+    *PRIVILEGED_HELPER.write().expect("Must set helper") = helper;
+}
+
+/// The configured helper, if elevated writes were opted into
+pub fn privileged_helper() -> Option<Box<dyn PrivilegedWriter>> {
+    PRIVILEGED_HELPER
+        .read()
+        .expect("Must read helper")
+        .clone()
+        .map(|h| Box::new(h) as Box<dyn PrivilegedWriter>)
+}
+
+/// Error for read-only plists, with a hint on how to write them anyway
+pub fn readonly_error() -> String {
+    format!(
+        "plist is read-only! Set privileged_helper in config.toml or {} (e.g. \"sudo tee\") to write it with elevated permissions",
+        PRIVILEGED_HELPER_VAR
+    )
+}
+
+/// Whether we can replace path ourselves: the atomic rename
+/// needs its directory to be writable as well as the file
+pub fn can_write<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+
+    [path, dir].iter().all(|p| {
+        CString::new(p.as_os_str().as_bytes())
+            .map(|c| unsafe { libc::access(c.as_ptr(), libc::W_OK) } == 0)
+            .unwrap_or(false)
+    })
+}
+
+/// Replace path with contents, going through helper if it is read-only to us
+pub fn replace_file(
+    path: &Path,
+    contents: &[u8],
+    readonly: bool,
+    helper: Option<&dyn PrivilegedWriter>,
+) -> Result<(), String> {
+    if !readonly {
+        return write_atomic(path, contents);
+    }
+
+    helper.ok_or_else(readonly_error)?.write(path, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replace_file, HelperCommand, PrivilegedWriter, PRIVILEGED_HELPER_VAR};
+    use crate::launchd::test_util::test_dir;
+    use std::cell::RefCell;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// Records writes instead of elevating
+    struct FakeWriter {
+        writes: RefCell<Vec<(PathBuf, Vec<u8>)>>,
+    }
+
+    impl PrivilegedWriter for FakeWriter {
+        fn describe(&self) -> String {
+            "fake".to_string()
+        }
+
+        fn write(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+            self.writes
+                .borrow_mut()
+                .push((path.to_path_buf(), contents.to_vec()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn replace_file_readonly_uses_helper() {
+        let fake = FakeWriter {
+            writes: RefCell::new(vec![]),
+        };
+        let path = Path::new("/Library/LaunchDaemons/com.example.plist");
+
+        replace_file(path, b"new", true, Some(&fake)).unwrap();
+
+        assert_eq!(
+            *fake.writes.borrow(),
+            vec![(path.to_path_buf(), b"new".to_vec())]
+        );
+    }
+
+    #[test]
+    fn replace_file_readonly_without_helper() {
+        let path = Path::new("/Library/LaunchDaemons/com.example.plist");
+        let err = replace_file(path, b"new", true, None).unwrap_err();
+
+        assert!(err.contains(PRIVILEGED_HELPER_VAR));
+    }
+
+    #[test]
+    fn replace_file_writable_skips_helper() {
+        let dir = test_dir("privileged-writable");
+        let path = dir.join("a.plist");
+        let fake = FakeWriter {
+            writes: RefCell::new(vec![]),
+        };
+
+        replace_file(&path, b"new", false, Some(&fake)).unwrap();
+
+        assert!(fake.writes.borrow().is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn helper_command_pipes_contents() {
+        let dir = test_dir("privileged-helper");
+        let path = dir.join("a.plist");
+
+        let tee = HelperCommand::parse("  tee  -a ").unwrap();
+        assert_eq!(tee.describe(), "tee -a");
+
+        tee.write(&path, b"one").unwrap();
+        tee.write(&path, b"two").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "onetwo");
+
+        let err = HelperCommand::parse("false").unwrap().write(&path, b"");
+        assert!(err.is_err());
+        assert!(HelperCommand::parse(" ").is_none());
+        assert!(HelperCommand::parse("sudo 'tee").is_none());
+        assert_eq!(
+            HelperCommand::parse("\"/opt/My Tools/tee\" -a")
+                .unwrap()
+                .describe(),
+            "'/opt/My Tools/tee' -a"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::launchd::plist::{
+    LaunchdEntryLocation, LaunchdEntryType, ADMIN_LAUNCH_DAEMONS, GLOBAL_LAUNCH_AGENTS,
+    GLOBAL_LAUNCH_DAEMONS, SYSTEM_LAUNCH_AGENTS,
+};
+
+pub const USER_LAUNCH_AGENTS: &str = "~/Library/LaunchAgents";
+
+/// Bundled jobs registered with SMAppService, and helper apps in LoginItems
+pub const APP_LAUNCH_AGENTS: &str = "/Applications/*.app/Contents/Library/LaunchAgents";
+pub const APP_LAUNCH_DAEMONS: &str = "/Applications/*.app/Contents/Library/LaunchDaemons";
+pub const APP_LOGIN_ITEMS: &str = "/Applications/*.app/Contents/Library/LoginItems";
+pub const USER_APP_LAUNCH_AGENTS: &str = "~/Applications/*.app/Contents/Library/LaunchAgents";
+
+/// A directory of job plists, and how the jobs in it are classified.
+/// Only plists directly inside it are jobs, subdirectories are not scanned.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchDir {
+    /// `~` is $HOME, `*` matches within one path component
+    pub path: String,
+    pub scope: LaunchdEntryLocation,
+    #[serde(rename = "type")]
+    pub entry_type: LaunchdEntryType,
+}
+
+impl SearchDir {
+    pub fn new<S: Into<String>>(
+        path: S,
+        scope: LaunchdEntryLocation,
+        entry_type: LaunchdEntryType,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            scope,
+            entry_type,
+        }
+    }
+
+    /// The deepest directory the path is under before any wildcard, the
+    /// path itself if it has none. New matches can only appear below it.
+    pub fn watch_root(&self) -> PathBuf {
+        let home = env::var("HOME").unwrap_or_default();
+        let path = expand_home(&self.path, &home);
+
+        Path::new(&path)
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains('*'))
+            .collect()
+    }
+
+    /// Existing directories matching the path, with `~` and `*` resolved
+    pub fn expand(&self) -> Vec<SearchDir> {
+        let home = env::var("HOME").unwrap_or_default();
+        let path = expand_home(&self.path, &home);
+        let mut found = vec![PathBuf::new()];
+
+        for component in Path::new(&path).components() {
+            let pattern = component.as_os_str().to_string_lossy();
+
+            if !pattern.contains('*') {
+                found.iter_mut().for_each(|p| p.push(component));
+                continue;
+            }
+
+            found = found
+                .iter()
+                .filter_map(|dir| fs::read_dir(dir).ok())
+                .flat_map(|rd| rd.filter_map(Result::ok))
+                .filter(|e| wildcard_match(&pattern, &e.file_name().to_string_lossy()))
+                .map(|e| e.path())
+                .collect();
+            found.sort();
+        }
+
+        found
+            .into_iter()
+            .filter(|p| p.is_dir())
+            .map(|p| SearchDir {
+                path: p.to_string_lossy().to_string(),
+                ..self.clone()
+            })
+            .collect()
+    }
+}
+
+/// The directories launchd itself loads from, plus app bundles
+pub fn default_search_dirs() -> Vec<SearchDir> {
+    use LaunchdEntryLocation::*;
+    use LaunchdEntryType::*;
+
+    vec![
+        SearchDir::new(USER_LAUNCH_AGENTS, User, Agent),
+        SearchDir::new(USER_APP_LAUNCH_AGENTS, User, Agent),
+        SearchDir::new(GLOBAL_LAUNCH_AGENTS, Global, Agent),
+        SearchDir::new(ADMIN_LAUNCH_DAEMONS, Global, Daemon),
+        SearchDir::new(APP_LAUNCH_AGENTS, Global, Agent),
+        SearchDir::new(APP_LAUNCH_DAEMONS, Global, Daemon),
+        SearchDir::new(APP_LOGIN_ITEMS, Global, Agent),
+        SearchDir::new(SYSTEM_LAUNCH_AGENTS, System, Agent),
+        SearchDir::new(GLOBAL_LAUNCH_DAEMONS, System, Daemon),
+    ]
+}
+
+/// Expand every dir, keeping the first of any duplicates
+pub fn resolve_search_dirs(dirs: &[SearchDir]) -> Vec<SearchDir> {
+    let mut resolved: Vec<SearchDir> = vec![];
+
+    for dir in dirs.iter().flat_map(|d| d.expand()) {
+        if !resolved.iter().any(|r| r.path == dir.path) {
+            resolved.push(dir);
+        }
+    }
+
+    resolved
+}
+
+pub fn expand_home(path: &str, home: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+/// Existing watch roots of dirs, leaving out any inside another
+pub fn watch_roots(dirs: &[SearchDir]) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = dirs
+        .iter()
+        .map(|d| d.watch_root())
+        .filter(|p| p.is_dir())
+        .collect();
+
+    // Parents sort before their children
+    roots.sort();
+    roots.dedup_by(|child, parent| child.starts_with(parent));
+    roots
+}
+
+/// Glob-style `*` (any run of characters), everything else is literal
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_home, resolve_search_dirs, watch_roots, wildcard_match, SearchDir};
+    use crate::launchd::plist::{LaunchdEntryLocation, LaunchdEntryType};
+    use crate::launchd::test_util::test_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn dirs_of(paths: &[&PathBuf]) -> Vec<SearchDir> {
+        paths
+            .iter()
+            .map(|p| {
+                SearchDir::new(
+                    p.to_string_lossy(),
+                    LaunchdEntryLocation::Global,
+                    LaunchdEntryType::Agent,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn search_dirs_wildcards() {
+        assert!(wildcard_match("*.app", "Foo.app"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "abbbc"));
+        assert!(!wildcard_match("*.app", "Foo.app.zip"));
+        assert!(!wildcard_match("a*b", "ba"));
+        assert!(!wildcard_match("plain", "plains"));
+
+        assert_eq!(expand_home("~/Library", "/Users/me"), "/Users/me/Library");
+        assert_eq!(expand_home("~", "/Users/me"), "/Users/me");
+        assert_eq!(expand_home("~other/x", "/Users/me"), "~other/x");
+    }
+
+    #[test]
+    fn search_dirs_expand_bundles() {
+        let dir = test_dir("search-dirs");
+        for app in &["A.app", "B.app", "C.app"] {
+            fs::create_dir_all(dir.join(app).join("Contents/Library")).unwrap();
+        }
+        fs::create_dir_all(dir.join("A.app/Contents/Library/LaunchAgents")).unwrap();
+        fs::create_dir_all(dir.join("B.app/Contents/Library/LaunchAgents")).unwrap();
+        fs::create_dir_all(dir.join("Other/Contents/Library/LaunchAgents")).unwrap();
+
+        let pattern = dir.join("*.app/Contents/Library/LaunchAgents");
+        let dirs = resolve_search_dirs(&[
+            SearchDir::new(
+                pattern.to_string_lossy(),
+                LaunchdEntryLocation::Global,
+                LaunchdEntryType::Agent,
+            ),
+            SearchDir::new(
+                dir.join("A.app/Contents/Library/LaunchAgents")
+                    .to_string_lossy(),
+                LaunchdEntryLocation::User,
+                LaunchdEntryType::Agent,
+            ),
+            SearchDir::new(
+                dir.to_string_lossy(),
+                LaunchdEntryLocation::User,
+                LaunchdEntryType::Daemon,
+            ),
+            SearchDir::new(
+                dir.join("Missing").to_string_lossy(),
+                LaunchdEntryLocation::User,
+                LaunchdEntryType::Agent,
+            ),
+        ]);
+
+        let paths: Vec<PathBuf> = dirs.iter().map(|d| PathBuf::from(&d.path)).collect();
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("A.app/Contents/Library/LaunchAgents"),
+                dir.join("B.app/Contents/Library/LaunchAgents"),
+                dir.clone(),
+            ]
+        );
+
+        // First one listed wins for duplicates
+        assert_eq!(dirs[0].scope, LaunchdEntryLocation::Global);
+
+        // Bundles installed later are under the dir holding the wildcard
+        assert_eq!(
+            watch_roots(&dirs_of(&[&pattern, &dir.join("A.app")])),
+            vec![dir.clone()]
+        );
+        assert_eq!(
+            SearchDir::new(
+                "~/Library/LaunchAgents",
+                LaunchdEntryLocation::User,
+                LaunchdEntryType::Agent
+            )
+            .watch_root(),
+            PathBuf::from(std::env::var("HOME").unwrap()).join("Library/LaunchAgents")
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+//! Fixtures shared by the launchd tests
+
+use crate::launchd::plist::{LaunchdEntryLocation, LaunchdEntryType};
+use crate::launchd::search_dirs::SearchDir;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A fresh, empty directory under the system temp dir
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("launchk-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write a plist with only a Label
+pub fn write_job(path: &Path, label: &str) {
+    let mut job = plist::Dictionary::new();
+    job.insert("Label".to_string(), plist::Value::from(label));
+    plist::Value::Dictionary(job).to_file_xml(path).unwrap();
+}
+
+/// Parse the keys of a job's top level dict
+pub fn job(xml: &str) -> plist::Value {
+    let doc = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><plist version=\"1.0\"><dict>{}</dict></plist>",
+        xml
+    );
+    plist::Value::from_reader_xml(doc.as_bytes()).unwrap()
+}
+
+/// A single user agents search dir at `dir`
+pub fn agents(dir: &Path) -> Vec<SearchDir> {
+    vec![SearchDir::new(
+        dir.to_string_lossy(),
+        LaunchdEntryLocation::User,
+        LaunchdEntryType::Agent,
+    )]
+}
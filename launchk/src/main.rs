@@ -14,28 +14,46 @@ use cursive::views::{NamedView, Panel};
 use cursive::Cursive;
 use std::process::exit;
 
+use crate::config::Config;
+use crate::launchd::edit::set_backup_dir;
 use crate::launchd::plist::{init_plist_map, PLIST_MAP_INIT};
+use crate::launchd::privileged::set_privileged_helper;
 use crate::tui::root::RootLayout;
+use crate::tui::theme::Theme;
 
+mod config;
 mod launchd;
 mod tui;
 
 fn main() {
     env_logger::init();
 
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid config: {}", e);
+        exit(1);
+    });
+
+    // Before cursive takes over the terminal, so the error can be read
+    let theme = Theme::load(&config.theme()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1);
+    });
+
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .expect("Must build tokio runtime");
 
+    set_privileged_helper(config.privileged_helper());
+    set_backup_dir(config.backup_dir());
+
     // Cache launchd job plist paths, spawn fsnotify to keep up with changes
-    PLIST_MAP_INIT.call_once(|| init_plist_map(runtime.handle()));
+    PLIST_MAP_INIT.call_once(|| init_plist_map(runtime.handle(), &config.search_dirs()));
 
     let mut siv: Cursive = cursive::default();
-    siv.load_toml(include_str!("tui/style.toml"))
-        .expect("Must load styles");
+    theme.apply(&mut siv);
 
-    let root_layout = RootLayout::new(&mut siv, runtime.handle());
+    let root_layout = RootLayout::new(&mut siv, runtime.handle(), &config);
     let root_layout = NamedView::new("root_layout", root_layout);
 
     let panel = Panel::new(root_layout)
@@ -1,20 +1,57 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::mpsc::Sender;
 
+use chrono::Local;
+use cursive::traits::{Nameable, Resizable, Scrollable};
+use cursive::utils::markup::StyledString;
 use cursive::Cursive;
 use cursive::{
-    theme::Effect,
+    theme::{Effect, Style},
     view::Margins,
-    views::{Dialog, DummyView, LinearLayout, RadioGroup, TextView},
+    views::{Dialog, DummyView, EditView, LinearLayout, RadioGroup, SelectView, TextView},
 };
 
+use crate::config::Config;
+use crate::launchd::atomic_write::write_atomic;
+use crate::launchd::calendar::CalendarSchedule;
+use crate::launchd::convert::cron::from_cron;
+use crate::launchd::convert::systemd::from_systemd;
+use crate::launchd::convert::Conversion;
+use crate::launchd::edit::{plist_to_xml, PlistEdit};
 use crate::launchd::entry_status::{get_entry_status, LaunchdEntryStatus};
+use crate::launchd::export::{export_plist, import_plist, ExportFormat, EXPORT_FORMATS};
+use crate::launchd::graph::JobGraph;
+use crate::launchd::health::HealthProblem;
+use crate::launchd::job::LaunchdJob;
+use crate::launchd::plist::{read_plist, LaunchdPlist};
+use crate::launchd::privileged::privileged_helper;
+use crate::launchd::search_dirs::expand_home;
+use crate::tui::omnibox::command::OmniboxCommand;
 use crate::tui::omnibox::command::OMNIBOX_COMMANDS;
-use crate::tui::omnibox::view::OmniboxEvent;
+use crate::tui::omnibox::view::{OmniboxEvent, OmniboxView};
+use crate::tui::preset::{save_preset, Preset, PresetSort, PRESET_KEYS};
 use crate::tui::root::CbSinkMessage;
-use crate::tui::omnibox::command::OmniboxCommand;
+use crate::tui::service_list::bulk::{report, BulkAction};
+use crate::tui::service_list::columns::{ColumnConfig, ALL_COLUMNS};
+use crate::tui::theme::{self, Theme};
 use xpc_sys::csr::{csr_check, CsrConfig};
 use xpc_sys::enums::{DomainType, SessionType};
 
+/// How many upcoming runs the schedule dialog lists
+const SCHEDULE_RUNS: usize = 10;
+
+/// Where jobs converted from systemd or cron are written, unless changed
+const CONVERTED_DIR: &str = "~/Library/LaunchAgents";
+/// A crontab line has no name to start the label from
+const CONVERTED_CRON_LABEL: &str = "local.crontab";
+
+const TEXT_PROMPT_INPUT: &str = "text_prompt_input";
+const COLUMN_PICKER_LIST: &str = "column_picker_list";
+
 /// XPC "error" key can be present with no failure..."notice"?
 pub fn show_error(err: String) -> CbSinkMessage {
     let cl = |siv: &mut Cursive| {
@@ -30,6 +67,17 @@ pub fn show_error(err: String) -> CbSinkMessage {
     Box::new(cl)
 }
 
+/// Something finished, e.g. a file was written
+pub fn show_notice(msg: String) -> CbSinkMessage {
+    Box::new(move |siv| {
+        siv.add_layer(
+            Dialog::around(TextView::new(msg))
+                .title("Done")
+                .dismiss_button("Ok"),
+        )
+    })
+}
+
 /// OmniboxCommand::Prompt(msg, followup commands)
 pub fn show_prompt(
     tx: Sender<OmniboxEvent>,
@@ -55,6 +103,80 @@ pub fn show_prompt(
     Box::new(cl)
 }
 
+/// Show the XML diff of an edit and write it (after a backup) on confirm,
+/// then offer to reload. after_write runs once the review is dismissed.
+pub fn show_edit_review(
+    tx: Sender<OmniboxEvent>,
+    label: String,
+    edit: PlistEdit,
+    after_write: fn(&mut Cursive),
+) -> CbSinkMessage {
+    let diff = edit.diff();
+
+    let cl = move |siv: &mut Cursive| {
+        let roles = theme::roles();
+        let mut styled = StyledString::new();
+
+        for line in diff.lines() {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                roles.diff_file
+            } else if line.starts_with('+') {
+                roles.diff_add
+            } else if line.starts_with('-') {
+                roles.diff_remove
+            } else if line.starts_with("@@") {
+                roles.diff_hunk
+            } else {
+                Style::none()
+            };
+
+            styled.append_styled(format!("{}\n", line), style);
+        }
+
+        // Make elevation explicit, the helper may prompt for a password
+        let privileged = edit.plist_meta.readonly;
+        let write_label = privileged_helper()
+            .filter(|_| privileged)
+            .map(|h| format!("Write with {}", h.describe()))
+            .unwrap_or_else(|| "Write".to_string());
+
+        let review = Dialog::around(TextView::new(styled).scrollable())
+            .title(format!("Write changes to {}?", label))
+            .button(write_label, move |s| {
+                let committed = edit.commit();
+
+                // The helper had the terminal, redraw over whatever it left
+                if privileged {
+                    s.clear();
+                }
+
+                match committed {
+                    Ok(backup_path) => {
+                        s.pop_layer();
+                        after_write(s);
+
+                        tx.send(OmniboxEvent::Command(OmniboxCommand::Confirm(
+                            format!(
+                                "Saved (backup at {}). Reload {}?",
+                                backup_path.display(),
+                                label
+                            ),
+                            vec![OmniboxCommand::Reload],
+                        )))
+                        .expect("Must prompt reload");
+                    }
+                    Err(e) => show_error(e)(s),
+                }
+            })
+            .dismiss_button("Cancel")
+            .full_screen();
+
+        siv.add_layer(review);
+    };
+
+    Box::new(cl)
+}
+
 /// Don't know how to get this info when job is not running,
 /// so we can ask user and suggest a default (domain 7, aqua)
 pub fn domain_session_prompt<S: Into<String>>(
@@ -157,17 +279,663 @@ pub fn show_csr_info() -> CbSinkMessage {
     })
 }
 
-pub fn show_help() -> CbSinkMessage {
-    let commands = OMNIBOX_COMMANDS
+/// StartCalendarInterval in crontab(5) form and the next few runs
+pub fn show_schedule(label: String, schedule: CalendarSchedule) -> CbSinkMessage {
+    let runs = schedule
+        .next_runs(Local::now().naive_local(), SCHEDULE_RUNS)
+        .iter()
+        .map(|t| t.format("%Y-%m-%d %a %H:%M").to_string())
+        .collect::<Vec<String>>();
+
+    let runs = if runs.is_empty() {
+        "Never fires".to_string()
+    } else {
+        runs.join("\n")
+    };
+
+    let text = format!(
+        "StartCalendarInterval (min hour day month weekday):\n{}\n\nNext runs:\n{}",
+        schedule
+            .intervals
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join("\n"),
+        runs
+    );
+
+    Box::new(move |siv| {
+        siv.add_layer(
+            Dialog::new()
+                .title(format!("{} schedule", label))
+                .content(TextView::new(text).scrollable())
+                .dismiss_button("OK")
+                .padding(Margins::trbl(4, 4, 4, 4)),
+        )
+    })
+}
+
+pub fn show_health(label: String, problems: Vec<HealthProblem>) -> CbSinkMessage {
+    let text = if problems.is_empty() {
+        "No problems found".to_string()
+    } else {
+        problems
+            .iter()
+            .map(|p| format!("✘ {}", p))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    Box::new(move |siv| {
+        siv.add_layer(
+            Dialog::new()
+                .title(format!("{} health", label))
+                .content(TextView::new(text).scrollable())
+                .dismiss_button("OK")
+                .padding(Margins::trbl(4, 4, 4, 4)),
+        )
+    })
+}
+
+/// Ask for a line of text (a file path, a width), on_text gets it on Enter or OK
+fn text_prompt<F>(title: String, initial: String, on_text: F) -> Dialog
+where
+    F: Fn(&mut Cursive, &str) + 'static,
+{
+    let on_text = Rc::new(on_text);
+    let on_submit = on_text.clone();
+
+    let input = EditView::new()
+        .content(initial)
+        .on_submit(move |s, text| on_submit(s, text))
+        .with_name(TEXT_PROMPT_INPUT)
+        .min_width(40);
+
+    Dialog::around(input)
+        .title(title)
+        .button("OK", move |s| {
+            let text = s
+                .call_on_name(TEXT_PROMPT_INPUT, |v: &mut EditView| v.get_content())
+                .expect("Must read input");
+            on_text(s, &text);
+        })
+        .dismiss_button("Cancel")
+}
+
+fn format_for_path(path: &str) -> Result<ExportFormat, String> {
+    ExportFormat::from_path(path).ok_or_else(|| {
+        let extensions = EXPORT_FORMATS
+            .iter()
+            .map(|f| format!(".{}", f.extension()))
+            .collect::<Vec<String>>();
+
+        format!("{} must end in {}", path, extensions.join(", "))
+    })
+}
+
+/// A path typed into a prompt, with ~ expanded and made absolute
+fn prompt_path(path: &str) -> PathBuf {
+    let path = PathBuf::from(expand_home(path, &env::var("HOME").unwrap_or_default()));
+    env::current_dir()
+        .map(|cwd| cwd.join(&path))
+        .unwrap_or(path)
+}
+
+/// Write the job's plist as JSON, YAML or TOML, picked by extension
+pub fn show_export(label: String, plist_meta: LaunchdPlist) -> CbSinkMessage {
+    let initial = format!("{}.{}", label, ExportFormat::Yaml.extension());
+
+    Box::new(move |siv| {
+        let plist_path = plist_meta.plist_path;
+
+        siv.add_layer(text_prompt(
+            format!("Export {} to", label),
+            initial,
+            move |s, path| {
+                let path = prompt_path(path);
+                let exported = format_for_path(&path.to_string_lossy())
+                    .and_then(|format| {
+                        let (plist, _) = read_plist(&plist_path)?;
+                        export_plist(&plist, format)
+                    })
+                    .and_then(|text| write_atomic(&path, text.as_bytes()));
+
+                s.pop_layer();
+
+                match exported {
+                    Ok(_) => {
+                        show_notice(format!("Exported {} to {}", plist_path, path.display()))(s)
+                    }
+                    Err(e) => show_error(e)(s),
+                }
+            },
+        ))
+    })
+}
+
+/// Read a job back from JSON, YAML or TOML and review it against the plist
+pub fn show_import(
+    tx: Sender<OmniboxEvent>,
+    label: String,
+    plist_meta: LaunchdPlist,
+) -> CbSinkMessage {
+    let initial = format!("{}.{}", label, ExportFormat::Yaml.extension());
+
+    Box::new(move |siv| {
+        let title = format!("Import {} from", label);
+
+        siv.add_layer(text_prompt(title, initial, move |s, path| {
+            let path = prompt_path(path);
+            let edit = format_for_path(&path.to_string_lossy()).and_then(|format| {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+                let plist = import_plist(&text, format)?;
+
+                // Catch mistyped keys before they reach launchd
+                LaunchdJob::from_plist(&plist)
+                    .map_err(|e| format!("{} is not a valid job: {}", path.display(), e))?;
+
+                let (og_plist, is_binary) = read_plist(&plist_meta.plist_path)?;
+                PlistEdit::new(&plist_meta, &og_plist, &plist, is_binary)
+            });
+
+            s.pop_layer();
+
+            match edit {
+                Ok(edit) => tx
+                    .send(OmniboxEvent::Command(OmniboxCommand::ReviewEdit(
+                        label.clone(),
+                        edit,
+                    )))
+                    .expect("Must review import"),
+                Err(e) => show_error(e)(s),
+            }
+        }))
+    })
+}
+
+/// Ask where to write a converted job, named after its label. The
+/// file name (less .plist) becomes the label.
+fn write_converted(siv: &mut Cursive, conversion: Conversion<LaunchdJob>) {
+    let initial = format!("{}/{}.plist", CONVERTED_DIR, conversion.output.label);
+
+    siv.add_layer(text_prompt(
+        "Write launchd plist to".to_string(),
+        initial,
+        move |s, path| {
+            let path = prompt_path(path);
+            let written = write_job(conversion.output.clone(), &path);
+
+            s.pop_layer();
+
+            match written {
+                Ok(_) => show_notice(format!(
+                    "Wrote {}\n\n{}",
+                    path.display(),
+                    conversion.report("Not converted: ")
+                ))(s),
+                Err(e) => show_error(e)(s),
+            }
+        },
+    ))
+}
+
+/// Write a new job plist, labelled after the file name
+fn write_job(mut job: LaunchdJob, path: &Path) -> Result<(), String> {
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+
+    job.label = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("{} has no file name", path.display()))?;
+
+    let xml = plist_to_xml(&job.to_plist())?;
+    write_atomic(path, xml.as_bytes())
+}
+
+/// Convert a .service unit, with the .timer next to it if there is one
+pub fn show_from_systemd() -> CbSinkMessage {
+    Box::new(|siv| {
+        siv.add_layer(text_prompt(
+            "Convert systemd unit".to_string(),
+            String::new(),
+            |s, path| {
+                let service = prompt_path(path);
+                let timer = service.with_extension("timer");
+
+                let conversion = fs::read_to_string(&service)
+                    .map_err(|e| format!("Cannot read {}: {}", service.display(), e))
+                    .and_then(|unit| {
+                        let timer = fs::read_to_string(&timer).ok();
+                        let label = service
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or_default();
+
+                        from_systemd(&label, &unit, timer.as_deref())
+                    });
+
+                s.pop_layer();
+
+                match conversion {
+                    Ok(conversion) => write_converted(s, conversion),
+                    Err(e) => show_error(e)(s),
+                }
+            },
+        ))
+    })
+}
+
+/// Convert one crontab entry (schedule and command)
+pub fn show_from_crontab() -> CbSinkMessage {
+    Box::new(|siv| {
+        siv.add_layer(text_prompt(
+            "Convert crontab line".to_string(),
+            String::new(),
+            |s, line| {
+                let conversion = from_cron(CONVERTED_CRON_LABEL, line);
+
+                s.pop_layer();
+
+                match conversion {
+                    Ok(conversion) => write_converted(s, conversion),
+                    Err(e) => show_error(e)(s),
+                }
+            },
+        ))
+    })
+}
+
+/// Write DOT to a path asked for, starting at initial
+fn export_dot(siv: &mut Cursive, dot: String, initial: String) {
+    siv.add_layer(text_prompt(
+        "Export DOT to".to_string(),
+        initial,
+        move |s, path| {
+            let path = prompt_path(path);
+            let written = write_atomic(&path, dot.as_bytes());
+            s.pop_layer();
+
+            match written {
+                Ok(_) => show_notice(format!("Wrote {}", path.display()))(s),
+                Err(e) => show_error(e)(s),
+            }
+        },
+    ))
+}
+
+/// A job's resources and the other jobs sharing them. Picking a job
+/// opens its graph on top, closing goes back.
+fn graph_dialog(graph: Rc<JobGraph>, label: String) -> Dialog {
+    let mut select: SelectView<Option<String>> = SelectView::new();
+
+    for (resource, others) in graph.related(&label) {
+        if others.is_empty() {
+            select.add_item(format!("{} {}", resource.verb(), resource), None);
+        }
+
+        for other in others {
+            select.add_item(
+                format!("{} {}  ->  {}", resource.verb(), resource, other),
+                Some(other.to_string()),
+            );
+        }
+    }
+
+    if select.is_empty() {
+        select.add_item(
+            "No MachServices, Sockets, WatchPaths, QueueDirectories or Program",
+            None,
+        );
+    }
+
+    let follow = graph.clone();
+    select.set_on_submit(move |s, other: &Option<String>| {
+        if let Some(other) = other {
+            s.add_layer(graph_dialog(follow.clone(), other.clone()));
+        }
+    });
+
+    let focused = graph.clone();
+    let focus_label = label.clone();
+
+    Dialog::around(select.scrollable())
+        .title(format!("{} graph", label))
+        .button("Export DOT", move |s| {
+            export_dot(
+                s,
+                focused.to_dot(Some(&focus_label)),
+                format!("{}.dot", focus_label),
+            )
+        })
+        .button("Export all", move |s| {
+            export_dot(s, graph.to_dot(None), "launchk.dot".to_string())
+        })
+        .dismiss_button("Close")
+}
+
+pub fn show_graph(graph: JobGraph, label: String) -> CbSinkMessage {
+    Box::new(move |siv| siv.add_layer(graph_dialog(Rc::new(graph), label)))
+}
+
+/// Columns in picker order, and whether each is shown
+type PickerColumns = Rc<RefCell<Vec<(ColumnConfig, bool)>>>;
+
+fn picker_row((column, shown): &(ColumnConfig, bool)) -> String {
+    let width = column
+        .width()
+        .map(|w| w.to_string())
+        .unwrap_or_else(|| "auto".to_string());
+
+    format!(
+        "[{}] {:<10} {}",
+        if *shown { "x" } else { " " },
+        column.name.title(),
+        width
+    )
+}
+
+fn refresh_picker(siv: &mut Cursive, columns: &PickerColumns, selected: usize) {
+    siv.call_on_name(COLUMN_PICKER_LIST, |v: &mut SelectView<usize>| {
+        v.clear();
+        v.add_all(
+            columns
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (picker_row(c), i)),
+        );
+        v.set_selection(selected);
+    });
+}
+
+fn picked_column(siv: &mut Cursive) -> Option<usize> {
+    siv.call_on_name(COLUMN_PICKER_LIST, |v: &mut SelectView<usize>| {
+        v.selection()
+    })
+    .flatten()
+    .map(|i| *i)
+}
+
+/// Blank for the column's default, 0 or auto to share the space left
+fn parse_width(text: &str) -> Result<Option<usize>, String> {
+    match text.trim() {
+        "" => Ok(None),
+        "auto" => Ok(Some(0)),
+        n => n
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| format!("{} is not a width", n)),
+    }
+}
+
+fn column_picker(tx: Sender<OmniboxEvent>, current: Vec<ColumnConfig>) -> Dialog {
+    let mut picked: Vec<(ColumnConfig, bool)> = current.into_iter().map(|c| (c, true)).collect();
+
+    for column in ALL_COLUMNS.iter() {
+        if !picked.iter().any(|(c, _)| c.name == *column) {
+            picked.push((ColumnConfig::new(*column), false));
+        }
+    }
+
+    let columns: PickerColumns = Rc::new(RefCell::new(picked));
+
+    let mut list = SelectView::<usize>::new();
+    list.add_all(
+        columns
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (picker_row(c), i)),
+    );
+
+    let toggle_columns = columns.clone();
+    let list = list.on_submit(move |s, i: &usize| {
+        toggle_columns.borrow_mut()[*i].1 ^= true;
+        refresh_picker(s, &toggle_columns, *i);
+    });
+
+    let mover = |offset: isize| {
+        let columns = columns.clone();
+
+        move |s: &mut Cursive| {
+            let from = match picked_column(s) {
+                Some(i) => i,
+                None => return,
+            };
+            let to = from as isize + offset;
+
+            if to < 0 || to as usize >= columns.borrow().len() {
+                return;
+            }
+
+            columns.borrow_mut().swap(from, to as usize);
+            refresh_picker(s, &columns, to as usize);
+        }
+    };
+
+    let width_columns = columns.clone();
+    let apply_columns = columns.clone();
+
+    Dialog::around(
+        LinearLayout::vertical()
+            .child(TextView::new("Enter shows or hides the highlighted column"))
+            .child(DummyView)
+            .child(list.with_name(COLUMN_PICKER_LIST).scrollable()),
+    )
+    .title("Columns")
+    .button("Up", mover(-1))
+    .button("Down", mover(1))
+    .button("Width", move |s| {
+        let i = match picked_column(s) {
+            Some(i) => i,
+            None => return,
+        };
+        let (title, initial) = {
+            let (column, _) = &width_columns.borrow()[i];
+            (
+                format!(
+                    "{} width (blank for default, auto to share)",
+                    column.name.title()
+                ),
+                column.width.map(|w| w.to_string()).unwrap_or_default(),
+            )
+        };
+
+        let columns = width_columns.clone();
+        s.add_layer(text_prompt(title, initial, move |s, text| {
+            s.pop_layer();
+
+            match parse_width(text) {
+                Ok(width) => {
+                    columns.borrow_mut()[i].0.width = width;
+                    refresh_picker(s, &columns, i);
+                }
+                Err(e) => show_error(e)(s),
+            }
+        }));
+    })
+    .button("Apply", move |s| {
+        let shown: Vec<ColumnConfig> = apply_columns
+            .borrow()
+            .iter()
+            .filter(|(_, shown)| *shown)
+            .map(|(c, _)| c.clone())
+            .collect();
+
+        if shown.is_empty() {
+            show_error("Show at least one column".to_string())(s);
+            return;
+        }
+
+        s.pop_layer();
+        tx.send(OmniboxEvent::Command(OmniboxCommand::SetColumns(shown)))
+            .expect("Must set columns");
+    })
+    .dismiss_button("Cancel")
+}
+
+/// Pick the service list's columns, starting from the current ones
+pub fn show_column_picker(tx: Sender<OmniboxEvent>, current: Vec<ColumnConfig>) -> CbSinkMessage {
+    Box::new(move |siv| siv.add_layer(column_picker(tx, current)))
+}
+
+fn preset_row(n: usize, preset: &Preset) -> String {
+    let key = if n < PRESET_KEYS {
+        (n + 1).to_string()
+    } else {
+        " ".to_string()
+    };
+
+    format!("{}  {}: {}", key, preset.name, preset.describe())
+}
+
+pub fn show_presets(tx: Sender<OmniboxEvent>, presets: Vec<Preset>) -> CbSinkMessage {
+    Box::new(move |siv| {
+        let mut list = SelectView::new();
+        list.add_all(
+            presets
+                .into_iter()
+                .enumerate()
+                .map(|(n, p)| (preset_row(n, &p), p)),
+        );
+
+        list.set_on_submit(move |s, preset: &Preset| {
+            s.pop_layer();
+            tx.send(OmniboxEvent::Command(OmniboxCommand::ApplyPreset(
+                preset.clone(),
+            )))
+            .expect("Must apply preset");
+        });
+
+        siv.add_layer(
+            Dialog::around(list.scrollable())
+                .title("Presets")
+                .dismiss_button("Cancel"),
+        )
+    })
+}
+
+/// Built-in and user themes, the applied one selected. Switching lasts
+/// for the session, set `theme` in the config file to keep it
+pub fn show_themes() -> CbSinkMessage {
+    Box::new(|siv| {
+        let current = theme::current();
+        let names = theme::available();
+
+        let mut list = SelectView::new();
+        list.add_all_str(names.iter());
+        list.set_selection(names.iter().position(|n| *n == current).unwrap_or(0));
+
+        list.set_on_submit(|s, name: &str| {
+            s.pop_layer();
+
+            match Theme::load(name) {
+                Ok(theme) => theme.apply(s),
+                Err(e) => show_error(e)(s),
+            }
+        });
+
+        siv.add_layer(
+            Dialog::around(list.scrollable())
+                .title("Theme")
+                .dismiss_button("Cancel"),
+        )
+    })
+}
+
+/// Name the omnibox's filters and the table's sort, and append them to the
+/// config file. on_saved gets the preset once it is written.
+pub fn show_preset_save<F>(sort: Option<PresetSort>, on_saved: F) -> CbSinkMessage
+where
+    F: Fn(&mut Cursive, Preset) + Send + 'static,
+{
+    Box::new(move |siv| {
+        let state = siv
+            .call_on_name("omnibox", |v: &mut OmniboxView| v.state())
+            .expect("Must read omnibox");
+
+        siv.add_layer(text_prompt(
+            "Save preset as".to_string(),
+            "".to_string(),
+            move |s, name| {
+                let name = name.trim();
+
+                if name.is_empty() {
+                    return;
+                }
+
+                let preset = Preset {
+                    name: name.to_string(),
+                    filter: state.label_filter.clone(),
+                    filter_mode: state.filter_mode,
+                    job_types: state.job_type_filter.to_string(),
+                    sort,
+                };
+
+                let saved = save_preset(Config::path(), &preset, |text| {
+                    Config::from_toml(text).map(|_| ())
+                });
+
+                s.pop_layer();
+
+                match saved {
+                    Ok(_) => {
+                        let notice = format!(
+                            "Saved preset {} to {}",
+                            preset.name,
+                            Config::path().display()
+                        );
+                        on_saved(s, preset);
+                        show_notice(notice)(s)
+                    }
+                    Err(e) => show_error(e)(s),
+                }
+            },
+        ))
+    })
+}
+
+/// Per-job outcome of a bulk action
+pub fn show_bulk_results(
+    action: BulkAction,
+    results: Vec<(String, Result<(), String>)>,
+) -> CbSinkMessage {
+    let (title, body) = report(action, &results);
+
+    Box::new(move |siv| {
+        siv.add_layer(
+            Dialog::around(TextView::new(body).scrollable())
+                .title(title)
+                .dismiss_button("OK"),
+        )
+    })
+}
+
+/// Keys from the active keymap, then every command
+pub fn show_help(keys: Vec<(String, String)>) -> CbSinkMessage {
+    let key_width = keys.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+
+    let mut lines = keys
         .iter()
-        .map(|(cmd, desc, _)| format!("{}: {}", cmd, desc))
+        .map(|(key, desc)| format!("{:<width$}  {}", key, desc, width = key_width))
         .collect::<Vec<String>>();
 
+    lines.push("".to_string());
+    lines.extend(
+        OMNIBOX_COMMANDS
+            .iter()
+            .map(|(cmd, desc, _)| format!("{}: {}", cmd, desc)),
+    );
+
     Box::new(move |siv| {
         siv.add_layer(
             Dialog::new()
                 .title("Help")
-                .content(TextView::new(commands.join("\n")))
+                .content(TextView::new(lines.join("\n")).scrollable())
                 .dismiss_button("OK")
                 .padding(Margins::trbl(4, 4, 4, 4)),
         )
@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+
+use cursive::event::{Event, Key};
+
+use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::omnibox::command::{OmniboxCommand, OMNIBOX_COMMANDS};
+
+/// Bindings unless the config file's `[keys]` says otherwise
+static DEFAULT_KEYS: [(&str, &str); 10] = [
+    (":", "command-mode"),
+    ("/", "filter-mode"),
+    ("ctrl-u", "clear"),
+    ("s", "system"),
+    ("g", "global"),
+    ("u", "user"),
+    ("a", "agent"),
+    ("d", "daemon"),
+    ("l", "loaded"),
+    ("b", "broken"),
+];
+
+/// Keys the views handle themselves, which can't start a binding.
+/// (as shown in help, keys, what they do)
+static RESERVED_KEYS: [(&str, &[&str], &str); 9] = [
+    ("o", &["o"], "Sort by the next column"),
+    ("O", &["O"], "Reverse the sort"),
+    ("space", &["space"], "Mark the highlighted job"),
+    (
+        "1-9",
+        &["1", "2", "3", "4", "5", "6", "7", "8", "9"],
+        "Apply a preset",
+    ),
+    (
+        "arrows, pageup, pagedown, home, end",
+        &[
+            "up", "down", "left", "right", "pageup", "pagedown", "home", "end",
+        ],
+        "Move through the list",
+    ),
+    (
+        "tab",
+        &["tab"],
+        "Complete a command, or switch filter matching",
+    ),
+    ("enter", &["enter"], "Submit"),
+    (
+        "backspace",
+        &["backspace"],
+        "Delete the last character typed",
+    ),
+    ("esc", &["esc"], "Back to the service list"),
+];
+
+static JOB_TYPE_ACTIONS: [(&str, JobTypeFilter); 7] = [
+    ("system", JobTypeFilter::SYSTEM),
+    ("global", JobTypeFilter::GLOBAL),
+    ("user", JobTypeFilter::USER),
+    ("agent", JobTypeFilter::AGENT),
+    ("daemon", JobTypeFilter::DAEMON),
+    ("loaded", JobTypeFilter::LOADED),
+    ("broken", JobTypeFilter::BROKEN),
+];
+
+/// What a binding does
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum KeyAction {
+    CommandMode,
+    FilterMode,
+    /// Reset the filters and leave the omnibox
+    Clear,
+    JobType(JobTypeFilter),
+    Command(OmniboxCommand),
+}
+
+impl KeyAction {
+    fn from_name(name: &str) -> Option<Self> {
+        let job_type = JOB_TYPE_ACTIONS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, mask)| KeyAction::JobType(*mask));
+
+        let command = OMNIBOX_COMMANDS
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, _, cmd)| KeyAction::Command(cmd.clone()));
+
+        match name {
+            "command-mode" => Some(KeyAction::CommandMode),
+            "filter-mode" => Some(KeyAction::FilterMode),
+            "clear" => Some(KeyAction::Clear),
+            _ => job_type.or(command),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            KeyAction::CommandMode => "Enter a command".to_string(),
+            KeyAction::FilterMode => "Filter by label".to_string(),
+            KeyAction::Clear => "Clear filters".to_string(),
+            KeyAction::JobType(mask) => JOB_TYPE_ACTIONS
+                .iter()
+                .find(|(_, m)| m == mask)
+                .map(|(name, _)| format!("Toggle {} jobs", name))
+                .unwrap_or_else(|| format!("Toggle {:?}", mask)),
+            KeyAction::Command(cmd) => OMNIBOX_COMMANDS
+                .iter()
+                .find(|(_, _, c)| c == cmd)
+                .map(|(_, desc, _)| desc.to_string())
+                .unwrap_or_else(|| cmd.to_string()),
+        }
+    }
+}
+
+fn named_key(name: &str) -> Option<Event> {
+    let key = match name {
+        "space" => return Some(Event::Char(' ')),
+        "esc" => Key::Esc,
+        "enter" => Key::Enter,
+        "tab" => Key::Tab,
+        "backspace" => Key::Backspace,
+        "del" => Key::Del,
+        "ins" => Key::Ins,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        _ => return None,
+    };
+
+    Some(Event::Key(key))
+}
+
+/// One key press: `x`, `ctrl-x` (or `C-x`), `alt-x` (or `M-x`), or a named
+/// key like `space`, `f5`, `pagedown`
+pub fn parse_key(text: &str) -> Result<Event, String> {
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if let Some(c) = single(text) {
+        return Ok(Event::Char(c));
+    }
+
+    let lower = text.to_ascii_lowercase();
+    let modified = |prefixes: [&str; 2]| {
+        prefixes
+            .iter()
+            .find_map(|p| lower.strip_prefix(p))
+            .and_then(single)
+    };
+
+    if let Some(c) = modified(["ctrl-", "c-"]) {
+        Ok(Event::CtrlChar(c))
+    } else if let Some(c) = modified(["alt-", "m-"]) {
+        Ok(Event::AltChar(c))
+    } else if lower == "shift-tab" {
+        Ok(Event::Shift(Key::Tab))
+    } else {
+        named_key(&lower).ok_or_else(|| format!("Unknown key {}", text))
+    }
+}
+
+/// Whitespace separated key presses, e.g. `ctrl-x r`
+pub fn parse_keys(text: &str) -> Result<Vec<Event>, String> {
+    let keys = text
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<Event>, String>>()?;
+
+    if keys.is_empty() {
+        Err("Empty key".to_string())
+    } else {
+        Ok(keys)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Binding {
+    /// As written, for messages and help
+    text: String,
+    keys: Vec<Event>,
+    action: KeyAction,
+}
+
+/// Result of looking up the keys pressed so far
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum KeyLookup {
+    Action(KeyAction),
+    /// The start of a chord, wait for the next key
+    Prefix,
+    Unbound,
+}
+
+/// Keys and chords mapped to omnibox modes, job type toggles and commands
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// The defaults with `overrides` (key -> action name, or "none" to unbind)
+    /// on top. Errors if a key can't be parsed, is reserved, or conflicts
+    /// with another.
+    pub fn new(overrides: &BTreeMap<String, String>) -> Result<Self, String> {
+        let reserved = RESERVED_KEYS
+            .iter()
+            .flat_map(|(_, keys, _)| keys.iter())
+            .map(|k| parse_key(k).expect("Reserved keys must parse"))
+            .collect::<Vec<Event>>();
+
+        let mut user: Vec<(String, Vec<Event>, Option<KeyAction>)> = vec![];
+
+        for (text, name) in overrides {
+            let keys = parse_keys(text).map_err(|e| format!("Key {}: {}", text, e))?;
+            let action = match name.as_str() {
+                "none" => None,
+                _ => Some(
+                    KeyAction::from_name(name)
+                        .ok_or_else(|| format!("Key {}: unknown action {}", text, name))?,
+                ),
+            };
+
+            if reserved.contains(&keys[0]) {
+                return Err(format!("Key {} is reserved", text));
+            }
+
+            if let Some((other, ..)) = user.iter().find(|(_, k, _)| *k == keys) {
+                return Err(format!("Keys {} and {} are the same", other, text));
+            }
+
+            user.push((text.clone(), keys, action));
+        }
+
+        let defaults = DEFAULT_KEYS.iter().filter_map(|(text, name)| {
+            let keys = parse_keys(text).expect("Default keys must parse");
+
+            if user.iter().any(|(_, k, _)| *k == keys) {
+                return None;
+            }
+
+            Some(Binding {
+                text: text.to_string(),
+                keys,
+                action: KeyAction::from_name(name).expect("Default actions must exist"),
+            })
+        });
+
+        let mut bindings: Vec<Binding> = defaults.collect();
+
+        bindings.extend(user.into_iter().filter_map(|(text, keys, action)| {
+            action.map(|action| Binding { text, keys, action })
+        }));
+
+        for binding in bindings.iter() {
+            let shadowed = bindings
+                .iter()
+                .find(|b| b.keys.len() < binding.keys.len() && binding.keys.starts_with(&b.keys));
+
+            if let Some(prefix) = shadowed {
+                return Err(format!(
+                    "Key {} is bound, so {} can never be typed",
+                    prefix.text, binding.text
+                ));
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// While typing into the omnibox, only bindings starting with a
+    /// modified or named key apply, so letters can still be typed
+    pub fn lookup(&self, pressed: &[Event], typing: bool) -> KeyLookup {
+        let mut candidates = self
+            .bindings
+            .iter()
+            .filter(|b| !(typing && matches!(b.keys[0], Event::Char(_))))
+            .filter(|b| b.keys.starts_with(pressed));
+
+        match candidates.next() {
+            None => KeyLookup::Unbound,
+            Some(b) if b.keys.len() == pressed.len() => KeyLookup::Action(b.action.clone()),
+            Some(_) => KeyLookup::Prefix,
+        }
+    }
+
+    /// Key and what it does, bindings then the fixed keys
+    pub fn help(&self) -> Vec<(String, String)> {
+        self.bindings
+            .iter()
+            .map(|b| (b.text.clone(), b.action.describe()))
+            .chain(
+                RESERVED_KEYS
+                    .iter()
+                    .map(|(text, _, desc)| (text.to_string(), desc.to_string())),
+            )
+            .collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("Default keymap must be valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use cursive::event::{Event, Key};
+
+    use super::{parse_keys, KeyAction, KeyLookup, Keymap};
+    use crate::launchd::job_type_filter::JobTypeFilter;
+    use crate::tui::omnibox::command::OmniboxCommand;
+
+    fn keymap(overrides: &[(&str, &str)]) -> Result<Keymap, String> {
+        Keymap::new(
+            &overrides
+                .iter()
+                .map(|(k, a)| (k.to_string(), a.to_string()))
+                .collect::<BTreeMap<String, String>>(),
+        )
+    }
+
+    #[test]
+    fn keymap_parse_keys() {
+        assert_eq!(
+            parse_keys("C-x r").unwrap(),
+            vec![Event::CtrlChar('x'), Event::Char('r')]
+        );
+        assert_eq!(parse_keys("ctrl-U").unwrap(), vec![Event::CtrlChar('u')]);
+        assert_eq!(parse_keys("M-f").unwrap(), vec![Event::AltChar('f')]);
+        assert_eq!(parse_keys("F5").unwrap(), vec![Event::Key(Key::F5)]);
+        assert_eq!(parse_keys("R").unwrap(), vec![Event::Char('R')]);
+        assert!(parse_keys("hyper-x").is_err());
+        assert!(parse_keys(" ").is_err());
+    }
+
+    #[test]
+    fn keymap_lookup() {
+        let keys = keymap(&[("ctrl-x r", "reload"), ("s", "none"), ("S", "system")]).unwrap();
+        let ctrl_x = Event::CtrlChar('x');
+
+        assert_eq!(
+            keys.lookup(std::slice::from_ref(&ctrl_x), false),
+            KeyLookup::Prefix
+        );
+        assert_eq!(
+            keys.lookup(&[ctrl_x.clone(), Event::Char('r')], false),
+            KeyLookup::Action(KeyAction::Command(OmniboxCommand::Reload))
+        );
+        assert_eq!(
+            keys.lookup(&[ctrl_x, Event::Char('q')], false),
+            KeyLookup::Unbound
+        );
+        assert_eq!(keys.lookup(&[Event::Char('s')], false), KeyLookup::Unbound);
+        assert_eq!(
+            keys.lookup(&[Event::Char('S')], false),
+            KeyLookup::Action(KeyAction::JobType(JobTypeFilter::SYSTEM))
+        );
+
+        // Letters are typed into the omnibox, but ctrl-u still clears
+        assert_eq!(keys.lookup(&[Event::Char('S')], true), KeyLookup::Unbound);
+        assert_eq!(
+            keys.lookup(&[Event::CtrlChar('u')], true),
+            KeyLookup::Action(KeyAction::Clear)
+        );
+    }
+
+    #[test]
+    fn keymap_conflicts() {
+        assert!(keymap(&[]).is_ok());
+        // g toggles global jobs, so g g could never be typed
+        assert!(keymap(&[("g g", "load")]).is_err());
+        assert!(keymap(&[("g", "none"), ("g g", "load")]).is_ok());
+        assert!(keymap(&[("C-r", "reload"), ("ctrl-r", "load")]).is_err());
+        assert!(keymap(&[("o", "reload")]).is_err());
+        assert!(keymap(&[("x", "frobnicate")]).is_err());
+        assert!(keymap(&[("ctrl-", "reload")]).is_err());
+    }
+}
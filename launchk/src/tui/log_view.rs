@@ -0,0 +1,287 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cursive::event::{Event, EventResult};
+use cursive::traits::{Nameable, Resizable, Scrollable};
+use cursive::utils::markup::StyledString;
+use cursive::view::{ScrollStrategy, ViewWrapper};
+use cursive::views::{Dialog, EditView, LinearLayout, TextContent, TextView};
+use cursive::Cursive;
+
+use tokio::runtime::Handle;
+use tokio::time::interval;
+
+use crate::launchd::log_tail::LogTail;
+use crate::tui::root::CbSinkMessage;
+use crate::tui::theme;
+
+const LOG_VIEW: &str = "log_view";
+const LOG_SEARCH_INPUT: &str = "log_search_input";
+
+/// Lines shown when the view opens, per file
+const BACKLOG_LINES: usize = 200;
+/// Oldest lines are dropped past this
+const MAX_LINES: usize = 5000;
+
+/// Follows a job's log files. `p` pauses, `/` searches
+pub struct LogView {
+    layout: LinearLayout,
+    tails: Vec<(&'static str, LogTail)>,
+    /// (stream, line)
+    lines: Vec<(&'static str, String)>,
+    status: TextContent,
+    content: TextContent,
+    paused: bool,
+    /// Lines read while paused, shown on resume
+    unseen: usize,
+    search: String,
+    error: Option<String>,
+}
+
+impl LogView {
+    fn new(tails: Vec<(&'static str, LogTail)>, lines: Vec<(&'static str, String)>) -> Self {
+        let status = TextContent::new("");
+        let content = TextContent::new("");
+
+        let layout = LinearLayout::vertical()
+            .child(TextView::new_with_content(status.clone()))
+            .child(
+                TextView::new_with_content(content.clone())
+                    .scrollable()
+                    .scroll_strategy(ScrollStrategy::StickToBottom),
+            );
+
+        let mut view = Self {
+            layout,
+            tails,
+            lines,
+            status,
+            content,
+            paused: false,
+            unseen: 0,
+            search: String::new(),
+            error: None,
+        };
+
+        view.render();
+        view
+    }
+
+    /// Read whatever was appended since last time
+    fn poll(&mut self) {
+        let mut read = 0;
+        self.error = None;
+
+        for (stream, tail) in self.tails.iter_mut() {
+            match tail.poll() {
+                Ok(new) => {
+                    read += new.len();
+                    self.lines.extend(new.into_iter().map(|l| (*stream, l)));
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+
+        let over = self.lines.len().saturating_sub(MAX_LINES);
+        self.lines.drain(..over);
+
+        if self.paused {
+            self.unseen += read;
+            self.render_status();
+        } else if read > 0 || self.error.is_some() {
+            self.render();
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.unseen = 0;
+        self.render();
+    }
+
+    fn set_search<S: Into<String>>(&mut self, search: S) {
+        self.search = search.into();
+        self.render();
+    }
+
+    fn render_status(&self) {
+        let mut status = vec![];
+
+        if self.paused {
+            status.push(format!("Paused, {} new lines", self.unseen));
+        } else {
+            status.push("Following".to_string());
+        }
+
+        if !self.search.is_empty() {
+            status.push(format!("matching \"{}\"", self.search));
+        }
+
+        if let Some(e) = &self.error {
+            status.push(e.clone());
+        }
+
+        status.push("(p) pause  (/) search".to_string());
+
+        self.status.set_content(StyledString::styled(
+            status.join(" · "),
+            theme::roles().subtle,
+        ));
+    }
+
+    fn render(&self) {
+        self.render_status();
+
+        if self.paused {
+            return;
+        }
+
+        let tagged = self.tails.len() > 1;
+        let roles = theme::roles();
+        let mut content = StyledString::new();
+
+        for (stream, line) in self.lines.iter() {
+            if !self.search.is_empty() && !line.contains(&self.search) {
+                continue;
+            }
+
+            if tagged {
+                let tag_style = if *stream == "err" {
+                    roles.error
+                } else {
+                    roles.subtle
+                };
+                content.append_styled(format!("{} │ ", stream), tag_style);
+            }
+
+            if self.search.is_empty() {
+                content.append_plain(line);
+            } else {
+                let mut parts = line.split(self.search.as_str()).peekable();
+
+                while let Some(part) = parts.next() {
+                    content.append_plain(part);
+
+                    if parts.peek().is_some() {
+                        content.append_styled(self.search.as_str(), roles.matched);
+                    }
+                }
+            }
+
+            content.append_plain("\n");
+        }
+
+        self.content.set_content(content);
+    }
+}
+
+impl ViewWrapper for LogView {
+    wrap_impl!(self.layout: LinearLayout);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char('p') => {
+                self.toggle_pause();
+                EventResult::Consumed(None)
+            }
+            Event::Char('/') => {
+                let search = self.search.clone();
+                EventResult::with_cb(move |siv| siv.add_layer(search_prompt(&search)))
+            }
+            _ => self.layout.on_event(event),
+        }
+    }
+}
+
+/// Only show lines containing the search, empty to show everything
+fn search_prompt(initial: &str) -> Dialog {
+    let submit = |siv: &mut Cursive, search: &str| {
+        let search = search.to_string();
+        siv.pop_layer();
+        siv.call_on_name(LOG_VIEW, |v: &mut LogView| v.set_search(search));
+    };
+
+    Dialog::new()
+        .title("Search logs")
+        .content(
+            EditView::new()
+                .content(initial)
+                .on_submit(submit)
+                .with_name(LOG_SEARCH_INPUT)
+                .min_width(40),
+        )
+        .button("OK", move |siv| {
+            let search = siv
+                .call_on_name(LOG_SEARCH_INPUT, |v: &mut EditView| v.get_content())
+                .expect("Must have search input");
+            submit(siv, &search);
+        })
+        .dismiss_button("Cancel")
+}
+
+/// Ask the view to read its files until it is closed
+async fn poll_log_view(
+    cb_sink: Sender<CbSinkMessage>,
+    poll_interval: Duration,
+    closed: Arc<AtomicBool>,
+) {
+    let mut interval = interval(poll_interval);
+
+    while !closed.load(Ordering::Relaxed) {
+        interval.tick().await;
+
+        let closed = closed.clone();
+        let sent = cb_sink.send(Box::new(move |siv| {
+            if siv
+                .call_on_name(LOG_VIEW, |v: &mut LogView| v.poll())
+                .is_none()
+            {
+                closed.store(true, Ordering::Relaxed);
+            }
+        }));
+
+        if sent.is_err() {
+            break;
+        }
+    }
+}
+
+/// Open a follow-mode view of the (stream, path) log files
+pub fn show_logs(
+    runtime_handle: &Handle,
+    cb_sink: Sender<CbSinkMessage>,
+    label: String,
+    paths: Vec<(&'static str, String)>,
+    poll_interval: Duration,
+) -> Result<(), String> {
+    let mut tails = vec![];
+    let mut lines = vec![];
+
+    for (stream, path) in paths {
+        let (tail, backlog) = LogTail::open(&path, BACKLOG_LINES)?;
+        lines.extend(backlog.into_iter().map(|l| (stream, l)));
+        tails.push((stream, tail));
+    }
+
+    cb_sink
+        .send(Box::new(move |siv| {
+            siv.add_layer(
+                Dialog::new()
+                    .title(format!("{} logs", label))
+                    .content(LogView::new(tails, lines).with_name(LOG_VIEW))
+                    .dismiss_button("Close")
+                    .full_screen(),
+            )
+        }))
+        .expect("Must show logs");
+
+    runtime_handle.spawn(poll_log_view(
+        cb_sink,
+        poll_interval,
+        Arc::new(AtomicBool::new(false)),
+    ));
+
+    Ok(())
+}
@@ -1,7 +1,12 @@
 mod dialog;
+pub mod keymap;
+mod log_view;
 mod omnibox;
 mod pager;
+mod plist_editor;
+pub mod preset;
 pub mod root;
-mod service_list;
+pub mod service_list;
 mod sysinfo;
 mod table;
+pub mod theme;
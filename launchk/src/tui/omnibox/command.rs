@@ -1,5 +1,10 @@
-use xpc_sys::enums::{DomainType, SessionType};
+use crate::launchd::edit::PlistEdit;
+use crate::launchd::plist::LaunchdPlist;
+use crate::tui::preset::{Preset, PresetSort};
+use crate::tui::service_list::bulk::BulkAction;
+use crate::tui::service_list::columns::ColumnConfig;
 use std::fmt;
+use xpc_sys::enums::{DomainType, SessionType};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum OmniboxCommand {
@@ -16,7 +21,24 @@ pub enum OmniboxCommand {
     Reload,
     Enable(DomainType),
     Disable(DomainType),
+    MarkAll,
+    UnmarkAll,
+    // (action, domain and session for marked jobs launchd has none for)
+    BulkRequest(BulkAction, Option<(DomainType, Option<SessionType>)>),
+    // (action, confirmed labels, domain and session for jobs without one)
+    Bulk(
+        BulkAction,
+        Vec<String>,
+        Option<(DomainType, Option<SessionType>)>,
+    ),
     Edit,
+    TreeEditRequest,
+    // (unit label, plist to open in tree editor)
+    TreeEdit(String, LaunchdPlist),
+    // (unit label, changes to diff before writing)
+    ReviewEdit(String, PlistEdit),
+    UndoEditRequest,
+    UndoEdit,
     // (message, on ok)
     Confirm(String, Vec<OmniboxCommand>),
     // (unit label, prompt for domain only?, action gen fn)
@@ -30,6 +52,33 @@ pub enum OmniboxCommand {
     DumpState,
     DumpJetsamPropertiesCategory,
     ProcInfo,
+    Schedule,
+    Systemd,
+    Crontab,
+    FromSystemd,
+    FromCrontab,
+    Graph,
+    Health,
+    Detail,
+    ColumnsRequest,
+    // (current columns, for the picker to start from)
+    Columns(Vec<ColumnConfig>),
+    SetColumns(Vec<ColumnConfig>),
+    Presets,
+    ApplyPreset(Preset),
+    PresetSaveRequest,
+    // (the table's current sort, to save with the omnibox filters)
+    PresetSave(Option<PresetSort>),
+    Theme,
+    LogsRequest,
+    // (unit label, plist with the log paths)
+    Logs(String, LaunchdPlist),
+    ExportRequest,
+    // (unit label, plist to export)
+    Export(String, LaunchdPlist),
+    ImportRequest,
+    // (unit label, plist to replace with the import)
+    Import(String, LaunchdPlist),
     Help,
     Quit,
 }
@@ -40,25 +89,25 @@ impl fmt::Display for OmniboxCommand {
     }
 }
 
-pub static OMNIBOX_COMMANDS: [(&str, &str, OmniboxCommand); 12] = [
+pub static OMNIBOX_COMMANDS: [(&str, &str, OmniboxCommand); 31] = [
     (
         "load",
-        "▶️  Load highlighted job",
+        "▶️  Load highlighted (or marked) jobs",
         OmniboxCommand::LoadRequest,
     ),
     (
         "unload",
-        "⏏️  Unload highlighted job",
+        "⏏️  Unload highlighted (or marked) jobs",
         OmniboxCommand::UnloadRequest,
     ),
     (
         "enable",
-        "▶️  Enable highlighted job (enables load)",
+        "▶️  Enable highlighted (or marked) jobs (enables load)",
         OmniboxCommand::EnableRequest,
     ),
     (
         "disable",
-        "⏏️  Disable highlighted job (prevents load)",
+        "⏏️  Disable highlighted (or marked) jobs (prevents load)",
         OmniboxCommand::DisableRequest,
     ),
     (
@@ -66,11 +115,31 @@ pub static OMNIBOX_COMMANDS: [(&str, &str, OmniboxCommand); 12] = [
         "✍️  Edit plist with $EDITOR, then reload job",
         OmniboxCommand::Edit,
     ),
+    (
+        "tree",
+        "🌲  Edit plist in tree editor, then reload job",
+        OmniboxCommand::TreeEditRequest,
+    ),
+    (
+        "undo-edit",
+        "↩️  Restore the plist backed up by the last edit",
+        OmniboxCommand::UndoEditRequest,
+    ),
     (
         "reload",
-        "🔄  Reload highlighted job",
+        "🔄  Reload highlighted (or marked) jobs",
         OmniboxCommand::Reload,
     ),
+    (
+        "mark-all",
+        "☑️  Mark every job the filters show (space marks one)",
+        OmniboxCommand::MarkAll,
+    ),
+    (
+        "unmark-all",
+        "🔲  Clear marks, actions go back to the highlighted job",
+        OmniboxCommand::UnmarkAll,
+    ),
     ("csrinfo", "ℹ️  See all CSR flags", OmniboxCommand::CSRInfo),
     (
         "dumpstate",
@@ -87,6 +156,81 @@ pub static OMNIBOX_COMMANDS: [(&str, &str, OmniboxCommand); 12] = [
         "ℹ️  launchctl procinfo for highlighted process",
         OmniboxCommand::ProcInfo,
     ),
+    (
+        "schedule",
+        "🗓  Next StartCalendarInterval runs for highlighted job",
+        OmniboxCommand::Schedule,
+    ),
+    (
+        "graph",
+        "🕸  Jobs sharing Mach services, sockets, paths or programs with highlighted job",
+        OmniboxCommand::Graph,
+    ),
+    (
+        "health",
+        "🩺  Missing programs, directories and watched paths for highlighted job",
+        OmniboxCommand::Health,
+    ),
+    (
+        "detail",
+        "🔎  Show or hide the highlighted job's detail pane",
+        OmniboxCommand::Detail,
+    ),
+    (
+        "columns",
+        "🏛  Choose, order and size service list columns",
+        OmniboxCommand::ColumnsRequest,
+    ),
+    (
+        "preset",
+        "🔖  Recall a saved filter and sort (or press its number)",
+        OmniboxCommand::Presets,
+    ),
+    (
+        "preset-save",
+        "💾  Save the current filter and sort as a preset",
+        OmniboxCommand::PresetSaveRequest,
+    ),
+    (
+        "theme",
+        "🎨  Switch colour theme for this session",
+        OmniboxCommand::Theme,
+    ),
+    (
+        "logs",
+        "📜  Follow highlighted job's StandardOutPath and StandardErrorPath",
+        OmniboxCommand::LogsRequest,
+    ),
+    (
+        "systemd",
+        "🐧  Show highlighted job as systemd units (opens in $PAGER)",
+        OmniboxCommand::Systemd,
+    ),
+    (
+        "crontab",
+        "🕰  Show highlighted job as crontab lines (opens in $PAGER)",
+        OmniboxCommand::Crontab,
+    ),
+    (
+        "from-systemd",
+        "🐧  New job from a systemd .service (and its .timer)",
+        OmniboxCommand::FromSystemd,
+    ),
+    (
+        "from-crontab",
+        "🕰  New job from a crontab line",
+        OmniboxCommand::FromCrontab,
+    ),
+    (
+        "export",
+        "📤  Export highlighted job's plist as JSON, YAML or TOML",
+        OmniboxCommand::ExportRequest,
+    ),
+    (
+        "import",
+        "📥  Replace plist from JSON, YAML or TOML, then reload job",
+        OmniboxCommand::ImportRequest,
+    ),
     ("help", "🤔  Show all commands", OmniboxCommand::Help),
     ("exit", "🚪 see ya!", OmniboxCommand::Quit),
 ];
@@ -3,12 +3,14 @@ use std::time::SystemTime;
 use crate::launchd::job_type_filter::JobTypeFilter;
 use crate::tui::omnibox::command::{OmniboxCommand, OMNIBOX_COMMANDS};
 use crate::tui::omnibox::view::OmniboxMode;
+use crate::tui::service_list::filter::FilterMode;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct OmniboxState {
     pub mode: OmniboxMode,
     pub tick: SystemTime,
     pub label_filter: String,
+    pub filter_mode: FilterMode,
     pub command_filter: String,
     pub job_type_filter: JobTypeFilter,
 }
@@ -26,6 +28,7 @@ impl OmniboxState {
             tick: SystemTime::now(),
             mode: mode.unwrap_or(self.mode.clone()),
             label_filter: label_filter.unwrap_or(self.label_filter.clone()),
+            filter_mode: self.filter_mode,
             command_filter: command_filter.unwrap_or(self.command_filter.clone()),
             job_type_filter: job_type_filter.unwrap_or(self.job_type_filter.clone()),
         }
@@ -57,6 +60,7 @@ impl Default for OmniboxState {
             mode: OmniboxMode::Idle,
             tick: SystemTime::now(),
             label_filter: "".to_string(),
+            filter_mode: FilterMode::default(),
             command_filter: "".to_string(),
             job_type_filter: JobTypeFilter::launchk_default(),
         }
@@ -7,13 +7,17 @@ use tokio::runtime::Handle;
 use tokio::time::interval;
 
 use cursive::direction::Direction;
-use cursive::event::{Event, EventResult, Key};
-use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
 use cursive::{Printer, Vec2, View, XY};
 
 use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::keymap::KeyAction;
 use crate::tui::omnibox::command::OmniboxCommand;
 use crate::tui::omnibox::state::OmniboxState;
+use crate::tui::preset::Preset;
+use crate::tui::service_list::filter::FilterMode;
+use crate::tui::service_list::query::Query;
+use crate::tui::theme;
 
 /// Consumers impl OmniboxSubscriber receive these events
 /// via a channel in a wrapped view
@@ -38,9 +42,9 @@ pub enum OmniboxMode {
     Idle,
 }
 
-/// Move OmniboxState back to idle some time after the user stops
+/// Move OmniboxState back to idle some time (`idle`) after the user stops
 /// interacting with it
-async fn tick(state: Arc<RwLock<OmniboxState>>, tx: Sender<OmniboxEvent>) {
+async fn tick(state: Arc<RwLock<OmniboxState>>, tx: Sender<OmniboxEvent>, idle: Duration) {
     let mut tick_rate = interval(Duration::from_millis(250));
 
     loop {
@@ -55,7 +59,7 @@ async fn tick(state: Arc<RwLock<OmniboxState>>, tx: Sender<OmniboxEvent>) {
         if let OmniboxMode::CommandConfirm(cmd) = mode {
             tx.send(OmniboxEvent::Command(cmd.clone()))
                 .expect("Must confirm command");
-        } else if *mode == OmniboxMode::Idle || tick.elapsed().unwrap() < Duration::from_secs(2) {
+        } else if *mode == OmniboxMode::Idle || tick.elapsed().unwrap() < idle {
             continue;
         }
 
@@ -89,30 +93,112 @@ pub struct OmniboxView {
     state: Arc<RwLock<OmniboxState>>,
     tx: Sender<OmniboxEvent>,
     last_size: RefCell<XY<usize>>,
+    /// What the omnibox starts with, and goes back to on `clear`
+    defaults: OmniboxState,
 }
 
 impl OmniboxView {
     /// Create a new Omnibox and receive its rx on create
-    pub fn new(handle: &Handle) -> (Self, Sender<OmniboxEvent>, Receiver<OmniboxEvent>) {
+    pub fn new(
+        handle: &Handle,
+        defaults: OmniboxState,
+        idle: Duration,
+    ) -> (Self, Sender<OmniboxEvent>, Receiver<OmniboxEvent>) {
         let (tx, rx): (Sender<OmniboxEvent>, Receiver<OmniboxEvent>) = channel();
-        let state = Arc::new(RwLock::new(OmniboxState::default()));
+        let state = Arc::new(RwLock::new(defaults.clone()));
 
         let tx_state = state.clone();
         let tx_tick = tx.clone();
 
-        handle.spawn(tick(tx_state, tx_tick));
+        handle.spawn(tick(tx_state, tx_tick, idle));
 
         (
             Self {
                 state,
                 tx: tx.clone(),
                 last_size: RefCell::new(XY::new(0, 0)),
+                defaults,
             },
             tx,
             rx,
         )
     }
 
+    pub fn state(&self) -> OmniboxState {
+        self.state.read().expect("Must read state").clone()
+    }
+
+    /// Replace the label filter, its mode and the job type filter
+    pub fn apply_preset(&mut self, preset: &Preset) {
+        let mut write = self.state.write().expect("Must write state");
+
+        let new_state = OmniboxState {
+            filter_mode: preset.filter_mode,
+            ..write.with_new(
+                Some(OmniboxMode::Idle),
+                Some(preset.filter.clone()),
+                Some("".to_string()),
+                Some(preset.job_type_filter()),
+            )
+        };
+
+        self.tx
+            .send(OmniboxEvent::StateUpdate(new_state.clone()))
+            .expect("Must send state");
+
+        *write = new_state;
+    }
+
+    /// Whether keys are being typed into the filter or command
+    pub fn is_typing(&self) -> bool {
+        let mode = &self.state.read().expect("Must read state").mode;
+        *mode == OmniboxMode::LabelFilter || *mode == OmniboxMode::CommandFilter
+    }
+
+    /// Modes, clearing and job type toggles, from the keymap
+    pub fn apply_action(&mut self, action: &KeyAction) -> EventResult {
+        let state = self.state.read().expect("Must read state");
+
+        let new_state = match action {
+            KeyAction::CommandMode => {
+                Some(state.with_new(Some(OmniboxMode::CommandFilter), None, None, None))
+            }
+            KeyAction::FilterMode => Some(state.with_new(
+                Some(OmniboxMode::LabelFilter),
+                None,
+                Some("".to_string()),
+                None,
+            )),
+            KeyAction::Clear => {
+                self.tx
+                    .send(OmniboxEvent::Command(OmniboxCommand::FocusServiceList))
+                    .expect("Must focus");
+                Some(self.defaults.with_new(None, None, None, None))
+            }
+            KeyAction::JobType(mask) => Some(Self::toggle_job_type(*mask, &*state)),
+            KeyAction::Command(_) => None,
+        };
+
+        drop(state);
+        self.set_state(new_state)
+    }
+
+    fn set_state(&mut self, new_state: Option<OmniboxState>) -> EventResult {
+        let new_state = match new_state {
+            Some(new_state) => new_state,
+            None => return EventResult::Ignored,
+        };
+
+        self.tx
+            .send(OmniboxEvent::StateUpdate(new_state.clone()))
+            .expect("Must send state");
+
+        let mut write = self.state.write().expect("Must write state");
+        *write = new_state;
+
+        EventResult::Consumed(None)
+    }
+
     /// Commands
     fn handle_active(event: &Event, state: &OmniboxState) -> Option<OmniboxState> {
         let OmniboxState {
@@ -141,7 +227,6 @@ impl OmniboxView {
         };
 
         match (event, mode) {
-            (ev, OmniboxMode::JobTypeFilter) => Self::handle_job_type_filter(ev, state),
             // User -> string filters
             (Event::Char(_), OmniboxMode::LabelFilter)
             | (Event::Char(_), OmniboxMode::CommandFilter) => {
@@ -159,6 +244,11 @@ impl OmniboxView {
                 cf.truncate(cf.len() - 1);
                 Some(state.with_new(None, None, Some(cf), None))
             }
+            // Cycle exact, fuzzy, regex and query matching
+            (Event::Key(Key::Tab), OmniboxMode::LabelFilter) => Some(OmniboxState {
+                filter_mode: state.filter_mode.next(),
+                ..state.with_new(None, None, None, None)
+            }),
             // Hit enter to shortcut to complete + submit
             (Event::Key(Key::Enter), OmniboxMode::CommandFilter) if suggested_command.is_some() => {
                 let (name, _, cmd) = suggested_command.unwrap();
@@ -191,21 +281,12 @@ impl OmniboxView {
         }
     }
 
-    /// Toggle bitmask on key
-    fn handle_job_type_filter(event: &Event, state: &OmniboxState) -> Option<OmniboxState> {
+    /// Toggle bitmask
+    fn toggle_job_type(mask: JobTypeFilter, state: &OmniboxState) -> OmniboxState {
         let mut jtf = state.job_type_filter.clone();
+        jtf.toggle(mask);
 
-        match event {
-            Event::Char('s') => jtf.toggle(JobTypeFilter::SYSTEM),
-            Event::Char('g') => jtf.toggle(JobTypeFilter::GLOBAL),
-            Event::Char('u') => jtf.toggle(JobTypeFilter::USER),
-            Event::Char('a') => jtf.toggle(JobTypeFilter::AGENT),
-            Event::Char('d') => jtf.toggle(JobTypeFilter::DAEMON),
-            Event::Char('l') => jtf.toggle(JobTypeFilter::LOADED),
-            _ => return None,
-        };
-
-        Some(state.with_new(Some(OmniboxMode::JobTypeFilter), None, None, Some(jtf)))
+        state.with_new(Some(OmniboxMode::JobTypeFilter), None, None, Some(jtf))
     }
 
     fn draw_command_header(&self, printer: &Printer<'_, '_>) {
@@ -213,28 +294,30 @@ impl OmniboxView {
         let OmniboxState {
             command_filter,
             label_filter,
+            filter_mode,
             mode,
             ..
         } = &*read;
 
+        let filter_header = format!("Filter ({}) > ", filter_mode);
         let cmd_header = match *mode {
-            OmniboxMode::LabelFilter => "Filter > ",
+            OmniboxMode::LabelFilter => filter_header.as_str(),
             OmniboxMode::CommandFilter => "Command > ",
             OmniboxMode::CommandConfirm(_) => "OK! > ",
-            _ if command_filter.len() < 1 && label_filter.len() > 0 => "Filter > ",
+            _ if command_filter.len() < 1 && label_filter.len() > 0 => filter_header.as_str(),
             _ => "",
         };
 
-        let subtle = Style::from(Color::Light(BaseColor::Black));
-        let purple = Style::from(Color::Light(BaseColor::Blue));
+        let roles = theme::roles();
 
         let modal_hilight = if let OmniboxMode::Idle = mode {
-            subtle
+            roles.subtle
         } else {
-            purple
+            roles.accent
         };
 
-        let visible_filter = if command_filter.len() > 0 || *mode == OmniboxMode::CommandFilter {
+        let showing_command = command_filter.len() > 0 || *mode == OmniboxMode::CommandFilter;
+        let visible_filter = if showing_command {
             command_filter
         } else {
             label_filter
@@ -251,6 +334,18 @@ impl OmniboxView {
             let sub = printer.offset(XY::new(cmd_header.len() + visible_filter.len(), 0));
             self.draw_command_suggestion(&sub);
         };
+
+        // Print why the query is not applied yet
+        if !showing_command && *filter_mode == FilterMode::Query && !label_filter.is_empty() {
+            if let Err(e) = Query::parse(label_filter) {
+                printer.with_style(roles.error, |p| {
+                    p.print(
+                        XY::new(cmd_header.len() + visible_filter.len() + 1, 0),
+                        format!("✘ {}", e).as_str(),
+                    )
+                });
+            }
+        }
     }
 
     fn draw_command_suggestion(&self, printer: &Printer<'_, '_>) {
@@ -263,7 +358,7 @@ impl OmniboxView {
         let (cmd, desc, ..) = suggestion.unwrap();
         let cmd_string = cmd.to_string().replacen(&state.command_filter, "", 1);
 
-        printer.with_style(Style::from(Color::Light(BaseColor::Black)), |p| {
+        printer.with_style(theme::roles().subtle, |p| {
             p.print(XY::new(0, 0), cmd_string.as_str())
         });
 
@@ -271,29 +366,25 @@ impl OmniboxView {
         printer.print(XY::new(start, 0), format!("-- {}", desc).as_str());
     }
 
-    fn draw_job_type_filter(&self, printer: &Printer<'_, '_>) {
-        let read = self.state.read().expect("Must read state");
-        let OmniboxState {
-            job_type_filter,
-            mode,
-            ..
-        } = &*read;
+    /// Where each job type toggle is drawn: (mask, x, text), and the x of
+    /// the closing bracket. Shared by drawing and clicking
+    fn job_type_toggles(&self, mode: &OmniboxMode) -> (Vec<(JobTypeFilter, usize, String)>, usize) {
+        let expanded = *mode == OmniboxMode::JobTypeFilter;
 
-        let mut jtf_ofs = if *mode != OmniboxMode::JobTypeFilter {
-            "[sguadl]".len()
+        let mut jtf_ofs = if !expanded {
+            "[sguadlb]".len()
         } else {
-            "[system global user agent daemon loaded]".len()
+            "[system global user agent daemon loaded broken]".len()
         };
 
         if jtf_ofs < self.last_size.borrow().x {
             jtf_ofs = self.last_size.borrow().x - jtf_ofs;
         }
 
-        printer.print(XY::new(jtf_ofs, 0), "[");
+        // Past the [
         jtf_ofs += 1;
 
-        let inactive = Style::from(Color::Light(BaseColor::Black));
-        let active = Style::from(Color::Light(BaseColor::Blue)).combine(Effect::Bold);
+        let mut toggles = vec![];
 
         for mask in [
             JobTypeFilter::SYSTEM,
@@ -302,32 +393,83 @@ impl OmniboxView {
             JobTypeFilter::AGENT,
             JobTypeFilter::DAEMON,
             JobTypeFilter::LOADED,
+            JobTypeFilter::BROKEN,
         ]
         .iter()
         {
             let mut mask_string = format!("{:?} ", mask).to_ascii_lowercase();
-            if *mode != OmniboxMode::JobTypeFilter {
+            if !expanded {
                 mask_string.truncate(1);
             }
 
             // No space at end if expanded
-            if *mask == JobTypeFilter::LOADED && *mode == OmniboxMode::JobTypeFilter {
+            if *mask == JobTypeFilter::BROKEN && expanded {
                 mask_string.truncate(mask_string.len() - 1);
             }
 
+            let len = mask_string.len();
+            toggles.push((*mask, jtf_ofs, mask_string));
+            jtf_ofs += len;
+        }
+
+        (toggles, jtf_ofs)
+    }
+
+    fn draw_job_type_filter(&self, printer: &Printer<'_, '_>) {
+        let read = self.state.read().expect("Must read state");
+        let OmniboxState {
+            job_type_filter,
+            mode,
+            ..
+        } = &*read;
+
+        let (toggles, end) = self.job_type_toggles(mode);
+        let roles = theme::roles();
+
+        if let Some((_, start, _)) = toggles.first() {
+            printer.print(XY::new(start - 1, 0), "[");
+        }
+
+        for (mask, x, mask_string) in toggles.iter() {
             let style = if job_type_filter.contains(*mask) {
-                active
+                roles.active
             } else {
-                inactive
+                roles.subtle
             };
 
-            printer.with_style(style, |p| {
-                p.print(XY::new(jtf_ofs, 0), mask_string.as_str())
-            });
-            jtf_ofs += mask_string.len();
+            printer.with_style(style, |p| p.print(XY::new(*x, 0), mask_string.as_str()));
         }
 
-        printer.print(XY::new(jtf_ofs, 0), "]");
+        printer.print(XY::new(end, 0), "]");
+    }
+
+    /// Clicking a job type toggles it, clicking anywhere else hands the
+    /// keys back to the service list
+    fn on_click(&mut self, x: usize) -> EventResult {
+        let state = self.state.read().expect("Must read state");
+
+        let (toggles, _) = self.job_type_toggles(&state.mode);
+        let clicked = toggles
+            .into_iter()
+            .find(|(_, start, text)| x >= *start && x < start + text.trim_end().len());
+
+        let new_state = match clicked {
+            Some((mask, ..)) => Some(Self::toggle_job_type(mask, &*state)),
+            None if state.mode == OmniboxMode::Idle => {
+                self.tx
+                    .send(OmniboxEvent::Command(OmniboxCommand::FocusServiceList))
+                    .expect("Must focus");
+                None
+            }
+            None => None,
+        };
+
+        drop(state);
+
+        match self.set_state(new_state) {
+            EventResult::Ignored => EventResult::Consumed(None),
+            res => res,
+        }
     }
 }
 
@@ -341,51 +483,41 @@ impl View for OmniboxView {
         self.last_size.replace(sz);
     }
 
+    /// Typing, Esc and the keys for completing commands. Everything else
+    /// comes from the keymap as a `KeyAction`
     fn on_event(&mut self, event: Event) -> EventResult {
         let state = self.state.read().expect("Must read state");
         let mode = &state.mode;
 
+        if let Event::Mouse {
+            offset,
+            position,
+            event: MouseEvent::Press(MouseButton::Left),
+        } = event
+        {
+            drop(state);
+
+            return match position.checked_sub(offset) {
+                Some(pos) if pos.y == 0 => self.on_click(pos.x),
+                _ => EventResult::Ignored,
+            };
+        }
+
         let new_state = match (event, mode) {
-            (Event::CtrlChar('u'), _) => {
-                self.tx
-                    .send(OmniboxEvent::Command(OmniboxCommand::FocusServiceList))
-                    .expect("Must focus");
-                Some(OmniboxState::default())
-            }
             (Event::Key(Key::Esc), _) => {
                 self.tx
                     .send(OmniboxEvent::Command(OmniboxCommand::FocusServiceList))
                     .expect("Must focus");
                 Some(state.with_new(Some(OmniboxMode::Idle), None, Some("".to_string()), None))
             }
-            (Event::Char(':'), _) => {
-                Some(state.with_new(Some(OmniboxMode::CommandFilter), None, None, None))
+            (e, OmniboxMode::LabelFilter) | (e, OmniboxMode::CommandFilter) => {
+                Self::handle_active(&e, &*state)
             }
-            (Event::Char('/'), _) => Some(state.with_new(
-                Some(OmniboxMode::LabelFilter),
-                None,
-                Some("".to_string()),
-                None,
-            )),
-            (e, OmniboxMode::Idle) => Self::handle_job_type_filter(&e, &*state),
-            (e, _) => Self::handle_active(&e, &*state),
+            _ => None,
         };
 
-        if new_state.is_none() {
-            return EventResult::Ignored;
-        }
-
-        let new_state = new_state.unwrap();
-
-        self.tx
-            .send(OmniboxEvent::StateUpdate(new_state.clone()))
-            .expect("Must send state");
-
         drop(state);
-        let mut write = self.state.write().expect("Must write state");
-        *write = new_state;
-
-        EventResult::Consumed(None)
+        self.set_state(new_state)
     }
 
     fn take_focus(&mut self, _: Direction) -> bool {
@@ -1,4 +1,3 @@
-use std::env;
 use std::io::Write;
 use std::process::{Command, Stdio};
 use std::sync::mpsc::Sender;
@@ -6,17 +5,23 @@ use std::sync::mpsc::Sender;
 use super::root::CbSinkMessage;
 use cursive::Cursive;
 
-lazy_static! {
-    static ref PAGER: String = env::var("PAGER").unwrap_or("less".to_string());
-}
+/// Show the pager (program and arguments, see `Config::pager`), write buf,
+/// and clear Cursive after exiting
+pub fn show_pager(
+    cbsink: &Sender<CbSinkMessage>,
+    pager: &[String],
+    buf: &[u8],
+) -> Result<(), String> {
+    let (program, args) = pager
+        .split_first()
+        .ok_or_else(|| "No pager set".to_string())?;
 
-/// Show $PAGER (or less), write buf, and clear Cursive after exiting
-pub fn show_pager(cbsink: &Sender<CbSinkMessage>, buf: &[u8]) -> Result<(), String> {
     cbsink
         .send(Box::new(Cursive::clear))
         .expect("Must clear before");
 
-    let mut pager = Command::new(&*PAGER)
+    let mut pager = Command::new(program)
+        .args(args)
         .stdin(Stdio::piped())
         .spawn()
         .map_err(|e| e.to_string())?;
@@ -38,6 +43,6 @@ pub fn show_pager(cbsink: &Sender<CbSinkMessage>, buf: &[u8]) -> Result<(), Stri
     if res.success() {
         Ok(())
     } else {
-        Err(format!("{} exited {:?}", &*PAGER, res))
+        Err(format!("{} exited {:?}", program, res))
     }
 }
@@ -0,0 +1,2 @@
+mod tree;
+pub mod view;
@@ -0,0 +1,409 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::launchd::known_keys::PlistKind;
+
+/// One step from a container to its child
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum PlistPathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for PlistPathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlistPathSegment::Key(k) => write!(f, "{}", k),
+            PlistPathSegment::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
+/// Location of a value inside the root dictionary
+pub type PlistPath = Vec<PlistPathSegment>;
+
+/// A visible line in the tree editor
+#[derive(Debug, Clone)]
+pub struct PlistTreeRow {
+    pub path: PlistPath,
+    pub kind: Option<PlistKind>,
+    pub expanded: bool,
+    pub text: String,
+}
+
+pub fn get<'a>(plist: &'a plist::Value, path: &[PlistPathSegment]) -> Option<&'a plist::Value> {
+    path.iter()
+        .try_fold(plist, |value, segment| match (value, segment) {
+            (plist::Value::Dictionary(d), PlistPathSegment::Key(k)) => d.get(k),
+            (plist::Value::Array(a), PlistPathSegment::Index(i)) => a.get(*i),
+            _ => None,
+        })
+}
+
+pub fn get_mut<'a>(
+    plist: &'a mut plist::Value,
+    path: &[PlistPathSegment],
+) -> Option<&'a mut plist::Value> {
+    path.iter()
+        .try_fold(plist, |value, segment| match (value, segment) {
+            (plist::Value::Dictionary(d), PlistPathSegment::Key(k)) => d.get_mut(k),
+            (plist::Value::Array(a), PlistPathSegment::Index(i)) => a.get_mut(*i),
+            _ => None,
+        })
+}
+
+/// Replace the value at path
+pub fn set(
+    plist: &mut plist::Value,
+    path: &[PlistPathSegment],
+    value: plist::Value,
+) -> Result<(), String> {
+    let target = get_mut(plist, path).ok_or_else(|| "Cannot find value".to_string())?;
+    *target = value;
+    Ok(())
+}
+
+/// Remove the value at path, returning it
+pub fn remove(plist: &mut plist::Value, path: &[PlistPathSegment]) -> Option<plist::Value> {
+    let (last, parent) = path.split_last()?;
+
+    match (get_mut(plist, parent)?, last) {
+        (plist::Value::Dictionary(d), PlistPathSegment::Key(k)) => d.remove(k),
+        (plist::Value::Array(a), PlistPathSegment::Index(i)) if *i < a.len() => Some(a.remove(*i)),
+        _ => None,
+    }
+}
+
+/// Add a child to the container at parent. Dictionaries need a key
+/// that isn't already present, arrays append. Returns the new path.
+pub fn insert(
+    plist: &mut plist::Value,
+    parent: &[PlistPathSegment],
+    key: Option<String>,
+    value: plist::Value,
+) -> Result<PlistPath, String> {
+    let container = get_mut(plist, parent).ok_or_else(|| "Cannot find parent".to_string())?;
+    let mut path = parent.to_vec();
+
+    match (container, key) {
+        (plist::Value::Dictionary(d), Some(k)) => {
+            if k.is_empty() {
+                return Err("Key cannot be empty".to_string());
+            }
+
+            if d.contains_key(&k) {
+                return Err(format!("{} already exists", k));
+            }
+
+            d.insert(k.clone(), value);
+            path.push(PlistPathSegment::Key(k));
+        }
+        (plist::Value::Dictionary(_), None) => return Err("Must provide a key".to_string()),
+        (plist::Value::Array(a), _) => {
+            a.push(value);
+            path.push(PlistPathSegment::Index(a.len() - 1));
+        }
+        _ => return Err("Can only add to a dictionary or array".to_string()),
+    }
+
+    Ok(path)
+}
+
+/// Rows for every value whose ancestors are all expanded
+pub fn flatten(plist: &plist::Value, expanded: &HashSet<PlistPath>) -> Vec<PlistTreeRow> {
+    let mut rows = vec![];
+    flatten_into(plist, &mut vec![], expanded, &mut rows);
+    rows
+}
+
+fn flatten_into(
+    value: &plist::Value,
+    path: &mut PlistPath,
+    expanded: &HashSet<PlistPath>,
+    rows: &mut Vec<PlistTreeRow>,
+) {
+    let children: Vec<(PlistPathSegment, &plist::Value)> = match value {
+        plist::Value::Dictionary(d) => d
+            .iter()
+            .map(|(k, v)| (PlistPathSegment::Key(k.clone()), v))
+            .collect(),
+        plist::Value::Array(a) => a
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (PlistPathSegment::Index(i), v))
+            .collect(),
+        _ => return,
+    };
+
+    for (segment, child) in children {
+        path.push(segment.clone());
+
+        let kind = PlistKind::of(child);
+        let is_container = kind.map(|k| k.is_container()).unwrap_or(false);
+        let is_expanded = is_container && expanded.contains(path);
+
+        let marker = match (is_container, is_expanded) {
+            (true, true) => "▾",
+            (true, false) => "▸",
+            _ => " ",
+        };
+
+        let text = format!(
+            "{}{} {}: {}",
+            "  ".repeat(path.len() - 1),
+            marker,
+            segment,
+            summarize(child)
+        );
+
+        rows.push(PlistTreeRow {
+            path: path.clone(),
+            kind,
+            expanded: is_expanded,
+            text,
+        });
+
+        if is_expanded {
+            flatten_into(child, path, expanded, rows);
+        }
+
+        path.pop();
+    }
+}
+
+/// Short description of a value for its tree row
+pub fn summarize(value: &plist::Value) -> String {
+    match value {
+        plist::Value::Dictionary(d) => format!("{{{} keys}}", d.len()),
+        plist::Value::Array(a) => format!("[{} items]", a.len()),
+        plist::Value::String(s) => format!("\"{}\"", s),
+        v => {
+            let kind = PlistKind::of(v)
+                .map(|k| k.to_string())
+                .unwrap_or("uid".to_string());
+            format!("{} ({})", display_scalar(v), kind)
+        }
+    }
+}
+
+/// Text for a scalar, as it should appear in an input
+pub fn display_scalar(value: &plist::Value) -> String {
+    match value {
+        plist::Value::String(s) => s.clone(),
+        plist::Value::Boolean(b) => b.to_string(),
+        plist::Value::Real(r) => r.to_string(),
+        plist::Value::Integer(i) => i
+            .as_signed()
+            .map(|s| s.to_string())
+            .or(i.as_unsigned().map(|u| u.to_string()))
+            .unwrap_or_default(),
+        plist::Value::Data(d) => d.iter().map(|b| format!("{:02x}", b)).collect(),
+        v => format!("{:?}", v),
+    }
+}
+
+/// Parse user input into a scalar of the given kind
+pub fn parse_scalar(kind: PlistKind, input: &str) -> Result<plist::Value, String> {
+    let trimmed = input.trim();
+
+    match kind {
+        PlistKind::String => Ok(plist::Value::String(input.to_string())),
+        PlistKind::Boolean => trimmed
+            .parse::<bool>()
+            .map(plist::Value::Boolean)
+            .map_err(|_| format!("{} is not true or false", trimmed)),
+        PlistKind::Integer => trimmed
+            .parse::<i64>()
+            .map(plist::Value::from)
+            .or_else(|_| trimmed.parse::<u64>().map(plist::Value::from))
+            .map_err(|_| format!("{} is not an integer", trimmed)),
+        PlistKind::Real => trimmed
+            .parse::<f64>()
+            .map(plist::Value::Real)
+            .map_err(|_| format!("{} is not a number", trimmed)),
+        PlistKind::Data => {
+            if trimmed.len() % 2 != 0 || !trimmed.is_ascii() {
+                return Err("Data must be an even number of hex digits".to_string());
+            }
+
+            (0..trimmed.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&trimmed[i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map(plist::Value::Data)
+                .map_err(|_| format!("{} is not hex", trimmed))
+        }
+        k => Err(format!("Cannot edit {} values inline", k)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{flatten, get, insert, parse_scalar, remove, PlistPath, PlistPathSegment};
+    use crate::launchd::known_keys::PlistKind;
+    use crate::launchd::test_util::job;
+
+    fn key(k: &str) -> PlistPathSegment {
+        PlistPathSegment::Key(k.to_string())
+    }
+
+    fn fixture() -> plist::Value {
+        job("<key>Label</key><string>com.example.job</string>\
+             <key>ProgramArguments</key><array><string>/bin/echo</string><string>hi</string></array>\
+             <key>Sockets</key><dict><key>Listener</key><dict><key>SockServiceName</key><string>8080</string></dict></dict>")
+    }
+
+    #[test]
+    fn tree_insert() {
+        let mut plist = fixture();
+
+        assert_eq!(
+            insert(&mut plist, &[], Some("RunAtLoad".to_string()), true.into()),
+            Ok(vec![key("RunAtLoad")])
+        );
+        assert_eq!(
+            get(&plist, &[key("RunAtLoad")]),
+            Some(&plist::Value::Boolean(true))
+        );
+
+        assert!(insert(&mut plist, &[], Some("Label".to_string()), "x".into()).is_err());
+        assert!(insert(&mut plist, &[], Some("".to_string()), "x".into()).is_err());
+        assert!(insert(&mut plist, &[], None, "x".into()).is_err());
+
+        // Arrays append, and ignore a key
+        let args = [key("ProgramArguments")];
+        assert_eq!(
+            insert(
+                &mut plist,
+                &args,
+                Some("ignored".to_string()),
+                "there".into()
+            ),
+            Ok(vec![key("ProgramArguments"), PlistPathSegment::Index(2)])
+        );
+        assert_eq!(
+            get(
+                &plist,
+                &[key("ProgramArguments"), PlistPathSegment::Index(2)]
+            ),
+            Some(&plist::Value::from("there"))
+        );
+
+        assert!(insert(&mut plist, &[key("Label")], None, "x".into()).is_err());
+        assert!(insert(&mut plist, &[key("Missing")], None, "x".into()).is_err());
+    }
+
+    #[test]
+    fn tree_remove() {
+        let mut plist = fixture();
+        let args = key("ProgramArguments");
+
+        assert_eq!(
+            remove(&mut plist, &[args.clone(), PlistPathSegment::Index(0)]),
+            Some(plist::Value::from("/bin/echo"))
+        );
+        assert_eq!(
+            get(&plist, &[args.clone(), PlistPathSegment::Index(0)]),
+            Some(&plist::Value::from("hi"))
+        );
+        assert_eq!(
+            remove(&mut plist, &[args.clone(), PlistPathSegment::Index(1)]),
+            None
+        );
+        assert_eq!(
+            remove(&mut plist, &[key("Label")]),
+            Some(plist::Value::from("com.example.job"))
+        );
+        assert_eq!(remove(&mut plist, &[key("Label")]), None);
+        assert_eq!(remove(&mut plist, &[]), None);
+    }
+
+    #[test]
+    fn tree_flatten() {
+        let plist = fixture();
+        let texts = |expanded: &HashSet<PlistPath>| -> Vec<String> {
+            flatten(&plist, expanded)
+                .into_iter()
+                .map(|row| row.text)
+                .collect()
+        };
+
+        assert_eq!(
+            texts(&HashSet::new()),
+            vec![
+                "  Label: \"com.example.job\"",
+                "▸ ProgramArguments: [2 items]",
+                "▸ Sockets: {1 keys}",
+            ]
+        );
+
+        // Children of collapsed containers stay hidden
+        let mut expanded = HashSet::new();
+        expanded.insert(vec![key("Sockets"), key("Listener")]);
+        assert_eq!(texts(&expanded).len(), 3);
+
+        expanded.insert(vec![key("ProgramArguments")]);
+        expanded.insert(vec![key("Sockets")]);
+        assert_eq!(
+            texts(&expanded),
+            vec![
+                "  Label: \"com.example.job\"",
+                "▾ ProgramArguments: [2 items]",
+                "    [0]: \"/bin/echo\"",
+                "    [1]: \"hi\"",
+                "▾ Sockets: {1 keys}",
+                "  ▾ Listener: {1 keys}",
+                "      SockServiceName: \"8080\"",
+            ]
+        );
+
+        let rows = flatten(&plist, &expanded);
+        assert_eq!(
+            rows[3].path,
+            vec![key("ProgramArguments"), PlistPathSegment::Index(1)]
+        );
+        assert_eq!(rows[5].kind, Some(PlistKind::Dictionary));
+        assert!(rows[5].expanded);
+    }
+
+    #[test]
+    fn tree_parse_scalar() {
+        assert_eq!(
+            parse_scalar(PlistKind::Data, " 00ff1A "),
+            Ok(plist::Value::Data(vec![0x00, 0xff, 0x1a]))
+        );
+        assert!(parse_scalar(PlistKind::Data, "abc").is_err());
+        assert!(parse_scalar(PlistKind::Data, "zz").is_err());
+        assert!(parse_scalar(PlistKind::Data, "é").is_err());
+
+        assert_eq!(
+            parse_scalar(PlistKind::Integer, "-5"),
+            Ok(plist::Value::from(-5_i64))
+        );
+        assert_eq!(
+            parse_scalar(PlistKind::Integer, "18446744073709551615"),
+            Ok(plist::Value::from(u64::MAX))
+        );
+        assert!(parse_scalar(PlistKind::Integer, "1.5").is_err());
+
+        assert_eq!(
+            parse_scalar(PlistKind::Boolean, " true "),
+            Ok(plist::Value::Boolean(true))
+        );
+        assert!(parse_scalar(PlistKind::Boolean, "yes").is_err());
+
+        assert_eq!(
+            parse_scalar(PlistKind::Real, "0.25"),
+            Ok(plist::Value::Real(0.25))
+        );
+        // Strings keep their spaces
+        assert_eq!(
+            parse_scalar(PlistKind::String, " a "),
+            Ok(plist::Value::from(" a "))
+        );
+        assert!(parse_scalar(PlistKind::Date, "2021-01-01").is_err());
+        assert!(parse_scalar(PlistKind::Array, "").is_err());
+    }
+}
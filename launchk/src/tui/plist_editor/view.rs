@@ -0,0 +1,467 @@
+use std::collections::HashSet;
+use std::sync::mpsc::Sender;
+
+use cursive::event::{Event, EventResult, Key};
+use cursive::traits::{Nameable, Resizable, Scrollable};
+use cursive::view::ViewWrapper;
+use cursive::views::{Dialog, DummyView, EditView, LinearLayout, SelectView, TextView};
+use cursive::{Cursive, View};
+
+use crate::launchd::edit::PlistEdit;
+use crate::launchd::known_keys::{PlistKind, KNOWN_KEYS, PLIST_KINDS};
+use crate::launchd::plist::LaunchdPlist;
+use crate::launchd::privileged::privileged_helper;
+use crate::tui::dialog::{show_edit_review, show_error};
+use crate::tui::omnibox::view::OmniboxEvent;
+use crate::tui::plist_editor::tree;
+use crate::tui::plist_editor::tree::{PlistPath, PlistTreeRow};
+use crate::tui::root::CbSinkMessage;
+
+const PLIST_EDITOR: &str = "plist_editor";
+const PLIST_EDITOR_INPUT: &str = "plist_editor_input";
+
+/// Tree of a job's plist where containers can be expanded and
+/// scalars edited in place. Changes are kept in memory until saved
+/// and reviewed.
+pub struct PlistEditorView {
+    label: String,
+    plist_meta: LaunchdPlist,
+    is_binary: bool,
+    og_plist: plist::Value,
+    plist: plist::Value,
+    expanded: HashSet<PlistPath>,
+    select_view: SelectView<PlistTreeRow>,
+    tx: Sender<OmniboxEvent>,
+}
+
+impl PlistEditorView {
+    pub fn new(
+        label: String,
+        plist_meta: LaunchdPlist,
+        plist: plist::Value,
+        is_binary: bool,
+        tx: Sender<OmniboxEvent>,
+    ) -> Self {
+        let mut view = Self {
+            label,
+            plist_meta,
+            is_binary,
+            og_plist: plist.clone(),
+            plist,
+            expanded: HashSet::new(),
+            select_view: SelectView::new(),
+            tx,
+        };
+
+        view.refresh(None);
+        view
+    }
+
+    /// Rebuild rows, keeping the cursor in place or moving it to select
+    fn refresh(&mut self, select: Option<&PlistPath>) {
+        let rows = tree::flatten(&self.plist, &self.expanded);
+        let current = self.select_view.selected_id().unwrap_or(0);
+        let index = select
+            .and_then(|p| rows.iter().position(|r| r.path == *p))
+            .unwrap_or(current);
+
+        self.select_view.clear();
+        self.select_view
+            .add_all(rows.into_iter().map(|r| (r.text.clone(), r)));
+
+        if !self.select_view.is_empty() {
+            self.select_view
+                .set_selection(index.min(self.select_view.len() - 1));
+        }
+    }
+
+    fn toggle(&mut self, path: &PlistPath) {
+        if !self.expanded.remove(path) {
+            self.expanded.insert(path.clone());
+        }
+
+        self.refresh(Some(path));
+    }
+
+    fn set_value(&mut self, path: &PlistPath, value: plist::Value) -> Result<(), String> {
+        tree::set(&mut self.plist, path, value)?;
+        self.refresh(Some(path));
+        Ok(())
+    }
+
+    fn add(
+        &mut self,
+        parent: &PlistPath,
+        key: Option<String>,
+        kind: PlistKind,
+    ) -> Result<(), String> {
+        let path = tree::insert(&mut self.plist, parent, key, kind.default_value())?;
+
+        if !parent.is_empty() {
+            self.expanded.insert(parent.clone());
+        }
+
+        self.refresh(Some(&path));
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &PlistPath) {
+        tree::remove(&mut self.plist, path);
+
+        // Array indices shift on remove, so forget what was expanded below the parent
+        let parent = &path[..path.len() - 1];
+        self.expanded
+            .retain(|p| p.len() <= parent.len() || !p.starts_with(parent));
+
+        self.refresh(None);
+    }
+
+    /// Edited since it was opened
+    fn is_dirty(&self) -> bool {
+        self.plist != self.og_plist
+    }
+
+    /// Delete the value at path, asking first if there are unsaved changes
+    /// (they can only be undone by closing without saving)
+    fn remove_prompt(&mut self, path: PlistPath) -> EventResult {
+        if !self.is_dirty() {
+            self.remove(&path);
+            return EventResult::Consumed(None);
+        }
+
+        EventResult::with_cb(move |siv| {
+            let name = path.last().map(|s| s.to_string()).unwrap_or_default();
+            let path = path.clone();
+
+            siv.add_layer(
+                Dialog::text(format!("Delete {}? There are unsaved changes", name))
+                    .button("Delete", move |s| {
+                        s.pop_layer();
+                        Self::update(s, |v| {
+                            v.remove(&path);
+                            Ok(())
+                        });
+                    })
+                    .dismiss_button("Cancel"),
+            );
+        })
+    }
+
+    /// Close the editor, asking first if there are unsaved changes
+    fn close(siv: &mut Cursive) {
+        let dirty = siv
+            .call_on_name(PLIST_EDITOR, |v: &mut PlistEditorView| v.is_dirty())
+            .unwrap_or(false);
+
+        if !dirty {
+            siv.pop_layer();
+            return;
+        }
+
+        siv.add_layer(
+            Dialog::text("Discard unsaved changes?")
+                .button("Discard", |s| {
+                    s.pop_layer();
+                    s.pop_layer();
+                })
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Changes for review, written through the same path as $EDITOR
+    fn edit(&self) -> Result<(String, PlistEdit, Sender<OmniboxEvent>), String> {
+        let edit = PlistEdit::new(
+            &self.plist_meta,
+            &self.og_plist,
+            &self.plist,
+            self.is_binary,
+        )?;
+
+        Ok((self.label.clone(), edit, self.tx.clone()))
+    }
+
+    /// Run f against the editor layer and show any error
+    fn update<F>(siv: &mut Cursive, f: F)
+    where
+        F: FnOnce(&mut PlistEditorView) -> Result<(), String>,
+    {
+        let res = siv
+            .call_on_name(PLIST_EDITOR, f)
+            .unwrap_or(Err("Cannot find plist editor".to_string()));
+
+        if let Err(e) = res {
+            show_error(e)(siv);
+        }
+    }
+
+    /// Review the diff, and close the editor once it is written
+    fn save(siv: &mut Cursive) {
+        let res = siv
+            .call_on_name(PLIST_EDITOR, |v: &mut PlistEditorView| v.edit())
+            .unwrap_or(Err("Cannot find plist editor".to_string()));
+
+        match res {
+            Ok((label, edit, tx)) => show_edit_review(tx, label, edit, |s| {
+                s.pop_layer();
+            })(siv),
+            Err(e) => show_error(e)(siv),
+        }
+    }
+
+    fn submit_scalar(siv: &mut Cursive, path: &PlistPath, kind: PlistKind, input: &str) {
+        match tree::parse_scalar(kind, input) {
+            Ok(value) => {
+                siv.pop_layer();
+                Self::update(siv, |v| v.set_value(path, value));
+            }
+            Err(e) => show_error(e)(siv),
+        }
+    }
+
+    /// Type-aware input for the scalar at path
+    fn edit_prompt(&self, row: &PlistTreeRow) -> EventResult {
+        let path = row.path.clone();
+        let kind = row.kind;
+        let current = tree::get(&self.plist, &row.path)
+            .map(tree::display_scalar)
+            .unwrap_or_default();
+
+        EventResult::with_cb(move |siv| {
+            let title = format!(
+                "{} ({})",
+                path.last().map(|s| s.to_string()).unwrap_or_default(),
+                kind.map(|k| k.to_string()).unwrap_or("uid".to_string())
+            );
+
+            match kind {
+                Some(PlistKind::Boolean) => {
+                    let path = path.clone();
+                    let mut choices = SelectView::new();
+                    let (first, second) = if current == "true" {
+                        (true, false)
+                    } else {
+                        (false, true)
+                    };
+                    choices.add_item(first.to_string(), first);
+                    choices.add_item(second.to_string(), second);
+
+                    let choices = choices.on_submit(move |s, b: &bool| {
+                        let value = plist::Value::Boolean(*b);
+                        s.pop_layer();
+                        Self::update(s, |v| v.set_value(&path, value));
+                    });
+
+                    siv.add_layer(
+                        Dialog::around(choices)
+                            .title(title)
+                            .dismiss_button("Cancel"),
+                    );
+                }
+                Some(k) if !k.is_container() && k != PlistKind::Date => {
+                    let submit_path = path.clone();
+                    let button_path = path.clone();
+
+                    let input = EditView::new()
+                        .content(current.clone())
+                        .on_submit(move |s, text| Self::submit_scalar(s, &submit_path, k, text))
+                        .with_name(PLIST_EDITOR_INPUT)
+                        .min_width(40);
+
+                    siv.add_layer(
+                        Dialog::around(input)
+                            .title(title)
+                            .button("OK", move |s| {
+                                let text = s
+                                    .call_on_name(PLIST_EDITOR_INPUT, |v: &mut EditView| {
+                                        v.get_content()
+                                    })
+                                    .expect("Must read input");
+                                Self::submit_scalar(s, &button_path, k, &text);
+                            })
+                            .dismiss_button("Cancel"),
+                    );
+                }
+                _ => show_error(format!("{} values cannot be edited here", title))(siv),
+            }
+        })
+    }
+
+    /// Pick a known launchd key (top level only) or a custom key/type
+    fn add_prompt(&self) -> EventResult {
+        let selected = self.select_view.selection();
+
+        // Add into the container under the cursor, otherwise next to the selection
+        let parent: PlistPath = match selected.as_ref() {
+            Some(row) if row.kind.map(|k| k.is_container()).unwrap_or(false) => row.path.clone(),
+            Some(row) => row.path[..row.path.len() - 1].to_vec(),
+            None => vec![],
+        };
+
+        let parent_value = tree::get(&self.plist, &parent);
+        let is_dictionary = parent_value
+            .map(|v| v.as_dictionary().is_some())
+            .unwrap_or(false);
+
+        let missing_keys: Vec<(&'static str, PlistKind, &'static str)> = if parent.is_empty() {
+            let existing = parent_value.and_then(|v| v.as_dictionary());
+            KNOWN_KEYS
+                .iter()
+                .filter(|(k, _, _)| existing.map(|d| !d.contains_key(k)).unwrap_or(true))
+                .cloned()
+                .collect()
+        } else {
+            vec![]
+        };
+
+        EventResult::with_cb(move |siv| {
+            if !is_dictionary {
+                siv.add_layer(Self::kind_prompt(parent.clone(), false));
+                return;
+            }
+
+            if missing_keys.is_empty() {
+                siv.add_layer(Self::kind_prompt(parent.clone(), true));
+                return;
+            }
+
+            let mut keys: SelectView<Option<(String, PlistKind)>> = SelectView::new();
+            keys.add_item("Custom key...", None);
+
+            for (key, kind, desc) in missing_keys.iter() {
+                keys.add_item(
+                    format!("{} ({}) -- {}", key, kind, desc),
+                    Some((key.to_string(), *kind)),
+                );
+            }
+
+            let parent = parent.clone();
+            let keys = keys.on_submit(move |s, choice: &Option<(String, PlistKind)>| {
+                s.pop_layer();
+
+                match choice {
+                    Some((key, kind)) => {
+                        let (key, kind) = (key.clone(), *kind);
+                        Self::update(s, |v| v.add(&parent, Some(key), kind));
+                    }
+                    None => s.add_layer(Self::kind_prompt(parent.clone(), true)),
+                }
+            });
+
+            siv.add_layer(
+                Dialog::around(keys.scrollable().max_height(20))
+                    .title("Add key")
+                    .dismiss_button("Cancel"),
+            );
+        })
+    }
+
+    /// Choose the type of a new value, and its key for dictionaries
+    fn kind_prompt(parent: PlistPath, with_key: bool) -> Dialog {
+        let mut kinds = SelectView::new();
+        for kind in PLIST_KINDS.iter() {
+            kinds.add_item(kind.to_string(), *kind);
+        }
+
+        let kinds = kinds.on_submit(move |s, kind: &PlistKind| {
+            let key = if with_key {
+                s.call_on_name(PLIST_EDITOR_INPUT, |v: &mut EditView| {
+                    v.get_content().to_string()
+                })
+            } else {
+                None
+            };
+
+            let kind = *kind;
+            s.pop_layer();
+            Self::update(s, |v| v.add(&parent, key, kind));
+        });
+
+        let mut layout = LinearLayout::vertical();
+
+        if with_key {
+            layout = layout
+                .child(TextView::new("Key"))
+                .child(EditView::new().with_name(PLIST_EDITOR_INPUT).min_width(30))
+                .child(DummyView);
+        }
+
+        layout = layout.child(TextView::new("Type")).child(kinds);
+
+        Dialog::around(layout)
+            .title("Add value")
+            .dismiss_button("Cancel")
+    }
+}
+
+impl ViewWrapper for PlistEditorView {
+    wrap_impl!(self.select_view: SelectView<PlistTreeRow>);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        let selected = self.select_view.selection();
+        let is_container = selected
+            .as_ref()
+            .and_then(|r| r.kind)
+            .map(|k| k.is_container())
+            .unwrap_or(false);
+
+        match (event, selected) {
+            (Event::Key(Key::Enter), Some(row)) if is_container => {
+                self.toggle(&row.path);
+                EventResult::Consumed(None)
+            }
+            (Event::Key(Key::Right), Some(row)) if is_container && !row.expanded => {
+                self.toggle(&row.path);
+                EventResult::Consumed(None)
+            }
+            (Event::Key(Key::Left), Some(row)) if row.expanded => {
+                self.toggle(&row.path);
+                EventResult::Consumed(None)
+            }
+            (Event::Key(Key::Enter), Some(row)) => self.edit_prompt(&row),
+            (Event::Char('a'), _) => self.add_prompt(),
+            (Event::Char('d'), Some(row)) | (Event::Key(Key::Del), Some(row)) => {
+                self.remove_prompt(row.path.clone())
+            }
+            (Event::Char('s'), _) => EventResult::with_cb(Self::save),
+            (Event::Char('q'), _) | (Event::Key(Key::Esc), _) => EventResult::with_cb(Self::close),
+            (e, _) => self.select_view.on_event(e),
+        }
+    }
+}
+
+/// Show the tree editor for a job's plist as a new layer
+pub fn show_plist_editor(
+    label: String,
+    plist_meta: LaunchdPlist,
+    plist: plist::Value,
+    is_binary: bool,
+    tx: Sender<OmniboxEvent>,
+) -> CbSinkMessage {
+    Box::new(move |siv: &mut Cursive| {
+        let title = match (plist_meta.readonly, privileged_helper()) {
+            (true, Some(h)) => format!("{} (write with {})", plist_meta.plist_path, h.describe()),
+            (true, None) => format!("{} (read-only)", plist_meta.plist_path),
+            _ => plist_meta.plist_path.clone(),
+        };
+
+        let editor = PlistEditorView::new(label, plist_meta, plist, is_binary, tx)
+            .with_name(PLIST_EDITOR)
+            .scrollable()
+            .full_screen();
+
+        let layout = LinearLayout::vertical()
+            .child(editor)
+            .child(DummyView)
+            .child(TextView::new(
+                "Enter: edit/expand  a: add  d: delete  s: save  q: close",
+            ));
+
+        siv.add_layer(
+            Dialog::around(layout)
+                .title(title)
+                .button("Save", PlistEditorView::save)
+                .button("Close", PlistEditorView::close)
+                .full_screen(),
+        );
+    })
+}
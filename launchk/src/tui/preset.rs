@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::launchd::atomic_write::write_atomic;
+use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::service_list::columns::Column;
+use crate::tui::service_list::filter::FilterMode;
+use crate::tui::service_list::query::Query;
+
+/// How many presets have a number key
+pub const PRESET_KEYS: usize = 9;
+
+/// A saved view of the service list, e.g.
+///
+/// ```toml
+/// [[presets]]
+/// name = "crashed"
+/// filter = "loaded and exit!=0"
+/// filter_mode = "query"   # exact, fuzzy, regex or query
+/// job_types = "u"         # any of sguadlb, empty for everything
+/// sort = { column = "last_exit", descending = true }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub filter: String,
+    #[serde(default)]
+    pub filter_mode: FilterMode,
+    #[serde(default)]
+    pub job_types: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<PresetSort>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetSort {
+    pub column: Column,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl Preset {
+    /// Validated on load, so bad letters can't get this far
+    pub fn job_type_filter(&self) -> JobTypeFilter {
+        JobTypeFilter::from_letters(&self.job_types).unwrap_or_default()
+    }
+
+    /// One line for the preset picker
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+
+        if !self.filter.is_empty() {
+            parts.push(format!("{} \"{}\"", self.filter_mode, self.filter));
+        }
+
+        if !self.job_types.is_empty() {
+            parts.push(format!("[{}]", self.job_types));
+        }
+
+        if let Some(sort) = self.sort {
+            parts.push(format!(
+                "by {}{}",
+                sort.column.title(),
+                if sort.descending { " ▼" } else { " ▲" }
+            ));
+        }
+
+        if parts.is_empty() {
+            "everything".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let invalid = |e: String| format!("Preset {}: {}", self.name, e);
+
+        JobTypeFilter::from_letters(&self.job_types).map_err(invalid)?;
+
+        if self.filter_mode == FilterMode::Query && !self.filter.is_empty() {
+            Query::parse(&self.filter).map_err(invalid)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn validate_presets(presets: &[Preset], default: Option<&str>) -> Result<(), String> {
+    let mut seen = HashSet::new();
+
+    for preset in presets {
+        if !seen.insert(preset.name.as_str()) {
+            return Err(format!("Preset {} is listed twice", preset.name));
+        }
+
+        preset.validate()?;
+    }
+
+    match default {
+        Some(name) if !seen.contains(name) => Err(format!("No preset named {}", name)),
+        _ => Ok(()),
+    }
+}
+
+/// `[[presets]]` as TOML, to append to a config file
+pub fn preset_toml(preset: &Preset) -> Result<String, String> {
+    #[derive(Serialize)]
+    struct Presets<'a> {
+        presets: [&'a Preset; 1],
+    }
+
+    toml::to_string(&Presets { presets: [preset] }).map_err(|e| e.to_string())
+}
+
+/// Append the preset to the config file, leaving the rest of it (and its
+/// comments) as it was. `check` sees the whole new file before it is written.
+pub fn save_preset<F>(path: &Path, preset: &Preset, check: F) -> Result<(), String>
+where
+    F: Fn(&str) -> Result<(), String>,
+{
+    let mut text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+
+    text.push('\n');
+    text.push_str(&preset_toml(preset)?);
+    check(&text)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+
+    write_atomic(path, text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preset_toml, validate_presets, Preset, PresetSort};
+    use crate::tui::service_list::columns::Column;
+    use crate::tui::service_list::filter::FilterMode;
+
+    fn preset(name: &str) -> Preset {
+        Preset {
+            name: name.to_string(),
+            filter: "loaded and exit!=0".to_string(),
+            filter_mode: FilterMode::Query,
+            job_types: "u".to_string(),
+            sort: Some(PresetSort {
+                column: Column::LastExit,
+                descending: true,
+            }),
+        }
+    }
+
+    #[test]
+    fn preset_round_trip() {
+        #[derive(serde::Deserialize)]
+        struct Presets {
+            presets: Vec<Preset>,
+        }
+
+        let text = preset_toml(&preset("crashed")).unwrap();
+        let parsed: Presets = toml::from_str(&text).unwrap();
+
+        assert_eq!(parsed.presets, vec![preset("crashed")]);
+        assert_eq!(
+            preset("crashed").describe(),
+            "query \"loaded and exit!=0\", [u], by Exit ▼"
+        );
+    }
+
+    #[test]
+    fn preset_validation() {
+        assert!(validate_presets(&[preset("a"), preset("b")], Some("b")).is_ok());
+        assert!(validate_presets(&[preset("a"), preset("a")], None).is_err());
+        assert!(validate_presets(&[preset("a")], Some("b")).is_err());
+
+        let mut bad = preset("a");
+        bad.job_types = "ux".to_string();
+        assert!(validate_presets(&[bad], None).is_err());
+
+        let mut bad = preset("a");
+        bad.filter = "loaded and".to_string();
+        assert!(validate_presets(&[bad], None).is_err());
+    }
+}
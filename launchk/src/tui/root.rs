@@ -6,23 +6,35 @@ use std::sync::Arc;
 
 use cursive::event::{Event, EventResult, Key};
 use cursive::traits::{Resizable, Scrollable};
-use cursive::view::ViewWrapper;
-use cursive::views::{LinearLayout, NamedView, Panel};
+use cursive::view::{SizeConstraint, ViewWrapper};
+use cursive::views::{
+    HideableView, LinearLayout, NamedView, Panel, ResizedView, TextContent, TextView,
+};
 use cursive::{Cursive, Vec2, View};
 
 use tokio::runtime::Handle;
 
 use xpc_sys::objects::unix_fifo::UnixFifo;
 
+use crate::config::{Config, ConfirmPolicy, PollIntervals};
+use crate::launchd::log_tail::log_paths;
+use crate::tui::keymap::{KeyAction, KeyLookup, Keymap};
+use crate::tui::log_view::show_logs;
 use crate::tui::omnibox::command::OmniboxCommand;
+use crate::tui::omnibox::state::OmniboxState;
 use crate::tui::omnibox::subscribed_view::{
     OmniboxResult, OmniboxSubscribedView, OmniboxSubscriber, Subscribable,
 };
 use crate::tui::omnibox::view::{OmniboxError, OmniboxEvent, OmniboxView};
 use crate::tui::pager::show_pager;
+use crate::tui::plist_editor::view::show_plist_editor;
+use crate::tui::preset::Preset;
+use crate::tui::service_list::detail::DETAIL_LINES;
 use crate::tui::service_list::view::ServiceListView;
 use crate::tui::sysinfo::SysInfo;
 use crate::{
+    launchd::edit::{last_backup, undo_last_edit},
+    launchd::plist::read_plist,
     launchd::query::dumpjpcategory,
     tui::dialog::{show_csr_info, show_help},
 };
@@ -30,12 +42,23 @@ use crate::{launchd::query::dumpstate, tui::dialog};
 
 pub type CbSinkMessage = Box<dyn FnOnce(&mut Cursive) + Send>;
 
+type DetailPane = HideableView<ResizedView<Panel<TextView>>>;
+type OmniboxPane = ResizedView<ResizedView<Panel<NamedView<OmniboxView>>>>;
+
 pub struct RootLayout {
     layout: LinearLayout,
     omnibox_tx: Sender<OmniboxEvent>,
     runtime_handle: Handle,
     cbsink_channel: Sender<CbSinkMessage>,
     key_ring: VecDeque<Event>,
+    /// From the config file, and any saved since
+    presets: Vec<Preset>,
+    keymap: Keymap,
+    /// Keys of a chord typed so far
+    pending_keys: Vec<Event>,
+    pager: Vec<String>,
+    poll: PollIntervals,
+    confirm: ConfirmPolicy,
 }
 
 #[derive(Debug)]
@@ -44,6 +67,7 @@ enum RootLayoutChildren {
     SysInfo,
     Omnibox,
     ServiceList,
+    Detail,
 }
 
 async fn poll_omnibox(cb_sink: Sender<CbSinkMessage>, rx: Receiver<OmniboxEvent>) {
@@ -63,8 +87,14 @@ async fn poll_omnibox(cb_sink: Sender<CbSinkMessage>, rx: Receiver<OmniboxEvent>
 }
 
 impl RootLayout {
-    pub fn new(siv: &mut Cursive, runtime_handle: &Handle) -> Self {
-        let (omnibox, omnibox_tx, omnibox_rx) = OmniboxView::new(runtime_handle);
+    pub fn new(siv: &mut Cursive, runtime_handle: &Handle, config: &Config) -> Self {
+        let defaults = OmniboxState {
+            job_type_filter: config.filters.job_type_filter(),
+            filter_mode: config.filters.filter_mode,
+            ..OmniboxState::default()
+        };
+        let (omnibox, omnibox_tx, omnibox_rx) =
+            OmniboxView::new(runtime_handle, defaults, config.poll.idle());
         let cbsink_channel = RootLayout::cbsink_channel(siv, runtime_handle);
 
         runtime_handle.spawn(poll_omnibox(cbsink_channel.clone(), omnibox_rx));
@@ -75,29 +105,60 @@ impl RootLayout {
             layout: LinearLayout::vertical(),
             runtime_handle: runtime_handle.clone(),
             key_ring: VecDeque::with_capacity(3),
+            presets: config.presets.clone(),
+            keymap: config.keymap(),
+            pending_keys: vec![],
+            pager: config.pager(),
+            poll: config.poll,
+            confirm: config.confirm,
         };
 
-        new.setup(omnibox);
+        new.setup(omnibox, config);
+
+        if let Some(preset) = config.default_preset() {
+            new.omnibox_tx
+                .send(OmniboxEvent::Command(OmniboxCommand::ApplyPreset(preset)))
+                .expect("Must apply default preset");
+        }
+
         new
     }
 
-    fn setup(&mut self, omnibox: OmniboxView) {
+    fn setup(&mut self, omnibox: OmniboxView, config: &Config) {
         let sysinfo = Panel::new(SysInfo::default()).full_width();
 
         let omnibox = Panel::new(NamedView::new("omnibox", omnibox))
             .full_width()
             .max_height(3);
 
-        let service_list = ServiceListView::new(&self.runtime_handle, self.cbsink_channel.clone())
-            .full_width()
-            .full_height()
-            .scrollable()
-            .subscribable();
+        let detail = TextContent::new("");
+
+        let service_list = ServiceListView::new(
+            &self.runtime_handle,
+            self.cbsink_channel.clone(),
+            detail.clone(),
+            config,
+        )
+        .full_width()
+        .full_height()
+        .scrollable()
+        .subscribable();
+
+        // Border takes two lines
+        let detail_pane: DetailPane = HideableView::new(
+            Panel::new(TextView::new_with_content(detail))
+                .title("Details")
+                .resized(
+                    SizeConstraint::Full,
+                    SizeConstraint::Fixed(DETAIL_LINES + 2),
+                ),
+        );
 
         self.with_view_mut(|v| {
             v.add_child(sysinfo);
             v.add_child(omnibox);
             v.add_child(service_list);
+            v.add_child(detail_pane);
         });
 
         self.layout
@@ -156,6 +217,94 @@ impl RootLayout {
         }
     }
 
+    fn with_omnibox<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut OmniboxView) -> R,
+    {
+        let pane = self
+            .layout
+            .get_child_mut(RootLayoutChildren::Omnibox as usize)
+            .and_then(|v| v.as_any_mut().downcast_mut::<OmniboxPane>())
+            .expect("Must get omnibox");
+
+        let mut omnibox = pane
+            .get_inner_mut()
+            .get_inner_mut()
+            .get_inner_mut()
+            .get_mut();
+        f(&mut *omnibox)
+    }
+
+    fn on_key_action(&mut self, action: KeyAction) -> EventResult {
+        match action {
+            KeyAction::Command(cmd) => {
+                self.omnibox_tx
+                    .send(OmniboxEvent::Command(cmd))
+                    .expect("Must send command");
+                EventResult::Consumed(None)
+            }
+            action => {
+                self.layout
+                    .set_focus_index(RootLayoutChildren::Omnibox as usize)
+                    .expect("Must focus omnibox");
+                self.with_omnibox(|o| o.apply_action(&action))
+            }
+        }
+    }
+
+    /// Keymap bindings, None if the keys are for the focused view
+    fn on_key(&mut self, event: &Event) -> Option<EventResult> {
+        // Moving or clicking the mouse doesn't break a chord
+        if let Event::Mouse { .. } = event {
+            return None;
+        }
+
+        let typing = self.with_omnibox(|o| o.is_typing());
+        self.pending_keys.push(event.clone());
+
+        match self.keymap.lookup(&self.pending_keys, typing) {
+            KeyLookup::Action(action) => {
+                self.pending_keys.clear();
+                Some(self.on_key_action(action))
+            }
+            KeyLookup::Prefix => Some(EventResult::Consumed(None)),
+            // Drop the rest of a chord that didn't match
+            KeyLookup::Unbound if self.pending_keys.len() > 1 => {
+                self.pending_keys.clear();
+                Some(EventResult::Consumed(None))
+            }
+            KeyLookup::Unbound => {
+                self.pending_keys.clear();
+                None
+            }
+        }
+    }
+
+    fn add_preset(&mut self, preset: Preset) {
+        self.presets.push(preset);
+    }
+
+    /// 1-9 recall presets, unless they are being typed into the omnibox.
+    /// Resolved before the focused view sees them, which may consume
+    /// keys it has no use for (e.g. an empty list).
+    fn preset_key(&mut self, event: Event, n: usize) -> EventResult {
+        if self.with_omnibox(|o| o.is_typing()) {
+            return self.layout.on_event(event);
+        }
+
+        match self.presets.get(n) {
+            Some(preset) => {
+                self.omnibox_tx
+                    .send(OmniboxEvent::Command(OmniboxCommand::ApplyPreset(
+                        preset.clone(),
+                    )))
+                    .expect("Must apply preset");
+                EventResult::Consumed(None)
+            }
+            None => self.layout.on_event(event),
+        }
+    }
+
     fn ring_to_arrows(&mut self) -> Option<Event> {
         if self.key_ring.len() < 3 {
             None
@@ -194,19 +343,18 @@ impl ViewWrapper for RootLayout {
     fn wrap_on_event(&mut self, event: Event) -> EventResult {
         log::trace!("on_event: {:?}", event);
 
+        if let Some(res) = self.on_key(&event) {
+            return res;
+        }
+
         let ev = match event {
-            Event::Char('/')
-            | Event::Char(':')
-            | Event::CtrlChar('u')
-            | Event::Char('s')
-            | Event::Char('g')
-            | Event::Char('u')
-            | Event::Char('a')
-            | Event::Char('d')
-            | Event::Char('l')
-            | Event::Key(Key::Backspace) => {
+            Event::Key(Key::Backspace) => {
                 self.focus_and_forward(RootLayoutChildren::Omnibox, event)
             }
+            Event::Char(c @ '1'..='9') => {
+                let n = c.to_digit(10).expect("Must be a digit") as usize - 1;
+                self.preset_key(event, n)
+            }
             // TODO: wtf?
             // After exiting $EDITOR, for some reason we get a termcap issue. iTerm and Apple Terminal
             // exhibit the same behavior. This was the easiest way to solve the problem for now.
@@ -255,6 +403,73 @@ impl OmniboxSubscriber for RootLayout {
                     .expect("Must focus SL");
                 Ok(None)
             }
+            OmniboxEvent::Command(OmniboxCommand::Detail) => {
+                let pane = self
+                    .layout
+                    .get_child_mut(RootLayoutChildren::Detail as usize)
+                    .and_then(|v| v.as_any_mut().downcast_mut::<DetailPane>())
+                    .expect("Must get detail pane");
+
+                pane.set_visible(!pane.is_visible());
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::Columns(columns)) => {
+                self.cbsink_channel
+                    .send(dialog::show_column_picker(self.omnibox_tx.clone(), columns))
+                    .expect("Must show column picker");
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::Presets) => {
+                if self.presets.is_empty() {
+                    return Err(OmniboxError::CommandError(
+                        "No presets, save one with :preset-save".to_string(),
+                    ));
+                }
+
+                self.cbsink_channel
+                    .send(dialog::show_presets(
+                        self.omnibox_tx.clone(),
+                        self.presets.clone(),
+                    ))
+                    .expect("Must show presets");
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::ApplyPreset(preset)) => {
+                self.cbsink_channel
+                    .send(Box::new(move |siv| {
+                        siv.call_on_name("omnibox", |v: &mut OmniboxView| v.apply_preset(&preset));
+                    }))
+                    .expect("Must apply preset");
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::PresetSave(sort)) => {
+                self.cbsink_channel
+                    .send(dialog::show_preset_save(sort, |siv, preset| {
+                        siv.call_on_name("root_layout", |v: &mut NamedView<RootLayout>| {
+                            v.get_mut().add_preset(preset)
+                        });
+                    }))
+                    .expect("Must show preset prompt");
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::Theme) => {
+                self.cbsink_channel
+                    .send(dialog::show_themes())
+                    .expect("Must show themes");
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::FromSystemd) => {
+                self.cbsink_channel
+                    .send(dialog::show_from_systemd())
+                    .expect("Must show systemd prompt");
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::FromCrontab) => {
+                self.cbsink_channel
+                    .send(dialog::show_from_crontab())
+                    .expect("Must show crontab prompt");
+                Ok(None)
+            }
             OmniboxEvent::Command(OmniboxCommand::Confirm(p, c)) => {
                 self.cbsink_channel
                     .send(dialog::show_prompt(self.omnibox_tx.clone(), p, c))
@@ -272,6 +487,104 @@ impl OmniboxSubscriber for RootLayout {
                     .expect("Must show prompt");
                 Ok(None)
             }
+            OmniboxEvent::Command(OmniboxCommand::TreeEdit(label, plist_meta)) => {
+                let (plist, is_binary) =
+                    read_plist(&plist_meta.plist_path).map_err(OmniboxError::CommandError)?;
+
+                self.cbsink_channel
+                    .send(show_plist_editor(
+                        label,
+                        plist_meta,
+                        plist,
+                        is_binary,
+                        self.omnibox_tx.clone(),
+                    ))
+                    .expect("Must show plist editor");
+
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::ReviewEdit(label, edit)) => {
+                self.cbsink_channel
+                    .send(dialog::show_edit_review(
+                        self.omnibox_tx.clone(),
+                        label,
+                        edit,
+                        Cursive::noop,
+                    ))
+                    .expect("Must show edit review");
+
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::Export(label, plist_meta)) => {
+                self.cbsink_channel
+                    .send(dialog::show_export(label, plist_meta))
+                    .expect("Must show export prompt");
+
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::Import(label, plist_meta)) => {
+                self.cbsink_channel
+                    .send(dialog::show_import(
+                        self.omnibox_tx.clone(),
+                        label,
+                        plist_meta,
+                    ))
+                    .expect("Must show import prompt");
+
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::Logs(label, plist_meta)) => {
+                let (plist, _) =
+                    read_plist(&plist_meta.plist_path).map_err(OmniboxError::CommandError)?;
+                let paths = log_paths(&plist);
+
+                if paths.is_empty() {
+                    return Err(OmniboxError::CommandError(format!(
+                        "{} has no StandardOutPath or StandardErrorPath",
+                        label
+                    )));
+                }
+
+                show_logs(
+                    &self.runtime_handle,
+                    self.cbsink_channel.clone(),
+                    label,
+                    paths,
+                    self.poll.logs(),
+                )
+                .map_err(OmniboxError::CommandError)?;
+
+                Ok(None)
+            }
+            OmniboxEvent::Command(OmniboxCommand::UndoEditRequest) => {
+                let (plist_path, backup_path) = last_backup()
+                    .ok_or_else(|| OmniboxError::CommandError("No edits to undo".to_string()))?;
+
+                if !self.confirm.undo_edit {
+                    return Ok(Some(OmniboxCommand::UndoEdit));
+                }
+
+                Ok(Some(OmniboxCommand::Confirm(
+                    format!("Restore {} from {}?", plist_path, backup_path.display()),
+                    vec![OmniboxCommand::UndoEdit],
+                )))
+            }
+            OmniboxEvent::Command(OmniboxCommand::UndoEdit) => {
+                let undone = undo_last_edit();
+
+                // A privileged helper may have had the terminal
+                self.cbsink_channel
+                    .send(Box::new(Cursive::clear))
+                    .expect("Must clear");
+
+                let (plist_path, _) = undone.map_err(OmniboxError::CommandError)?;
+
+                self.cbsink_channel
+                    .send(dialog::show_notice(format!("Restored {}", plist_path)))
+                    .expect("Must show notice");
+
+                Ok(None)
+            }
             OmniboxEvent::Command(OmniboxCommand::CSRInfo) => {
                 self.cbsink_channel
                     .send(show_csr_info())
@@ -285,7 +598,7 @@ impl OmniboxSubscriber for RootLayout {
 
                 log::info!("shmem response sz {}", size);
 
-                show_pager(&self.cbsink_channel, unsafe {
+                show_pager(&self.cbsink_channel, &self.pager, unsafe {
                     &*slice_from_raw_parts(shmem.region as *mut u8, size)
                 })
                 .map_err(|e| OmniboxError::CommandError(e))?;
@@ -311,14 +624,14 @@ impl OmniboxSubscriber for RootLayout {
                     .expect("Must join read thread")
                     .map_err(|e| OmniboxError::CommandError(e))?;
 
-                show_pager(&self.cbsink_channel, &jetsam_data)
+                show_pager(&self.cbsink_channel, &self.pager, &jetsam_data)
                     .map_err(|e| OmniboxError::CommandError(e))?;
 
                 Ok(None)
             }
             OmniboxEvent::Command(OmniboxCommand::Help) => {
                 self.cbsink_channel
-                    .send(show_help())
+                    .send(show_help(self.keymap.help()))
                     .expect("Must show prompt");
 
                 Ok(None)
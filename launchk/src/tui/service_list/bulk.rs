@@ -0,0 +1,127 @@
+use std::fmt;
+
+/// How many labels a confirmation lists before "and N more"
+const SUMMARY_LABELS: usize = 10;
+
+/// What can be run across every marked job at once
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BulkAction {
+    Load,
+    Unload,
+    Enable,
+    Disable,
+    Reload,
+}
+
+impl BulkAction {
+    /// Whether a session type is needed on top of the domain
+    pub fn needs_session(&self) -> bool {
+        matches!(self, BulkAction::Load | BulkAction::Reload)
+    }
+
+    /// Whether the job must have a plist (launchctl bootstrap and bootout take a path)
+    pub fn needs_plist(&self) -> bool {
+        !matches!(self, BulkAction::Enable | BulkAction::Disable)
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            BulkAction::Load => "Loaded",
+            BulkAction::Unload => "Unloaded",
+            BulkAction::Enable => "Enabled",
+            BulkAction::Disable => "Disabled",
+            BulkAction::Reload => "Reloaded",
+        }
+    }
+}
+
+impl fmt::Display for BulkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+fn jobs(n: usize) -> String {
+    if n == 1 {
+        "1 job".to_string()
+    } else {
+        format!("{} jobs", n)
+    }
+}
+
+/// The confirmation prompt, e.g. "Unload 3 jobs?" and their labels
+pub fn summarize_targets(action: BulkAction, labels: &[String]) -> String {
+    let mut summary = format!("{} {}?\n", action, jobs(labels.len()));
+
+    for label in labels.iter().take(SUMMARY_LABELS) {
+        summary.push_str(&format!("\n  {}", label));
+    }
+
+    if labels.len() > SUMMARY_LABELS {
+        summary.push_str(&format!("\n  and {} more", labels.len() - SUMMARY_LABELS));
+    }
+
+    summary
+}
+
+/// Title and body for the result dialog, failures first
+pub fn report(action: BulkAction, results: &[(String, Result<(), String>)]) -> (String, String) {
+    let succeeded = results.iter().filter(|(_, r)| r.is_ok()).count();
+    let title = format!(
+        "{} {} of {}",
+        action.past_tense(),
+        succeeded,
+        jobs(results.len())
+    );
+
+    let mut lines: Vec<String> = results
+        .iter()
+        .filter_map(|(label, r)| r.as_ref().err().map(|e| format!("✘ {}: {}", label, e)))
+        .collect();
+
+    lines.extend(
+        results
+            .iter()
+            .filter(|(_, r)| r.is_ok())
+            .map(|(label, _)| format!("✔ {}", label)),
+    );
+
+    (title, lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{report, summarize_targets, BulkAction};
+
+    #[test]
+    fn bulk_summary() {
+        let labels: Vec<String> = (0..12).map(|i| format!("com.example.{}", i)).collect();
+
+        assert_eq!(
+            summarize_targets(BulkAction::Unload, &labels[..2]),
+            "Unload 2 jobs?\n\n  com.example.0\n  com.example.1"
+        );
+
+        let long = summarize_targets(BulkAction::Enable, &labels);
+        assert!(long.starts_with("Enable 12 jobs?"));
+        assert!(long.contains("com.example.9"));
+        assert!(!long.contains("com.example.10"));
+        assert!(long.ends_with("and 2 more"));
+    }
+
+    #[test]
+    fn bulk_report() {
+        let results = vec![
+            ("com.example.a".to_string(), Ok(())),
+            ("com.example.b".to_string(), Err("No plist".to_string())),
+        ];
+
+        assert_eq!(
+            report(BulkAction::Load, &results),
+            (
+                "Loaded 1 of 2 jobs".to_string(),
+                "✘ com.example.b: No plist\n✔ com.example.a".to_string()
+            )
+        );
+    }
+}
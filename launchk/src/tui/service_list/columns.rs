@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::service_list::detail::{program, schedule, WaitStatus};
+use crate::tui::service_list::list_item::ServiceListItem;
+
+/// Everything the service list can show about a job
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Label,
+    Pid,
+    LastExit,
+    Domain,
+    Session,
+    JobType,
+    PlistPath,
+    Program,
+    Schedule,
+    NextRun,
+    Loaded,
+    Enabled,
+    Health,
+}
+
+/// Registry order, which the column picker lists hidden columns in
+pub static ALL_COLUMNS: [Column; 13] = [
+    Column::Label,
+    Column::Pid,
+    Column::LastExit,
+    Column::Domain,
+    Column::Session,
+    Column::JobType,
+    Column::PlistPath,
+    Column::Program,
+    Column::Schedule,
+    Column::NextRun,
+    Column::Loaded,
+    Column::Enabled,
+    Column::Health,
+];
+
+impl Column {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Column::Label => "Name",
+            Column::Pid => "PID",
+            Column::LastExit => "Exit",
+            Column::Domain => "Domain",
+            Column::Session => "Session",
+            Column::JobType => "Job Type",
+            Column::PlistPath => "Plist",
+            Column::Program => "Program",
+            Column::Schedule => "Schedule",
+            Column::NextRun => "Next Run",
+            Column::Loaded => "Loaded",
+            Column::Enabled => "Enabled",
+            Column::Health => "Health",
+        }
+    }
+
+    /// Enough for the usual values. None shares the space the
+    /// fixed width columns leave.
+    pub fn default_width(&self) -> Option<usize> {
+        match self {
+            Column::Label | Column::PlistPath | Column::Program => None,
+            Column::Pid | Column::LastExit | Column::Loaded | Column::Health => Some(6),
+            Column::Domain => Some(10),
+            Column::Session => Some(12),
+            Column::JobType => Some(14),
+            Column::Schedule => Some(16),
+            Column::NextRun => Some(11),
+            Column::Enabled => Some(8),
+        }
+    }
+
+    pub fn value(&self, item: &ServiceListItem) -> String {
+        let status = &item.status;
+        let loaded = item.job_type_filter.intersects(JobTypeFilter::LOADED);
+        let job = status.job.as_ref();
+
+        let value = match self {
+            Column::Label => Some(item.name.clone()),
+            Column::Pid => Some(status.pid)
+                .filter(|pid| *pid > 0 && loaded)
+                .map(|pid| pid.to_string()),
+            Column::LastExit => status
+                .last_exit_status
+                .map(|s| match WaitStatus::decode(s) {
+                    WaitStatus::Exited(code) => code.to_string(),
+                    WaitStatus::Signaled(signal) => format!("sig{}", signal),
+                }),
+            Column::Domain => Some(status.domain.to_string()),
+            Column::Session => Some(status.limit_load_to_session_type.to_string()),
+            Column::JobType => status
+                .plist
+                .as_ref()
+                .map(|ec| format!("{}/{}", ec.entry_location, ec.entry_type)),
+            Column::PlistPath => status.plist.as_ref().map(|ec| ec.plist_path.clone()),
+            Column::Program => job.and_then(program),
+            Column::Schedule => job.map(schedule),
+            Column::NextRun => status
+                .plist
+                .as_ref()
+                .and_then(|ec| ec.schedule.as_ref())
+                .and_then(|s| s.next_runs(item.now, 1).pop())
+                .map(|t| t.format("%m-%d %H:%M").to_string()),
+            Column::Loaded => Some(if loaded { "✔" } else { "✘" }.to_string()),
+            Column::Enabled => status.plist.as_ref().map(|_| status.enabled.to_string()),
+            Column::Health => match (&status.plist, status.health.len()) {
+                (None, _) => None,
+                (Some(_), 0) => Some("✔".to_string()),
+                (Some(_), problems) => Some(format!("✘ {}", problems)),
+            },
+        };
+
+        value.unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// One column as configured, e.g. `{ name = "pid", width = 8 }`
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnConfig {
+    pub name: Column,
+    /// Left out for the column's default, 0 to share the remaining space
+    #[serde(default)]
+    pub width: Option<usize>,
+}
+
+impl ColumnConfig {
+    pub fn new(name: Column) -> Self {
+        Self { name, width: None }
+    }
+
+    /// Fixed width, or None if it shares the remaining space
+    pub fn width(&self) -> Option<usize> {
+        match self.width {
+            Some(0) => None,
+            Some(width) => Some(width),
+            None => self.name.default_width(),
+        }
+    }
+}
+
+/// Label, session, job type, PID and loaded, plus health and next run
+pub fn default_columns() -> Vec<ColumnConfig> {
+    [
+        Column::Label,
+        Column::Session,
+        Column::JobType,
+        Column::Pid,
+        Column::Loaded,
+        Column::Health,
+        Column::NextRun,
+    ]
+    .iter()
+    .map(|c| ColumnConfig::new(*c))
+    .collect()
+}
+
+pub fn validate_columns(columns: &[ColumnConfig]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+
+    for column in columns {
+        if !seen.insert(column.name) {
+            return Err(format!("Column {:?} is listed twice", column.name));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_columns, validate_columns, Column, ColumnConfig};
+
+    #[test]
+    fn columns_widths_and_duplicates() {
+        let sized = |width| ColumnConfig {
+            name: Column::Pid,
+            width,
+        };
+
+        assert_eq!(sized(None).width(), Some(6));
+        assert_eq!(sized(Some(9)).width(), Some(9));
+        assert_eq!(sized(Some(0)).width(), None);
+        assert_eq!(ColumnConfig::new(Column::Label).width(), None);
+
+        assert!(validate_columns(&default_columns()).is_ok());
+        assert!(validate_columns(&[sized(None), sized(Some(3))]).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use chrono::Local;
+
+use crate::launchd::job::{KeepAlive, LaunchdJob};
+use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::service_list::list_item::ServiceListItem;
+
+/// Height of the detail pane's text, one line per field
+pub const DETAIL_LINES: usize = 8;
+
+fn row<S: AsRef<str>>(key: &str, value: S) -> String {
+    format!("{:<11}{}", key, value.as_ref())
+}
+
+/// How a job last exited, from its wait(2) status
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WaitStatus {
+    Exited(i64),
+    Signaled(i64),
+}
+
+impl WaitStatus {
+    pub fn decode(status: i64) -> Self {
+        match status & 0x7f {
+            0 => WaitStatus::Exited((status >> 8) & 0xff),
+            signal => WaitStatus::Signaled(signal),
+        }
+    }
+}
+
+/// Decode a wait(2) status the way a shell would report it
+pub fn exit_status(status: i64) -> String {
+    match WaitStatus::decode(status) {
+        WaitStatus::Exited(code) => format!("exited {}", code),
+        WaitStatus::Signaled(signal) => format!("killed by signal {}", signal),
+    }
+}
+
+/// Quote arguments containing whitespace, so the command line is unambiguous
+pub fn command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|a| {
+            if a.is_empty() || a.contains(char::is_whitespace) {
+                format!("\"{}\"", a.replace('"', "\\\""))
+            } else {
+                a.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn keep_alive(keep_alive: &KeepAlive) -> String {
+    match keep_alive {
+        KeepAlive::Never => "no".to_string(),
+        KeepAlive::Always => "always".to_string(),
+        KeepAlive::OnFailure => "after a failed exit".to_string(),
+        KeepAlive::OnSuccess => "after a successful exit".to_string(),
+        KeepAlive::Conditions(c) => format!(
+            "on {}",
+            c.keys().cloned().collect::<Vec<String>>().join(", ")
+        ),
+    }
+}
+
+/// When launchd starts the job on its own
+pub fn schedule(job: &LaunchdJob) -> String {
+    let mut when = vec![];
+
+    if job.run_at_load {
+        when.push("at load".to_string());
+    }
+
+    if let Some(secs) = job.start_interval {
+        when.push(format!("every {}s", secs));
+    }
+
+    if let Some(calendar) = &job.start_calendar_interval {
+        when.push(calendar.to_string());
+    }
+
+    if when.is_empty() {
+        "on demand".to_string()
+    } else {
+        when.join(", ")
+    }
+}
+
+/// The program and its arguments, Program first if it differs from argv[0]
+pub fn program(job: &LaunchdJob) -> Option<String> {
+    job.command().map(|(program, args)| match args.first() {
+        Some(first) if *first == program => command_line(&args),
+        _ => format!("{} ({})", program, command_line(&args)),
+    })
+}
+
+/// Everything known about the job, for the detail pane
+pub fn describe(item: &ServiceListItem) -> String {
+    let status = &item.status;
+    let loaded = item.job_type_filter.intersects(JobTypeFilter::LOADED);
+
+    let state = match (loaded, status.pid) {
+        (true, pid) if pid > 0 => format!("running, PID {}", pid),
+        (true, _) => "loaded, not running".to_string(),
+        (false, _) => "not loaded".to_string(),
+    };
+
+    let last_exit = status
+        .last_exit_status
+        .map(|s| format!("last {}", exit_status(s)))
+        .unwrap_or_else(|| "no exit status".to_string());
+
+    let state = row("State", format!("{}, {}", state, last_exit));
+    let domain = row(
+        "Domain",
+        format!(
+            "{}, session {}",
+            status.domain, status.limit_load_to_session_type
+        ),
+    );
+
+    let plist_meta = match &status.plist {
+        Some(plist_meta) => plist_meta,
+        None => {
+            return vec![
+                row("Plist", "not found in any search directory"),
+                state,
+                domain,
+            ]
+            .join("\n")
+        }
+    };
+
+    let readonly = if plist_meta.readonly {
+        " (read-only)"
+    } else {
+        ""
+    };
+
+    let mut lines = vec![
+        row("Plist", format!("{}{}", plist_meta.plist_path, readonly)),
+        state,
+        domain,
+    ];
+
+    match &status.job {
+        Some(job) => {
+            let next = job
+                .start_calendar_interval
+                .as_ref()
+                .map(|c| {
+                    c.next_runs(Local::now().naive_local(), 1)
+                        .pop()
+                        .map(|t| format!(" (next {})", t.format("%Y-%m-%d %H:%M")))
+                        .unwrap_or_else(|| " (never fires)".to_string())
+                })
+                .unwrap_or_default();
+
+            lines.push(row(
+                "Program",
+                program(job).unwrap_or_else(|| "-".to_string()),
+            ));
+            lines.push(row("Schedule", format!("{}{}", schedule(job), next)));
+            lines.push(row("KeepAlive", keep_alive(&job.keep_alive)));
+            lines.push(row("Enabled", status.enabled.to_string()));
+        }
+        None => lines.push(row("Program", "plist could not be parsed")),
+    }
+
+    let health = match status.health.as_slice() {
+        [] => "✔".to_string(),
+        [problem] => format!("✘ {}", problem),
+        [problem, rest @ ..] => format!("✘ {} (+{} more, see :health)", problem, rest.len()),
+    };
+    lines.push(row("Health", health));
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_line, exit_status, WaitStatus};
+
+    #[test]
+    fn detail_exit_status() {
+        assert_eq!(exit_status(0), "exited 0");
+        assert_eq!(exit_status(78 << 8), "exited 78");
+        assert_eq!(exit_status(9), "killed by signal 9");
+        // Core dumped flag is not part of the signal
+        assert_eq!(exit_status(0x80 | 11), "killed by signal 11");
+        assert_eq!(WaitStatus::decode(78 << 8), WaitStatus::Exited(78));
+        assert_eq!(WaitStatus::decode(0x80 | 11), WaitStatus::Signaled(11));
+    }
+
+    #[test]
+    fn detail_command_line() {
+        let args: Vec<String> = vec!["/bin/sh", "-c", "echo \"hi\" > /tmp/x", ""]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        assert_eq!(
+            command_line(&args),
+            "/bin/sh -c \"echo \\\"hi\\\" > /tmp/x\" \"\""
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::tui::service_list::columns::Column;
+use crate::tui::service_list::query::{field_column, Query, Queryable};
+
+/// How `/` matches, Tab cycles through these
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterMode {
+    /// Case-insensitive substring
+    Exact,
+    /// Characters in order, ranked by how closely they are grouped
+    Fuzzy,
+    /// Case-insensitive unless the pattern says `(?-i)`. Until it parses,
+    /// the pattern is matched literally
+    Regex,
+    /// An expression like `loaded and not system and exit!=0`, nothing is
+    /// filtered out until it parses
+    Query,
+}
+
+impl Default for FilterMode {
+    fn default() -> Self {
+        FilterMode::Exact
+    }
+}
+
+impl FilterMode {
+    pub fn next(&self) -> Self {
+        match self {
+            FilterMode::Exact => FilterMode::Fuzzy,
+            FilterMode::Fuzzy => FilterMode::Regex,
+            FilterMode::Regex => FilterMode::Query,
+            FilterMode::Query => FilterMode::Exact,
+        }
+    }
+}
+
+impl fmt::Display for FilterMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_ascii_lowercase())
+    }
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn is_boundary(c: char) -> bool {
+    ".-_/ ".contains(c)
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Exact(Vec<char>),
+    Fuzzy(Vec<char>),
+    Regex(Box<Regex>),
+    /// PIDs and exit codes, where `pid:12` should not find 1234
+    Equals(String),
+}
+
+impl Matcher {
+    fn new(mode: FilterMode, column: Column, pattern: &str) -> Self {
+        let chars = || pattern.chars().map(lower).collect();
+
+        match (mode, column) {
+            (_, Column::Pid) | (_, Column::LastExit) => Matcher::Equals(pattern.to_lowercase()),
+            (FilterMode::Exact, _) | (FilterMode::Query, _) => Matcher::Exact(chars()),
+            (FilterMode::Fuzzy, _) => Matcher::Fuzzy(chars()),
+            (FilterMode::Regex, _) => {
+                let regex = RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .or_else(|_| {
+                        RegexBuilder::new(&regex::escape(pattern))
+                            .case_insensitive(true)
+                            .build()
+                    })
+                    .expect("Escaped pattern must compile");
+
+                Matcher::Regex(Box::new(regex))
+            }
+        }
+    }
+
+    /// Score (0 unless fuzzy) and the indices of the matched chars
+    fn find(&self, text: &str) -> Option<(i64, Vec<usize>)> {
+        let text_chars: Vec<char> = text.chars().map(lower).collect();
+
+        match self {
+            Matcher::Equals(value) => {
+                if text.to_lowercase() == *value {
+                    Some((0, (0..text_chars.len()).collect()))
+                } else {
+                    None
+                }
+            }
+            Matcher::Exact(pattern) => text_chars
+                .windows(pattern.len())
+                .position(|w| w == pattern.as_slice())
+                .map(|start| (0, (start..start + pattern.len()).collect())),
+            Matcher::Fuzzy(pattern) => fuzzy(pattern, &text_chars),
+            Matcher::Regex(regex) => regex.find(text).map(|m| {
+                let matched = text
+                    .char_indices()
+                    .enumerate()
+                    .filter(|(_, (byte, _))| m.start() <= *byte && *byte < m.end())
+                    .map(|(i, _)| i)
+                    .collect();
+
+                (0, matched)
+            }),
+        }
+    }
+}
+
+/// Every in-order placement of the pattern starting at each occurrence of
+/// its first char, keeping the best scored. Consecutive chars and chars
+/// starting a word (after `.`, `-`, `_`, `/`) score higher, gaps lower.
+fn fuzzy(pattern: &[char], text: &[char]) -> Option<(i64, Vec<usize>)> {
+    let first = *pattern.first()?;
+    let mut best: Option<(i64, Vec<usize>)> = None;
+
+    for start in (0..text.len()).filter(|i| text[*i] == first) {
+        let mut matched = vec![start];
+        let mut at = start + 1;
+
+        for c in &pattern[1..] {
+            match text[at..].iter().position(|t| t == c) {
+                Some(offset) => {
+                    matched.push(at + offset);
+                    at += offset + 1;
+                }
+                None => break,
+            }
+        }
+
+        if matched.len() < pattern.len() {
+            // Later starts only have less text to match in
+            break;
+        }
+
+        let score: i64 = matched
+            .iter()
+            .enumerate()
+            .map(|(n, i)| {
+                let gap = if n > 0 { i - matched[n - 1] - 1 } else { 0 };
+                let mut score = 1 - gap.min(3) as i64;
+
+                if n > 0 && gap == 0 {
+                    score += 4;
+                }
+
+                if *i == 0 || is_boundary(text[i - 1]) {
+                    score += 6;
+                }
+
+                score
+            })
+            .sum();
+
+        if best.as_ref().map(|(b, _)| score > *b).unwrap_or(true) {
+            best = Some((score, matched));
+        }
+    }
+
+    best
+}
+
+#[derive(Debug)]
+struct Term {
+    column: Column,
+    matcher: Matcher,
+}
+
+/// The `/` filter: whitespace separated terms that must all match, either
+/// a label pattern or `field:pattern` (e.g. `pid:123`, `program:node`,
+/// `domain:gui`). In query mode, a `Query` instead.
+#[derive(Debug)]
+pub struct LabelFilter {
+    mode: FilterMode,
+    terms: Vec<Term>,
+    query: Option<Query>,
+}
+
+/// Rank among the other matches (higher first) and the label's matched chars
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct FilterMatch {
+    pub score: i64,
+    pub label: Vec<usize>,
+}
+
+impl LabelFilter {
+    pub fn new(query: &str, mode: FilterMode) -> Self {
+        if mode == FilterMode::Query {
+            return Self {
+                mode,
+                terms: vec![],
+                query: Query::parse(query).ok(),
+            };
+        }
+
+        let terms = query
+            .split_whitespace()
+            .filter_map(|term| {
+                let qualified = term.find(':').and_then(|at| {
+                    field_column(&term[..at]).map(|column| (column, &term[at + 1..]))
+                });
+
+                let (column, pattern) = qualified.unwrap_or((Column::Label, term));
+
+                // Still being typed
+                if pattern.is_empty() {
+                    return None;
+                }
+
+                Some(Term {
+                    column,
+                    matcher: Matcher::new(mode, column, pattern),
+                })
+            })
+            .collect();
+
+        Self {
+            mode,
+            terms,
+            query: None,
+        }
+    }
+
+    /// Whether matches should be ordered by score rather than the usual order
+    pub fn ranks(&self) -> bool {
+        self.mode == FilterMode::Fuzzy && self.terms.iter().any(|t| t.column == Column::Label)
+    }
+
+    pub fn matches<Q: Queryable>(&self, item: &Q) -> Option<FilterMatch> {
+        let label = item.field(Column::Label).unwrap_or_else(|| "-".to_string());
+
+        let mut result = self.match_label(&label)?;
+        result.score += self.match_fields(item)?;
+
+        Some(result)
+    }
+
+    /// Just the label terms, which can rule a job out before anything
+    /// else about it is looked up
+    pub fn match_label(&self, label: &str) -> Option<FilterMatch> {
+        let mut result = FilterMatch::default();
+
+        for term in self.terms.iter().filter(|t| t.column == Column::Label) {
+            let (score, matched) = term.matcher.find(label)?;
+            result.score += score;
+            result.label.extend(matched);
+        }
+
+        result.label.sort_unstable();
+        result.label.dedup();
+
+        Some(result)
+    }
+
+    /// The other terms' score, or nothing if they (or the query) don't match
+    pub fn match_fields<Q: Queryable>(&self, item: &Q) -> Option<i64> {
+        if let Some(query) = &self.query {
+            return Some(0).filter(|_| query.eval(item));
+        }
+
+        self.terms
+            .iter()
+            .filter(|t| t.column != Column::Label)
+            .try_fold(0, |score, term| {
+                let field = item.field(term.column).unwrap_or_else(|| "-".to_string());
+                term.matcher.find(&field).map(|(s, _)| score + s)
+            })
+    }
+}
+
+impl Default for LabelFilter {
+    fn default() -> Self {
+        Self::new("", FilterMode::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterMode, LabelFilter};
+    use crate::tui::service_list::columns::Column;
+    use crate::tui::service_list::query::{Flag, Queryable};
+
+    struct Fields(&'static str, Option<&'static str>);
+
+    impl Queryable for Fields {
+        fn field(&self, column: Column) -> Option<String> {
+            match column {
+                Column::Label => Some(self.0.to_string()),
+                Column::Pid => self.1.map(String::from),
+                Column::Program => Some("/usr/local/bin/node".to_string()),
+                _ => None,
+            }
+        }
+
+        fn flag(&self, flag: Flag) -> bool {
+            flag == Flag::Running && self.1.is_some()
+        }
+    }
+
+    fn fields(label: &'static str, pid: &'static str) -> Fields {
+        Fields(label, Some(pid).filter(|p| *p != "-"))
+    }
+
+    #[test]
+    fn filter_modes() {
+        let job = fields("com.apple.Safari.History", "123");
+
+        let exact = LabelFilter::new("safari", FilterMode::Exact);
+        assert_eq!(
+            exact.matches(&job).unwrap().label,
+            vec![10, 11, 12, 13, 14, 15]
+        );
+        assert!(LabelFilter::new("cas", FilterMode::Exact)
+            .matches(&job)
+            .is_none());
+
+        let fuzzy = LabelFilter::new("cas", FilterMode::Fuzzy);
+        assert!(fuzzy.ranks());
+        assert_eq!(fuzzy.matches(&job).unwrap().label, vec![0, 4, 10]);
+        assert!(LabelFilter::new("csa", FilterMode::Fuzzy)
+            .matches(&fields("com.apple.a", "-"))
+            .is_none());
+
+        let regex = LabelFilter::new("^com\\.apple\\.s", FilterMode::Regex);
+        assert_eq!(
+            regex.matches(&job).unwrap().label,
+            (0..11).collect::<Vec<usize>>()
+        );
+        // Incomplete patterns match literally until they parse
+        assert!(LabelFilter::new("History(", FilterMode::Regex)
+            .matches(&job)
+            .is_none());
+        assert!(LabelFilter::new("History(", FilterMode::Regex)
+            .matches(&fields("a.History(", "-"))
+            .is_some());
+    }
+
+    #[test]
+    fn filter_fuzzy_ranking() {
+        let fuzzy = LabelFilter::new("sfs", FilterMode::Fuzzy);
+        let score = |label| fuzzy.matches(&fields(label, "-")).unwrap().score;
+
+        // Word starts beat scattered letters
+        assert!(score("com.apple.safari.sync") > score("com.apple.softwareupdates"));
+        // The best placement is found even when an earlier one exists
+        assert_eq!(
+            LabelFilter::new("sf", FilterMode::Fuzzy)
+                .matches(&fields("assets.safari.files", "-"))
+                .unwrap()
+                .label,
+            vec![7, 9]
+        );
+    }
+
+    #[test]
+    fn filter_fields() {
+        let job = fields("com.example.web", "123");
+
+        let matches = |query| LabelFilter::new(query, FilterMode::Exact).matches(&job);
+
+        assert!(matches("pid:123").is_some());
+        assert!(matches("pid:12").is_none());
+        assert!(matches("PROGRAM:node web").is_some());
+        assert!(matches("program:python").is_none());
+        // Unknown fields are part of the label pattern
+        assert!(matches("foo:bar").is_none());
+        // Qualified terms do not highlight the label
+        assert_eq!(matches("pid:123 web").unwrap().label, vec![12, 13, 14]);
+        // Still typing
+        assert!(matches("pid:").is_some());
+        assert!(!LabelFilter::new("pid:1", FilterMode::Fuzzy).ranks());
+
+        // The label is checked alone, before fields are looked up
+        let split = LabelFilter::new("web pid:9", FilterMode::Exact);
+        assert_eq!(
+            split.match_label("com.example.web").unwrap().label,
+            vec![12, 13, 14]
+        );
+        assert!(split.match_label("com.example.db").is_none());
+        assert!(split.match_fields(&job).is_none());
+    }
+
+    #[test]
+    fn filter_query_mode() {
+        let matches = |query, job| {
+            LabelFilter::new(query, FilterMode::Query)
+                .matches(&job)
+                .is_some()
+        };
+
+        assert!(matches("running and program ~ node", fields("a", "1")));
+        assert!(!matches("running", fields("a", "-")));
+        // Not filtered while the query is incomplete
+        assert!(matches("running and", fields("a", "-")));
+    }
+}
@@ -1,46 +1,194 @@
-use std::borrow::Borrow;
+use std::rc::Rc;
 
+use chrono::NaiveDateTime;
+use cursive::theme::Style;
+
+use crate::launchd::disabled::EnableState;
 use crate::launchd::entry_status::LaunchdEntryStatus;
+use crate::launchd::job::KeepAlive;
 use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::service_list::columns::Column;
+use crate::tui::service_list::query::{Flag, Queryable};
 use crate::tui::table::table_list_view::TableListItem;
+use crate::tui::theme;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ServiceListItem {
     pub name: String,
     pub status: LaunchdEntryStatus,
     pub job_type_filter: JobTypeFilter,
+    /// The columns the list is showing, in order
+    pub columns: Rc<Vec<Column>>,
+    /// Characters of the name the `/` filter matched
+    pub label_matches: Vec<usize>,
+    /// Space or `:mark-all` marked it for a bulk action
+    pub marked: bool,
+    /// When the list was refreshed, so every row's next run counts from the same time
+    pub now: NaiveDateTime,
+}
+
+/// What a row's colour says about its job, most urgent first
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RowStatus {
+    /// Last exited non-zero, even if launchd has restarted it since
+    Failed,
+    Unhealthy,
+    Disabled,
+    Running,
+    /// Loaded, but not running
+    Idle,
+    Unloaded,
+}
+
+impl ServiceListItem {
+    pub fn row_status(&self) -> RowStatus {
+        let status = &self.status;
+
+        if status.last_exit_status.map(|s| s != 0).unwrap_or(false) {
+            RowStatus::Failed
+        } else if !status.health.is_empty() {
+            RowStatus::Unhealthy
+        } else if status.enabled == EnableState::Disabled {
+            RowStatus::Disabled
+        } else if !self.job_type_filter.intersects(JobTypeFilter::LOADED) {
+            RowStatus::Unloaded
+        } else if status.pid > 0 {
+            RowStatus::Running
+        } else {
+            RowStatus::Idle
+        }
+    }
 }
 
 impl TableListItem for ServiceListItem {
     fn as_row(&self) -> Vec<String> {
-        let session_type = self.status.limit_load_to_session_type.to_string();
-
-        let entry_type = self
-            .status
-            .plist
-            .borrow()
-            .as_ref()
-            .map(|ec| format!("{}/{}", ec.entry_location, ec.entry_type))
-            .unwrap_or("-".to_string());
-
-        let pid = if self.status.pid > 0 && self.job_type_filter.intersects(JobTypeFilter::LOADED) {
-            format!("{}", self.status.pid)
-        } else {
-            "-".to_string()
-        };
+        self.columns.iter().map(|c| c.value(self)).collect()
+    }
 
-        let loaded = if self.job_type_filter.intersects(JobTypeFilter::LOADED) {
-            "✔"
-        } else {
-            "✘"
+    fn id(&self) -> &str {
+        &self.name
+    }
+
+    fn highlights(&self, column: usize) -> &[usize] {
+        match self.columns.get(column) {
+            Some(Column::Label) => &self.label_matches,
+            _ => &[],
+        }
+    }
+
+    fn marked(&self) -> bool {
+        self.marked
+    }
+
+    fn style(&self, _column: usize) -> Style {
+        let roles = theme::roles();
+
+        match self.row_status() {
+            RowStatus::Failed => roles.failed,
+            RowStatus::Unhealthy => roles.unhealthy,
+            RowStatus::Disabled => roles.disabled,
+            RowStatus::Running => roles.running,
+            RowStatus::Idle => roles.idle,
+            RowStatus::Unloaded => Style::none(),
+        }
+    }
+}
+
+impl Queryable for ServiceListItem {
+    fn field(&self, column: Column) -> Option<String> {
+        Some(column.value(self)).filter(|v| v != "-")
+    }
+
+    fn flag(&self, flag: Flag) -> bool {
+        let job = self.status.job.as_ref();
+
+        match flag {
+            Flag::JobType(mask) => self.job_type_filter.intersects(mask),
+            Flag::Running => {
+                self.job_type_filter.intersects(JobTypeFilter::LOADED) && self.status.pid > 0
+            }
+            Flag::KeepAlive => job
+                .map(|j| j.keep_alive != KeepAlive::Never)
+                .unwrap_or(false),
+            Flag::RunAtLoad => job.map(|j| j.run_at_load).unwrap_or(false),
+            Flag::Scheduled => job
+                .map(|j| j.start_interval.is_some() || j.start_calendar_interval.is_some())
+                .unwrap_or(false),
+            Flag::Enabled => self.status.enabled == EnableState::Enabled,
+            Flag::Disabled => self.status.enabled == EnableState::Disabled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{RowStatus, ServiceListItem};
+    use crate::launchd::disabled::EnableState;
+    use crate::launchd::entry_status::LaunchdEntryStatus;
+    use crate::launchd::health::HealthProblem;
+    use crate::launchd::job_type_filter::JobTypeFilter;
+
+    fn item(job_type_filter: JobTypeFilter, status: LaunchdEntryStatus) -> ServiceListItem {
+        ServiceListItem {
+            name: "com.example.job".to_string(),
+            status,
+            job_type_filter,
+            columns: Rc::new(vec![]),
+            label_matches: vec![],
+            marked: false,
+            now: chrono::Local::now().naive_local(),
+        }
+    }
+
+    #[test]
+    fn list_item_row_status() {
+        let loaded = JobTypeFilter::LOADED | JobTypeFilter::USER;
+        let status = |f: fn(&mut LaunchdEntryStatus)| {
+            let mut status = LaunchdEntryStatus::default();
+            f(&mut status);
+            status
         };
 
-        vec![
-            self.name.clone(),
-            session_type,
-            entry_type,
-            pid,
-            loaded.to_string(),
-        ]
+        assert_eq!(
+            item(JobTypeFilter::USER, status(|_| {})).row_status(),
+            RowStatus::Unloaded
+        );
+        assert_eq!(item(loaded, status(|_| {})).row_status(), RowStatus::Idle);
+        assert_eq!(
+            item(loaded, status(|s| s.pid = 42)).row_status(),
+            RowStatus::Running
+        );
+        assert_eq!(
+            item(loaded, status(|s| s.enabled = EnableState::Disabled)).row_status(),
+            RowStatus::Disabled
+        );
+        assert_eq!(
+            item(
+                loaded,
+                status(|s| s.health = vec![HealthProblem::NoProgram])
+            )
+            .row_status(),
+            RowStatus::Unhealthy
+        );
+
+        // A crash outranks everything, even a restarted job
+        assert_eq!(
+            item(
+                loaded,
+                status(|s| {
+                    s.pid = 42;
+                    s.last_exit_status = Some(256);
+                    s.health = vec![HealthProblem::NoProgram];
+                })
+            )
+            .row_status(),
+            RowStatus::Failed
+        );
+        assert_eq!(
+            item(loaded, status(|s| s.last_exit_status = Some(0))).row_status(),
+            RowStatus::Idle
+        );
     }
 }
@@ -1,2 +1,7 @@
+pub mod bulk;
+pub mod columns;
+pub mod detail;
+pub mod filter;
 mod list_item;
+pub mod query;
 pub mod view;
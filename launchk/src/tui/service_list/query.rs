@@ -0,0 +1,439 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::launchd::job_type_filter::JobTypeFilter;
+use crate::tui::service_list::columns::Column;
+
+/// Fields that can be compared in a query or searched with `field:value`
+static FIELDS: [(&str, Column); 8] = [
+    ("label", Column::Label),
+    ("pid", Column::Pid),
+    ("exit", Column::LastExit),
+    ("domain", Column::Domain),
+    ("session", Column::Session),
+    ("type", Column::JobType),
+    ("plist", Column::PlistPath),
+    ("program", Column::Program),
+];
+
+pub fn field_column(name: &str) -> Option<Column> {
+    FIELDS
+        .iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, column)| *column)
+}
+
+/// Yes/no facts about a job, used bare: `loaded and not system`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Flag {
+    JobType(JobTypeFilter),
+    /// Loaded with a PID
+    Running,
+    /// KeepAlive is anything but false
+    KeepAlive,
+    RunAtLoad,
+    /// Has a StartInterval or StartCalendarInterval
+    Scheduled,
+    Enabled,
+    Disabled,
+}
+
+static FLAGS: [(&str, Flag); 13] = [
+    ("system", Flag::JobType(JobTypeFilter::SYSTEM)),
+    ("global", Flag::JobType(JobTypeFilter::GLOBAL)),
+    ("user", Flag::JobType(JobTypeFilter::USER)),
+    ("agent", Flag::JobType(JobTypeFilter::AGENT)),
+    ("daemon", Flag::JobType(JobTypeFilter::DAEMON)),
+    ("loaded", Flag::JobType(JobTypeFilter::LOADED)),
+    ("broken", Flag::JobType(JobTypeFilter::BROKEN)),
+    ("running", Flag::Running),
+    ("keepalive", Flag::KeepAlive),
+    ("runatload", Flag::RunAtLoad),
+    ("scheduled", Flag::Scheduled),
+    ("enabled", Flag::Enabled),
+    ("disabled", Flag::Disabled),
+];
+
+/// What a query is evaluated against
+pub trait Queryable {
+    /// The column's value, None if the job has none
+    fn field(&self, column: Column) -> Option<String>;
+    fn flag(&self, flag: Flag) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Case-insensitive substring
+    Contains,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        };
+
+        write!(f, "{}", op)
+    }
+}
+
+impl Op {
+    /// Numbers compare as numbers, everything else case-insensitively
+    fn apply(&self, field: &str, value: &str) -> bool {
+        let (field, value) = (field.to_lowercase(), value.to_lowercase());
+        let ordering = || match (field.parse::<i64>(), value.parse::<i64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => field.cmp(&value),
+        };
+
+        match self {
+            Op::Eq => ordering() == Ordering::Equal,
+            Op::Ne => ordering() != Ordering::Equal,
+            Op::Lt => ordering() == Ordering::Less,
+            Op::Le => ordering() != Ordering::Greater,
+            Op::Gt => ordering() == Ordering::Greater,
+            Op::Ge => ordering() != Ordering::Less,
+            Op::Contains => field.contains(&value),
+        }
+    }
+}
+
+/// e.g. `loaded and not system and exit!=0`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Flag(Flag),
+    Compare(Column, Op, String),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: tokens.iter().peekable(),
+        };
+
+        let query = parser.or()?;
+
+        match parser.tokens.next() {
+            None => Ok(query),
+            Some(token) => Err(format!("Unexpected {}", token)),
+        }
+    }
+
+    /// A comparison against a field the job has no value for is false
+    pub fn eval<Q: Queryable>(&self, item: &Q) -> bool {
+        match self {
+            Query::Flag(flag) => item.flag(*flag),
+            Query::Compare(column, op, value) => item
+                .field(*column)
+                .map(|field| op.apply(&field, value))
+                .unwrap_or(false),
+            Query::Not(query) => !query.eval(item),
+            Query::And(a, b) => a.eval(item) && b.eval(item),
+            Query::Or(a, b) => a.eval(item) || b.eval(item),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Op(Op),
+    Word(String),
+    Quoted(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+            Token::Op(op) => write!(f, "`{}`", op),
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Quoted(text) => write!(f, "\"{}\"", text),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"()=!<>~\"".contains(c)
+}
+
+fn take_op(chars: &mut Peekable<Chars<'_>>) -> Result<Op, String> {
+    let first = chars.next().expect("Must have op char");
+    let equals = chars.peek() == Some(&'=');
+
+    let op = match (first, equals) {
+        ('=', _) => Op::Eq,
+        ('!', true) => Op::Ne,
+        ('<', true) => Op::Le,
+        ('<', false) => Op::Lt,
+        ('>', true) => Op::Ge,
+        ('>', false) => Op::Gt,
+        ('~', _) => Op::Contains,
+        _ => return Err("Expected `!=`".to_string()),
+    };
+
+    // Second char of ==, !=, <= and >=
+    if equals && first != '~' {
+        chars.next();
+    }
+
+    Ok(op)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut chars = text.chars().peekable();
+    let mut tokens = vec![];
+
+    while let Some(c) = chars.peek().copied() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut quoted = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => quoted.push(c),
+                        None => return Err("Unclosed `\"`".to_string()),
+                    }
+                }
+
+                tokens.push(Token::Quoted(quoted));
+            }
+            '=' | '!' | '<' | '>' | '~' => tokens.push(Token::Op(take_op(&mut chars)?)),
+            _ => {
+                let mut word = String::new();
+
+                while let Some(c) = chars.peek().copied().filter(|c| is_word_char(*c)) {
+                    word.push(c);
+                    chars.next();
+                }
+
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_keyword(token: Option<&&Token>, keyword: &str) -> bool {
+    match token {
+        Some(Token::Word(word)) => word.eq_ignore_ascii_case(keyword),
+        _ => false,
+    }
+}
+
+/// or := and ("or" and)*, and := not ("and" not)*,
+/// not := "not" not | atom, atom := "(" or ")" | field op value | flag
+struct Parser<'a> {
+    tokens: Peekable<std::slice::Iter<'a, Token>>,
+}
+
+impl<'a> Parser<'a> {
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+
+        while is_keyword(self.tokens.peek(), "or") {
+            self.tokens.next();
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.not()?;
+
+        while is_keyword(self.tokens.peek(), "and") {
+            self.tokens.next();
+            query = Query::And(Box::new(query), Box::new(self.not()?));
+        }
+
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<Query, String> {
+        if is_keyword(self.tokens.peek(), "not") {
+            self.tokens.next();
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Query, String> {
+        let name = match self.tokens.next() {
+            Some(Token::Open) => {
+                let query = self.or()?;
+
+                return match self.tokens.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err("Unclosed `(`".to_string()),
+                };
+            }
+            Some(Token::Word(word))
+                if !["and", "or", "not"]
+                    .iter()
+                    .any(|k| word.eq_ignore_ascii_case(k)) =>
+            {
+                word
+            }
+            Some(token) => return Err(format!("Unexpected {}", token)),
+            None => return Err("Expected a flag or comparison".to_string()),
+        };
+
+        if let Some(Token::Op(op)) = self.tokens.peek() {
+            self.tokens.next();
+
+            let column = field_column(name).ok_or_else(|| {
+                format!(
+                    "Unknown field `{}`, expected one of {}",
+                    name,
+                    FIELDS
+                        .iter()
+                        .map(|(f, _)| *f)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+
+            return match self.tokens.next() {
+                Some(Token::Word(value)) | Some(Token::Quoted(value)) => {
+                    Ok(Query::Compare(column, *op, value.clone()))
+                }
+                _ => Err(format!("Expected a value after `{}`", op)),
+            };
+        }
+
+        FLAGS
+            .iter()
+            .find(|(flag, _)| flag.eq_ignore_ascii_case(name))
+            .map(|(_, flag)| Query::Flag(*flag))
+            .ok_or_else(|| format!("Unknown flag `{}`", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Flag, Op, Query, Queryable};
+    use crate::launchd::job_type_filter::JobTypeFilter;
+    use crate::tui::service_list::columns::Column;
+
+    struct Job {
+        job_type: JobTypeFilter,
+        keep_alive: bool,
+        pid: Option<&'static str>,
+        exit: Option<&'static str>,
+    }
+
+    impl Queryable for Job {
+        fn field(&self, column: Column) -> Option<String> {
+            match column {
+                Column::Label => Some("com.example.Agent".to_string()),
+                Column::Pid => self.pid.map(String::from),
+                Column::LastExit => self.exit.map(String::from),
+                _ => None,
+            }
+        }
+
+        fn flag(&self, flag: Flag) -> bool {
+            match flag {
+                Flag::JobType(mask) => self.job_type.intersects(mask),
+                Flag::KeepAlive => self.keep_alive,
+                _ => false,
+            }
+        }
+    }
+
+    #[test]
+    fn query_parse() {
+        assert_eq!(
+            Query::parse("loaded and not system or exit!=0").unwrap(),
+            Query::Or(
+                Box::new(Query::And(
+                    Box::new(Query::Flag(Flag::JobType(JobTypeFilter::LOADED))),
+                    Box::new(Query::Not(Box::new(Query::Flag(Flag::JobType(
+                        JobTypeFilter::SYSTEM
+                    )))))
+                )),
+                Box::new(Query::Compare(Column::LastExit, Op::Ne, "0".to_string()))
+            )
+        );
+
+        assert_eq!(
+            Query::parse("label ~ \"example agent\"").unwrap(),
+            Query::Compare(Column::Label, Op::Contains, "example agent".to_string())
+        );
+
+        let error = |text| Query::parse(text).unwrap_err();
+        assert_eq!(error(""), "Expected a flag or comparison");
+        assert_eq!(error("loaded and"), "Expected a flag or comparison");
+        assert_eq!(error("(loaded or user"), "Unclosed `(`");
+        assert_eq!(error("loaded)"), "Unexpected `)`");
+        assert_eq!(error("exit !="), "Expected a value after `!=`");
+        assert_eq!(error("crashed"), "Unknown flag `crashed`");
+        assert!(error("uptime > 5").starts_with("Unknown field `uptime`"));
+        assert_eq!(error("label ~ \"x"), "Unclosed `\"`");
+    }
+
+    #[test]
+    fn query_eval() {
+        let crashed = Job {
+            job_type: JobTypeFilter::USER | JobTypeFilter::AGENT | JobTypeFilter::LOADED,
+            keep_alive: true,
+            pid: None,
+            exit: Some("78"),
+        };
+        let running = Job {
+            job_type: JobTypeFilter::SYSTEM | JobTypeFilter::DAEMON | JobTypeFilter::LOADED,
+            keep_alive: false,
+            pid: Some("120"),
+            exit: None,
+        };
+
+        let matches = |text: &str, job: &Job| Query::parse(text).unwrap().eval(job);
+
+        assert!(matches("loaded and not system and exit!=0", &crashed));
+        assert!(!matches("loaded and not system and exit!=0", &running));
+        assert!(matches("keepalive and user", &crashed));
+        assert!(matches("NOT (keepalive OR agent)", &running));
+        // No exit status is neither 0 nor anything else
+        assert!(!matches("exit!=0", &running));
+        assert!(!matches("exit=0", &running));
+        assert!(matches("pid > 99 and pid <= 120", &running));
+        assert!(matches("label ~ EXAMPLE.agent", &running));
+        assert!(matches("label == com.example.agent", &running));
+    }
+}
@@ -6,35 +6,54 @@ use std::sync::mpsc::Sender;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use chrono::Local;
 use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key};
 use cursive::view::ViewWrapper;
+use cursive::views::TextContent;
 use cursive::{Cursive, View, XY};
 
 use tokio::runtime::Handle;
 use tokio::time::interval;
-use xpc_sys::objects::unix_fifo::UnixFifo;
 use xpc_sys::enums::{DomainType, SessionType};
+use xpc_sys::objects::unix_fifo::UnixFifo;
 
+use crate::config::{Config, ConfirmPolicy};
+use crate::launchd::convert::cron::to_cron;
+use crate::launchd::convert::systemd::to_systemd;
+use crate::launchd::graph::JobGraph;
+use crate::launchd::job::LaunchdJob;
 use crate::launchd::job_type_filter::JobTypeFilter;
-use crate::launchd::plist::{edit_and_replace, LABEL_TO_ENTRY_CONFIG};
+use crate::launchd::plist::{edit_with_editor, read_plist, LABEL_TO_ENTRY_CONFIG};
 use crate::launchd::query::procinfo;
 use crate::launchd::query::{disable, enable, list_all, load, unload};
 use crate::launchd::{
     entry_status::get_entry_status, entry_status::LaunchdEntryStatus, plist::LaunchdPlist,
 };
+use crate::tui::dialog::{show_bulk_results, show_graph, show_health, show_schedule};
 use crate::tui::omnibox::command::OmniboxCommand;
 
 use crate::tui::omnibox::state::OmniboxState;
 use crate::tui::omnibox::subscribed_view::{OmniboxResult, OmniboxSubscriber};
 use crate::tui::omnibox::view::{OmniboxError, OmniboxEvent, OmniboxMode};
 use crate::tui::pager::show_pager;
+use crate::tui::preset::PresetSort;
 use crate::tui::root::CbSinkMessage;
+use crate::tui::service_list::bulk::{summarize_targets, BulkAction};
+use crate::tui::service_list::columns::{Column, ColumnConfig};
+use crate::tui::service_list::detail::describe;
+use crate::tui::service_list::filter::LabelFilter;
 use crate::tui::service_list::list_item::ServiceListItem;
+use crate::tui::table::sort::TableSort;
 use crate::tui::table::table_list_view::TableListView;
 
 /// Polls XPC for job list
-async fn poll_running_jobs(svcs: Arc<RwLock<HashSet<String>>>, cb_sink: Sender<CbSinkMessage>) {
-    let mut interval = interval(Duration::from_secs(1));
+async fn poll_running_jobs(
+    svcs: Arc<RwLock<HashSet<String>>>,
+    cb_sink: Sender<CbSinkMessage>,
+    every: Duration,
+) {
+    let mut interval = interval(every);
 
     loop {
         interval.tick().await;
@@ -52,30 +71,171 @@ async fn poll_running_jobs(svcs: Arc<RwLock<HashSet<String>>>, cb_sink: Sender<C
 }
 
 pub struct ServiceListView {
+    runtime_handle: Handle,
     cb_sink: Sender<CbSinkMessage>,
     running_jobs: Arc<RwLock<HashSet<String>>>,
     table_list_view: TableListView<ServiceListItem>,
-    label_filter: RefCell<String>,
+    label_filter: RefCell<LabelFilter>,
     job_type_filter: RefCell<JobTypeFilter>,
+    /// Shared with the detail pane's TextView
+    detail: TextContent,
+    /// What the detail pane was last filled in from
+    detail_for: Option<ServiceListItem>,
+    column_config: Vec<ColumnConfig>,
+    columns: Rc<Vec<Column>>,
+    /// Labels marked for bulk actions, kept while they are filtered out
+    marked: HashSet<String>,
+    editor: Vec<String>,
+    pager: Vec<String>,
+    confirm: ConfirmPolicy,
+}
+
+/// The bulk form of a single job command, for when jobs are marked
+fn bulk_action(cmd: &OmniboxCommand) -> Option<BulkAction> {
+    match cmd {
+        OmniboxCommand::LoadRequest => Some(BulkAction::Load),
+        OmniboxCommand::UnloadRequest => Some(BulkAction::Unload),
+        OmniboxCommand::EnableRequest => Some(BulkAction::Enable),
+        OmniboxCommand::DisableRequest => Some(BulkAction::Disable),
+        OmniboxCommand::Reload => Some(BulkAction::Reload),
+        _ => None,
+    }
+}
+
+fn bulk_request(
+    action: BulkAction,
+    dt: DomainType,
+    st: Option<SessionType>,
+) -> Vec<OmniboxCommand> {
+    vec![OmniboxCommand::BulkRequest(action, Some((dt, st)))]
+}
+
+/// Asked once, for every marked job launchd doesn't know the domain (or session) of
+fn bulk_prompt(action: BulkAction) -> fn(DomainType, Option<SessionType>) -> Vec<OmniboxCommand> {
+    match action {
+        BulkAction::Load => |dt, st| bulk_request(BulkAction::Load, dt, st),
+        BulkAction::Unload => |dt, st| bulk_request(BulkAction::Unload, dt, st),
+        BulkAction::Enable => |dt, st| bulk_request(BulkAction::Enable, dt, st),
+        BulkAction::Disable => |dt, st| bulk_request(BulkAction::Disable, dt, st),
+        BulkAction::Reload => |dt, st| bulk_request(BulkAction::Reload, dt, st),
+    }
+}
+
+/// Run one job's part of a bulk action, with its own domain and session
+/// where launchd has them
+fn run_bulk(
+    action: BulkAction,
+    label: &str,
+    fallback: &Option<(DomainType, Option<SessionType>)>,
+) -> Result<(), String> {
+    let LaunchdEntryStatus {
+        plist,
+        domain,
+        limit_load_to_session_type,
+        ..
+    } = get_entry_status(label);
+
+    let dt = Some(domain)
+        .filter(|d| *d != DomainType::Unknown)
+        .or_else(|| fallback.as_ref().map(|(d, _)| d.clone()))
+        .ok_or_else(|| "Unknown domain".to_string())?;
+
+    let st = Some(limit_load_to_session_type.clone())
+        .filter(|s| *s != SessionType::Unknown)
+        .or_else(|| fallback.as_ref().and_then(|(_, s)| s.clone()));
+
+    let plist_path = plist.map(|p| p.plist_path);
+    let plist_path = || {
+        plist_path
+            .clone()
+            .ok_or_else(|| "Cannot find plist".to_string())
+    };
+    let session = || st.clone().ok_or_else(|| "Unknown session type".to_string());
+    let label = label.to_string();
+
+    let unload_job = || {
+        unload(
+            label.clone(),
+            plist_path()?,
+            Some(dt.clone()),
+            Some(limit_load_to_session_type.clone()),
+            None,
+        )
+        .map_err(|e| e.to_string())
+    };
+    let load_job = || {
+        load(
+            label.clone(),
+            plist_path()?,
+            Some(dt.clone()),
+            Some(session()?),
+            None,
+        )
+        .map_err(|e| e.to_string())
+    };
+
+    match action {
+        BulkAction::Load => load_job().map(|_| ()),
+        BulkAction::Unload => unload_job().map(|_| ()),
+        BulkAction::Reload => unload_job().and_then(|_| load_job()).map(|_| ()),
+        BulkAction::Enable => enable(label.clone(), dt.clone())
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        BulkAction::Disable => disable(label.clone(), dt.clone())
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    }
+}
+
+fn table_for(columns: &[ColumnConfig]) -> TableListView<ServiceListItem> {
+    TableListView::new(
+        columns
+            .iter()
+            .map(|c| (c.name.title(), c.width()))
+            .collect::<Vec<(&str, Option<usize>)>>(),
+    )
 }
 
 impl ServiceListView {
-    pub fn new(runtime_handle: &Handle, cb_sink: Sender<CbSinkMessage>) -> Self {
+    pub fn new(
+        runtime_handle: &Handle,
+        cb_sink: Sender<CbSinkMessage>,
+        detail: TextContent,
+        config: &Config,
+    ) -> Self {
         let arc_svc = Arc::new(RwLock::new(HashSet::new()));
-        runtime_handle.spawn(poll_running_jobs(arc_svc.clone(), cb_sink.clone()));
+        runtime_handle.spawn(poll_running_jobs(
+            arc_svc.clone(),
+            cb_sink.clone(),
+            config.poll.jobs(),
+        ));
+
+        let columns = config.columns();
 
         Self {
+            runtime_handle: runtime_handle.clone(),
             cb_sink,
             running_jobs: arc_svc.clone(),
-            label_filter: RefCell::new("".into()),
-            job_type_filter: RefCell::new(JobTypeFilter::launchk_default()),
-            table_list_view: TableListView::new(vec![
-                ("Name", None),
-                ("Session", Some(12)),
-                ("Job Type", Some(14)),
-                ("PID", Some(6)),
-                ("Loaded", Some(6)),
-            ]),
+            label_filter: RefCell::new(LabelFilter::new("", config.filters.filter_mode)),
+            job_type_filter: RefCell::new(config.filters.job_type_filter()),
+            detail,
+            detail_for: None,
+            table_list_view: table_for(&columns),
+            columns: Rc::new(columns.iter().map(|c| c.name).collect()),
+            column_config: columns,
+            marked: HashSet::new(),
+            editor: config.editor(),
+            pager: config.pager(),
+            confirm: config.confirm,
+        }
+    }
+
+    /// Ask first if the config says to
+    fn confirm(ask: bool, prompt: String, cmds: Vec<OmniboxCommand>) -> OmniboxCommand {
+        if ask {
+            OmniboxCommand::Confirm(prompt, cmds)
+        } else {
+            OmniboxCommand::Chain(cmds)
         }
     }
 
@@ -83,28 +243,25 @@ impl ServiceListView {
         let plists = LABEL_TO_ENTRY_CONFIG.read().ok()?;
         let running = self.running_jobs.read().ok()?;
 
-        let name_filter = self.label_filter.borrow();
+        let label_filter = self.label_filter.borrow();
         let job_type_filter = self.job_type_filter.borrow();
 
         let running_no_plist = running.iter().filter(|r| !plists.contains_key(*r));
+        let now = Local::now().naive_local();
 
-        let mut items: Vec<ServiceListItem> = plists
+        let mut items: Vec<(i64, ServiceListItem)> = plists
             .keys()
             .into_iter()
             .chain(running_no_plist)
             .filter_map(|label| {
-                if !name_filter.is_empty()
-                    && !label
-                        .to_ascii_lowercase()
-                        .contains(name_filter.to_ascii_lowercase().as_str())
-                {
-                    return None;
-                }
+                // Label terms only need the name, so rule jobs out
+                // before asking launchd about them
+                let mut matched = label_filter.match_label(label)?;
 
                 let status = get_entry_status(label);
                 let is_loaded = running.contains(label);
 
-                let entry_job_type_filter = status
+                let mut entry_job_type_filter = status
                     .plist
                     .as_ref()
                     .map(|ec| ec.job_type_filter(is_loaded))
@@ -114,20 +271,34 @@ impl ServiceListView {
                         JobTypeFilter::default()
                     });
 
+                if !status.health.is_empty() {
+                    entry_job_type_filter.insert(JobTypeFilter::BROKEN);
+                }
+
                 if !job_type_filter.is_empty() && !entry_job_type_filter.contains(*job_type_filter)
                 {
                     return None;
                 }
 
-                Some(ServiceListItem {
+                let mut item = ServiceListItem {
                     status,
                     name: label.clone(),
                     job_type_filter: entry_job_type_filter,
-                })
+                    columns: self.columns.clone(),
+                    label_matches: vec![],
+                    marked: self.marked.contains(label),
+                    now,
+                };
+
+                matched.score += label_filter.match_fields(&item)?;
+                item.label_matches = matched.label;
+
+                Some((matched.score, item))
             })
             .collect();
 
-        items.sort_by(|a, b| {
+        // Unloaded first then by name, unless the table is sorted by a column
+        items.sort_by(|(_, a), (_, b)| {
             let loaded_a = a.job_type_filter.intersects(JobTypeFilter::LOADED);
             let loaded_b = b.job_type_filter.intersects(JobTypeFilter::LOADED);
             let name_cmp = a.name.cmp(&b.name);
@@ -141,26 +312,50 @@ impl ServiceListView {
             }
         });
 
-        Some(items)
+        // Best fuzzy matches first
+        if label_filter.ranks() {
+            items.sort_by(|(a, _), (b, _)| b.cmp(a));
+        }
+
+        Some(items.into_iter().map(|(_, item)| item).collect())
+    }
+
+    /// Only redraw when the highlighted job or its status changed
+    fn update_detail(&mut self) {
+        let item = self
+            .table_list_view
+            .get_highlighted_row()
+            .map(|i| (*i).clone());
+
+        if item == self.detail_for {
+            return;
+        }
+
+        self.detail
+            .set_content(item.as_ref().map(describe).unwrap_or_default());
+        self.detail_for = item;
     }
 
     fn handle_state_update(&mut self, state: OmniboxState) -> OmniboxResult {
         let OmniboxState {
             mode,
             label_filter,
+            filter_mode,
             job_type_filter,
             ..
         } = state;
 
         match mode {
             OmniboxMode::LabelFilter => {
-                self.label_filter.replace(label_filter);
+                self.label_filter
+                    .replace(LabelFilter::new(&label_filter, filter_mode));
             }
             OmniboxMode::JobTypeFilter => {
                 self.job_type_filter.replace(job_type_filter);
             }
             OmniboxMode::Idle => {
-                self.label_filter.replace(label_filter);
+                self.label_filter
+                    .replace(LabelFilter::new(&label_filter, filter_mode));
                 self.job_type_filter.replace(job_type_filter);
             }
             _ => {}
@@ -169,6 +364,55 @@ impl ServiceListView {
         Ok(None)
     }
 
+    fn toggle_mark(&mut self) -> EventResult {
+        let label = match self.table_list_view.get_highlighted_row() {
+            Some(item) => item.name.clone(),
+            None => return EventResult::Ignored,
+        };
+
+        if !self.marked.remove(&label) {
+            self.marked.insert(label);
+        }
+
+        // Move on, so space can mark a run of jobs
+        self.table_list_view.on_event(Event::Key(Key::Down))
+    }
+
+    /// Confirm a bulk action, unless a domain or session has to be asked
+    /// for first
+    fn handle_bulk_request(
+        &self,
+        action: BulkAction,
+        fallback: Option<(DomainType, Option<SessionType>)>,
+    ) -> OmniboxResult {
+        let mut labels: Vec<String> = self.marked.iter().cloned().collect();
+        labels.sort();
+
+        if fallback.is_none() {
+            let unknown = labels.iter().find(|label| {
+                let status = get_entry_status(label.as_str());
+
+                status.domain == DomainType::Unknown
+                    || (action.needs_session()
+                        && status.limit_load_to_session_type == SessionType::Unknown)
+            });
+
+            if let Some(label) = unknown {
+                return Ok(Some(OmniboxCommand::DomainSessionPrompt(
+                    label.clone(),
+                    !action.needs_session(),
+                    bulk_prompt(action),
+                )));
+            }
+        }
+
+        Ok(Some(Self::confirm(
+            self.confirm.bulk,
+            summarize_targets(action, &labels),
+            vec![OmniboxCommand::Bulk(action, labels, fallback)],
+        )))
+    }
+
     fn get_active_list_item(&self) -> Result<Rc<ServiceListItem>, OmniboxError> {
         self.table_list_view
             .get_highlighted_row()
@@ -186,8 +430,39 @@ impl ServiceListView {
         Ok((item.clone(), plist.clone()))
     }
 
-    fn handle_command(&self, cmd: OmniboxCommand) -> OmniboxResult {
+    fn handle_command(&mut self, cmd: OmniboxCommand) -> OmniboxResult {
+        if let Some(action) = bulk_action(&cmd).filter(|_| !self.marked.is_empty()) {
+            return Ok(Some(OmniboxCommand::BulkRequest(action, None)));
+        }
+
         match cmd {
+            OmniboxCommand::MarkAll => {
+                let shown = self.present_services().unwrap_or_default();
+                self.marked.extend(shown.into_iter().map(|item| item.name));
+                Ok(None)
+            }
+            OmniboxCommand::UnmarkAll => {
+                self.marked.clear();
+                Ok(None)
+            }
+            OmniboxCommand::BulkRequest(action, fallback) => {
+                self.handle_bulk_request(action, fallback)
+            }
+            OmniboxCommand::Bulk(action, labels, fallback) => {
+                let results: Vec<(String, Result<(), String>)> = labels
+                    .into_iter()
+                    .map(|label| {
+                        let result = run_bulk(action, &label, &fallback);
+                        (label, result)
+                    })
+                    .collect();
+
+                self.cb_sink
+                    .send(show_bulk_results(action, results))
+                    .expect("Must show bulk results");
+
+                Ok(None)
+            }
             OmniboxCommand::Reload => {
                 let (ServiceListItem { name, status, .. }, ..) = self.with_active_item_plist()?;
                 let LaunchdEntryStatus {
@@ -205,10 +480,14 @@ impl ServiceListView {
                             ]
                         }),
                     )),
-                    (st, dt) => Ok(Some(OmniboxCommand::Chain(vec![
-                        OmniboxCommand::Unload(dt.clone(), None),
-                        OmniboxCommand::Load(st, dt, None),
-                    ]))),
+                    (st, dt) => Ok(Some(Self::confirm(
+                        self.confirm.reload,
+                        format!("Reload {}?", name),
+                        vec![
+                            OmniboxCommand::Unload(dt.clone(), None),
+                            OmniboxCommand::Load(st, dt, None),
+                        ],
+                    ))),
                 }
             }
             OmniboxCommand::LoadRequest => {
@@ -235,7 +514,11 @@ impl ServiceListView {
                         true,
                         |dt, _| vec![OmniboxCommand::Unload(dt, None)],
                     ))),
-                    _ => Ok(Some(OmniboxCommand::Unload(domain, None))),
+                    _ => Ok(Some(Self::confirm(
+                        self.confirm.unload,
+                        format!("Unload {}?", name),
+                        vec![OmniboxCommand::Unload(domain, None)],
+                    ))),
                 }
             }
             OmniboxCommand::EnableRequest => {
@@ -256,25 +539,97 @@ impl ServiceListView {
                         true,
                         |dt, _| vec![OmniboxCommand::Disable(dt)],
                     ))),
-                    _ => Ok(Some(OmniboxCommand::Chain(vec![OmniboxCommand::Disable(
-                        domain,
-                    )]))),
+                    _ => Ok(Some(Self::confirm(
+                        self.confirm.disable,
+                        format!("Disable {}?", name),
+                        vec![OmniboxCommand::Disable(domain)],
+                    ))),
                 }
             }
             OmniboxCommand::Edit => {
                 let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
-                edit_and_replace(&plist).map_err(OmniboxError::CommandError)?;
+                let edit = edit_with_editor(&plist, &self.editor);
 
                 // Clear term
                 self.cb_sink
                     .send(Box::new(Cursive::clear))
                     .expect("Must clear");
 
-                Ok(Some(OmniboxCommand::Confirm(
-                    format!("Reload {}?", name),
-                    vec![OmniboxCommand::Reload],
+                Ok(Some(OmniboxCommand::ReviewEdit(
+                    name,
+                    edit.map_err(OmniboxError::CommandError)?,
                 )))
             }
+            OmniboxCommand::TreeEditRequest => {
+                let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
+                Ok(Some(OmniboxCommand::TreeEdit(name, plist)))
+            }
+            OmniboxCommand::ColumnsRequest => {
+                Ok(Some(OmniboxCommand::Columns(self.column_config.clone())))
+            }
+            OmniboxCommand::PresetSaveRequest => {
+                let sort = self.table_list_view.sort().map(|s| PresetSort {
+                    column: self.columns[s.column],
+                    descending: !s.ascending,
+                });
+
+                Ok(Some(OmniboxCommand::PresetSave(sort)))
+            }
+            OmniboxCommand::ApplyPreset(preset) => {
+                // The root layout applies the filters either way, so
+                // a hidden sort column only skips the sort
+                let sort = match preset.sort {
+                    None => None,
+                    Some(sort) => {
+                        let column = self
+                            .columns
+                            .iter()
+                            .position(|c| *c == sort.column)
+                            .ok_or_else(|| {
+                                OmniboxError::CommandError(format!(
+                                    "Applied preset {} without its sort: {} is not shown. Add it with :columns",
+                                    preset.name,
+                                    sort.column.title()
+                                ))
+                            })?;
+
+                        Some(TableSort {
+                            column,
+                            ascending: !sort.descending,
+                        })
+                    }
+                };
+
+                self.table_list_view.set_sort(sort);
+                Ok(None)
+            }
+            OmniboxCommand::SetColumns(columns) => {
+                // Keep sorting by the same column, wherever it moved.
+                // Unsorted if it was hidden.
+                let sort = self.table_list_view.sort().and_then(|sort| {
+                    let sorted = self.columns.get(sort.column)?;
+                    let column = columns.iter().position(|c| c.name == *sorted)?;
+                    Some(TableSort { column, ..sort })
+                });
+
+                self.table_list_view = table_for(&columns);
+                self.table_list_view.set_sort(sort);
+                self.columns = Rc::new(columns.iter().map(|c| c.name).collect());
+                self.column_config = columns;
+                Ok(None)
+            }
+            OmniboxCommand::LogsRequest => {
+                let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
+                Ok(Some(OmniboxCommand::Logs(name, plist)))
+            }
+            OmniboxCommand::ExportRequest => {
+                let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
+                Ok(Some(OmniboxCommand::Export(name, plist)))
+            }
+            OmniboxCommand::ImportRequest => {
+                let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
+                Ok(Some(OmniboxCommand::Import(name, plist)))
+            }
             OmniboxCommand::Load(st, dt, _handle) => {
                 let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
                 load(name, plist.plist_path, Some(dt), Some(st), None)
@@ -311,6 +666,79 @@ impl ServiceListView {
                     .map(|_| None)
                     .map_err(|e| OmniboxError::CommandError(e.to_string()))
             }
+            OmniboxCommand::Schedule => {
+                let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
+                let schedule = plist.schedule.ok_or_else(|| {
+                    OmniboxError::CommandError(format!("{} has no StartCalendarInterval", name))
+                })?;
+
+                self.cb_sink
+                    .send(show_schedule(name, schedule))
+                    .expect("Must show schedule");
+
+                Ok(None)
+            }
+            OmniboxCommand::Graph => {
+                let (ServiceListItem { name, .. }, _) = self.with_active_item_plist()?;
+                let plists = LABEL_TO_ENTRY_CONFIG
+                    .read()
+                    .map_err(|e| OmniboxError::CommandError(e.to_string()))?
+                    .plist_paths();
+
+                // Reads every plist, keep it off the UI thread
+                let cb_sink = self.cb_sink.clone();
+                self.runtime_handle.spawn_blocking(move || {
+                    cb_sink
+                        .send(show_graph(JobGraph::from_plists(&plists), name))
+                        .expect("Must show graph");
+                });
+
+                Ok(None)
+            }
+            OmniboxCommand::Health => {
+                let (ServiceListItem { name, status, .. }, _) = self.with_active_item_plist()?;
+
+                self.cb_sink
+                    .send(show_health(name, status.health))
+                    .expect("Must show health");
+
+                Ok(None)
+            }
+            OmniboxCommand::Systemd | OmniboxCommand::Crontab => {
+                let (ServiceListItem { name, .. }, plist) = self.with_active_item_plist()?;
+                let (plist, _) =
+                    read_plist(&plist.plist_path).map_err(OmniboxError::CommandError)?;
+                let job = LaunchdJob::from_plist(&plist).map_err(OmniboxError::CommandError)?;
+
+                let converted = if cmd == OmniboxCommand::Systemd {
+                    to_systemd(&job).map(|c| {
+                        let timer = c
+                            .output
+                            .timer
+                            .as_ref()
+                            .map(|t| format!("\n# {}.timer\n{}", name, t))
+                            .unwrap_or_default();
+
+                        format!(
+                            "{}# {}.service\n{}{}",
+                            c.report("# Not converted: "),
+                            name,
+                            c.output.service,
+                            timer
+                        )
+                    })
+                } else {
+                    to_cron(&job).map(|c| {
+                        format!("{}{}\n", c.report("# Not converted: "), c.output.join("\n"))
+                    })
+                }
+                .map_err(OmniboxError::CommandError)?;
+
+                show_pager(&self.cb_sink, &self.pager, converted.as_bytes())
+                    .map_err(OmniboxError::CommandError)?;
+
+                Ok(None)
+            }
             OmniboxCommand::ProcInfo => {
                 let (ServiceListItem { name, status, .. }, _) = self.with_active_item_plist()?;
 
@@ -336,7 +764,7 @@ impl ServiceListView {
                     .expect("Must join read thread")
                     .map_err(|e| OmniboxError::CommandError(e))?;
 
-                show_pager(&self.cb_sink, &procinfo_data)
+                show_pager(&self.cb_sink, &self.pager, &procinfo_data)
                     .map_err(|e| OmniboxError::CommandError(e))?;
 
                 Ok(None)
@@ -355,6 +783,15 @@ impl ViewWrapper for ServiceListView {
         if let Some(sorted) = self.present_services() {
             self.with_view_mut(|v| v.replace_and_preserve_selection(sorted));
         }
+
+        self.update_detail();
+    }
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char(' ') => self.toggle_mark(),
+            _ => self.table_list_view.on_event(event),
+        }
     }
 
     fn wrap_take_focus(&mut self, _: Direction) -> bool {
@@ -1,10 +1,11 @@
 use std::cell::Cell;
 
-use cursive::theme::{BaseColor, Color, Effect, Style};
 use cursive::{Printer, Vec2, View, XY};
 use xpc_sys::csr::{csr_check, CsrConfig};
 use xpc_sys::rs_sysctlbyname;
 
+use crate::tui::theme;
+
 pub struct SysInfo {
     current_size: Cell<XY<usize>>,
 }
@@ -36,8 +37,7 @@ impl View for SysInfo {
             )
         };
 
-        let bold = Style::from(Color::Light(BaseColor::White));
-        let bold = bold.combine(Effect::Bold);
+        let bold = theme::roles().label;
 
         printer.with_style(bold, |p| p.print(XY::new(0, 0), mac_os_label));
         printer.print(XY::new(mac_os_label.chars().count() + 1, 0), &mac_os_data);
@@ -70,6 +70,21 @@ impl ColumnSizer {
         }
     }
 
+    /// The column drawn at x, e.g. for a click on the headers
+    pub fn column_at(&self, x: usize) -> Option<usize> {
+        let mut start = 0;
+
+        for i in 0..self.num_columns {
+            start += self.width_for_index(i);
+
+            if x < start {
+                return Some(i);
+            }
+        }
+
+        None
+    }
+
     /// Call when x changes to recompute dynamic_column_size and padding
     pub fn update_x(&self, x: usize) {
         let mut remaining = if x > self.user_sizes_total {
@@ -78,7 +93,8 @@ impl ColumnSizer {
             0
         };
 
-        let mut dcs = remaining / self.num_dynamic_columns;
+        // Every column can be user sized, then there is nothing to divide
+        let mut dcs = remaining.checked_div(self.num_dynamic_columns).unwrap_or(0);
         if dcs > 35 {
             dcs = 35;
         }
@@ -88,7 +104,30 @@ impl ColumnSizer {
         }
 
         self.dynamic_column_size.set(dcs);
-        self.padding
-            .set(remaining / (self.num_dynamic_columns + self.user_sizes.len()));
+        self.padding.set(
+            remaining
+                .checked_div(self.num_dynamic_columns + self.user_sizes.len())
+                .unwrap_or(0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ColumnSizer;
+
+    #[test]
+    fn column_sizer_column_at() {
+        let sizer = ColumnSizer::new(vec![("Label", None), ("PID", Some(6)), ("Type", None)]);
+        sizer.update_x(40);
+
+        let label = sizer.width_for_index(0);
+        let pid = sizer.width_for_index(1);
+
+        assert_eq!(sizer.column_at(0), Some(0));
+        assert_eq!(sizer.column_at(label - 1), Some(0));
+        assert_eq!(sizer.column_at(label), Some(1));
+        assert_eq!(sizer.column_at(label + pid), Some(2));
+        assert_eq!(sizer.column_at(1000), None);
     }
 }
@@ -1,3 +1,4 @@
 mod column_sizer;
+pub mod sort;
 mod table_headers;
 pub mod table_list_view;
@@ -0,0 +1,210 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Sort column and direction, set by `o` (next column) and `O` (reverse)
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TableSort {
+    pub column: usize,
+    pub ascending: bool,
+}
+
+impl TableSort {
+    /// Unsorted -> first column -> ... -> last column -> unsorted
+    pub fn next_column(sort: Option<TableSort>, num_columns: usize) -> Option<TableSort> {
+        let column = match sort {
+            None => 0,
+            Some(TableSort { column, .. }) => column + 1,
+        };
+
+        if column < num_columns {
+            Some(TableSort {
+                column,
+                ascending: sort.map(|s| s.ascending).unwrap_or(true),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Flip the direction, sorting by the first column if unsorted
+    pub fn reverse(sort: Option<TableSort>) -> Option<TableSort> {
+        match sort {
+            None => Some(TableSort {
+                column: 0,
+                ascending: false,
+            }),
+            Some(sort) => Some(TableSort {
+                ascending: !sort.ascending,
+                ..sort
+            }),
+        }
+    }
+
+    /// Clicking a header: sort by it, or flip the direction if it is
+    /// already the sorted column
+    pub fn by_column(sort: Option<TableSort>, column: usize) -> Option<TableSort> {
+        match sort {
+            Some(sort) if sort.column == column => TableSort::reverse(Some(sort)),
+            _ => Some(TableSort {
+                column,
+                ascending: true,
+            }),
+        }
+    }
+
+    /// Header suffix for the sorted column
+    pub fn indicator(&self) -> &'static str {
+        if self.ascending {
+            "▲"
+        } else {
+            "▼"
+        }
+    }
+
+    /// Compare two rows' fields. Missing values ("-" or empty) go last
+    /// whichever the direction.
+    pub fn compare(&self, a: &[String], b: &[String]) -> Ordering {
+        let a = a.get(self.column).map(String::as_str).unwrap_or("");
+        let b = b.get(self.column).map(String::as_str).unwrap_or("");
+
+        match (is_missing(a), is_missing(b)) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) if self.ascending => compare_fields(a, b),
+            (false, false) => compare_fields(a, b).reverse(),
+        }
+    }
+}
+
+fn is_missing(field: &str) -> bool {
+    let field = field.trim();
+    field.is_empty() || field == "-"
+}
+
+fn take_number(chars: &mut Peekable<Chars<'_>>) -> u128 {
+    let mut n: u128 = 0;
+
+    while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+        n = n.saturating_mul(10).saturating_add(d as u128);
+        chars.next();
+    }
+
+    n
+}
+
+/// Numbers compare as numbers (PIDs, exit codes, `sig9` before `sig15`),
+/// everything else case-insensitively
+pub fn compare_fields(a: &str, b: &str) -> Ordering {
+    if let (Ok(a), Ok(b)) = (a.trim().parse::<i64>(), b.trim().parse::<i64>()) {
+        return a.cmp(&b);
+    }
+
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let ordering = match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                take_number(&mut a_chars).cmp(&take_number(&mut b_chars))
+            }
+            (Some(x), Some(y)) => {
+                a_chars.next();
+                b_chars.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_fields, TableSort};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn sort_compare_fields() {
+        assert_eq!(compare_fields("9", "10"), Ordering::Less);
+        assert_eq!(compare_fields("-1", "0"), Ordering::Less);
+        assert_eq!(compare_fields("sig9", "sig15"), Ordering::Less);
+        assert_eq!(
+            compare_fields("com.apple.B", "com.apple.a"),
+            Ordering::Greater
+        );
+        assert_eq!(compare_fields("job2.plist", "job10.plist"), Ordering::Less);
+        assert_eq!(compare_fields("abc", "ab"), Ordering::Greater);
+
+        let row = |field: &str| vec!["x".to_string(), field.to_string()];
+        let mut pids = vec![row("-"), row("120"), row("87"), row("4031")];
+        let mut sort = TableSort {
+            column: 1,
+            ascending: true,
+        };
+
+        pids.sort_by(|a, b| sort.compare(a, b));
+        assert_eq!(pids, vec![row("87"), row("120"), row("4031"), row("-")]);
+
+        sort = TableSort::reverse(Some(sort)).unwrap();
+        pids.sort_by(|a, b| sort.compare(a, b));
+        assert_eq!(pids, vec![row("4031"), row("120"), row("87"), row("-")]);
+    }
+
+    #[test]
+    fn sort_cycle_columns() {
+        let first = TableSort::next_column(None, 2);
+        assert_eq!(
+            first,
+            Some(TableSort {
+                column: 0,
+                ascending: true
+            })
+        );
+
+        let descending = TableSort::reverse(first);
+        assert_eq!(
+            TableSort::next_column(descending, 2),
+            Some(TableSort {
+                column: 1,
+                ascending: false
+            })
+        );
+        assert_eq!(
+            TableSort::next_column(TableSort::next_column(descending, 2), 2),
+            None
+        );
+    }
+
+    #[test]
+    fn sort_by_column() {
+        let pid = TableSort::by_column(None, 1);
+        assert_eq!(
+            pid,
+            Some(TableSort {
+                column: 1,
+                ascending: true
+            })
+        );
+        assert_eq!(
+            TableSort::by_column(pid, 1),
+            Some(TableSort {
+                column: 1,
+                ascending: false
+            })
+        );
+        assert_eq!(
+            TableSort::by_column(TableSort::by_column(pid, 1), 0),
+            Some(TableSort {
+                column: 0,
+                ascending: true
+            })
+        );
+    }
+}
@@ -1,42 +1,61 @@
+use std::cell::Cell;
+use std::rc::Rc;
 use std::sync::Arc;
 
-use cursive::theme::{BaseColor, Color, Effect, Style};
 use cursive::{Printer, View, XY};
 
+use crate::tui::theme;
+
 use super::column_sizer::ColumnSizer;
+use super::sort::TableSort;
 
 /// Draw column headers from their names + a column sizer
 pub struct TableHeaders {
     columns: Vec<String>,
     column_sizer: Arc<ColumnSizer>,
+    sort: Rc<Cell<Option<TableSort>>>,
 }
 
 impl TableHeaders {
     pub fn new<S: Into<String>>(
         columns: impl Iterator<Item = S>,
         column_sizer: Arc<ColumnSizer>,
+        sort: Rc<Cell<Option<TableSort>>>,
     ) -> Self {
         Self {
             columns: columns.map(|f| f.into()).collect(),
             column_sizer,
+            sort,
         }
     }
 }
 
 impl View for TableHeaders {
     fn draw(&self, printer: &Printer<'_, '_>) {
-        let bold = Style::from(Color::Dark(BaseColor::Blue)).combine(Effect::Bold);
+        let bold = theme::roles().header;
+
+        let sort = self.sort.get();
 
         let headers: String = self
             .columns
             .iter()
             .enumerate()
             .map(|(i, column)| {
-                format!(
-                    "{:with_padding$}",
-                    column,
-                    with_padding = self.column_sizer.width_for_index(i)
-                )
+                let width = self.column_sizer.width_for_index(i);
+                let column = match sort {
+                    // Keep the indicator in view, shortening the name if needed
+                    Some(sort) if sort.column == i => format!(
+                        "{} {}",
+                        column
+                            .chars()
+                            .take(width.saturating_sub(3))
+                            .collect::<String>(),
+                        sort.indicator()
+                    ),
+                    _ => column.clone(),
+                };
+
+                format!("{:with_padding$}", column, with_padding = width)
             })
             .collect::<Vec<String>>()
             .join("");
@@ -1,25 +1,57 @@
+use std::cell::Cell;
 use std::marker::PhantomData;
 use std::rc::Rc;
 
 use std::sync::Arc;
 
-use cursive::event::{Event, EventResult};
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::theme::Style;
 use cursive::traits::{Resizable, Scrollable};
+use cursive::utils::markup::StyledString;
 use cursive::view::ViewWrapper;
 use cursive::views::{LinearLayout, ResizedView, ScrollView, SelectView};
 use cursive::{Vec2, View};
 
 use crate::tui::table::table_headers::TableHeaders;
+use crate::tui::theme;
 
 use super::column_sizer::ColumnSizer;
+use super::sort::TableSort;
+
+/// Rows the selection moves per scroll wheel step
+const WHEEL_ROWS: usize = 3;
+
 pub trait TableListItem {
     fn as_row(&self) -> Vec<String>;
+
+    /// Stays the same across replaces, so the selection can follow the
+    /// item when sorting or new values move its row
+    fn id(&self) -> &str;
+
+    /// Indices of the chars to highlight in a column, e.g. what a filter matched
+    fn highlights(&self, _column: usize) -> &[usize] {
+        &[]
+    }
+
+    /// Marked for a bulk action
+    fn marked(&self) -> bool {
+        false
+    }
+
+    /// Style of a column's text, e.g. from the row's status. Marks and
+    /// highlights are drawn over it
+    fn style(&self, _column: usize) -> Style {
+        Style::none()
+    }
 }
 
 /// A "table" implemented on top of SelectView<T> where we
-/// divvy up x into columns
+/// divvy up x into columns. `o` sorts by the next column, `O`
+/// reverses the sort; rows keep the order they were given in
+/// when unsorted.
 pub struct TableListView<T> {
     column_sizer: Arc<ColumnSizer>,
+    sort: Rc<Cell<Option<TableSort>>>,
     linear_layout: LinearLayout,
     // LinearLayout swallows T from , but we still need it
     inner: PhantomData<T>,
@@ -36,10 +68,11 @@ impl<T: 'static + TableListItem> TableListView<T> {
             .into_iter()
             .map(|(n, _)| n.as_ref().to_string());
         let column_sizer = ColumnSizer::new(columns);
+        let sort = Rc::new(Cell::new(None));
 
         let mut linear_layout = LinearLayout::vertical();
         linear_layout.add_child(
-            TableHeaders::new(column_names, column_sizer.clone())
+            TableHeaders::new(column_names, column_sizer.clone(), sort.clone())
                 .full_width()
                 .max_height(1),
         );
@@ -52,6 +85,7 @@ impl<T: 'static + TableListItem> TableListView<T> {
         Self {
             linear_layout,
             column_sizer,
+            sort,
             inner: PhantomData::default(),
         }
     }
@@ -60,32 +94,86 @@ impl<T: 'static + TableListItem> TableListView<T> {
     where
         I: IntoIterator<Item = T>,
     {
-        let rows: Vec<(String, T)> = items
+        let mut rows: Vec<(Vec<String>, T)> = items
+            .into_iter()
+            .map(|item: T| (item.as_row(), item))
+            .collect();
+
+        // Stable, so ties keep the order they were given in
+        if let Some(sort) = self.sort.get() {
+            rows.sort_by(|(a, _), (b, _)| sort.compare(a, b));
+        }
+
+        let roles = theme::roles();
+        let highlight = roles.matched;
+        let marked = roles.marked;
+
+        let rows: Vec<(StyledString, T)> = rows
             .into_iter()
-            .map(|item: T| {
-                let presented: Vec<String> = item
-                    .as_row()
-                    .iter()
-                    .take(self.column_sizer.num_columns)
-                    .enumerate()
-                    .map(|(i, field)| {
-                        let wfi = self.column_sizer.width_for_index(i);
-                        let mut truncated = field.clone();
-                        truncated.truncate(wfi - 1);
-                        format!("{:with_padding$}", truncated, with_padding = wfi)
-                    })
-                    .collect();
-
-                (presented.join(""), item)
+            .map(|(row, item)| {
+                let mut presented = StyledString::new();
+
+                for (i, field) in row.iter().take(self.column_sizer.num_columns).enumerate() {
+                    let plain = if item.marked() {
+                        item.style(i).combine(marked)
+                    } else {
+                        item.style(i)
+                    };
+                    let wfi = self.column_sizer.width_for_index(i);
+                    let highlights = item.highlights(i);
+                    // By chars, a byte index can land inside ✔
+                    let truncated: Vec<char> = field.chars().take(wfi - 1).collect();
+
+                    for (j, c) in truncated.iter().enumerate() {
+                        if highlights.contains(&j) {
+                            presented.append_styled(c.to_string(), highlight);
+                        } else {
+                            presented.append_styled(c.to_string(), plain);
+                        }
+                    }
+
+                    presented.append_styled(" ".repeat(wfi - truncated.len()), plain);
+                }
+
+                (presented, item)
             })
             .collect();
 
         let sv = self.get_mut_selectview();
+        let current_id = sv.selection().map(|item| item.id().to_string());
+        // Only if the selected item is gone
         let current_selection = sv.selected_id().unwrap_or(0);
 
+        let selection = current_id
+            .and_then(|id| rows.iter().position(|(_, item)| item.id() == id))
+            .unwrap_or(current_selection);
+
         sv.clear();
         sv.add_all(rows);
-        sv.set_selection(current_selection);
+        sv.set_selection(selection);
+    }
+
+    pub fn sort(&self) -> Option<TableSort> {
+        self.sort.get()
+    }
+
+    /// Takes effect the next time rows are replaced
+    pub fn set_sort(&mut self, sort: Option<TableSort>) {
+        self.sort.set(sort);
+    }
+
+    fn scroll_selection(&mut self, key: Key) -> EventResult {
+        if self.get_selectview().is_empty() {
+            return EventResult::Ignored;
+        }
+
+        self.linear_layout.set_focus_index(1).expect("Must focus");
+
+        for _ in 0..WHEEL_ROWS {
+            self.linear_layout.on_event(Event::Key(key));
+        }
+
+        EventResult::Consumed(None)
     }
 
     pub fn get_highlighted_row(&self) -> Option<Rc<T>> {
@@ -125,6 +213,39 @@ impl<T: 'static + TableListItem> ViewWrapper for TableListView<T> {
     wrap_impl!(self.linear_layout: LinearLayout);
 
     fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        let sort = match event {
+            Event::Char('o') => Some(TableSort::next_column(
+                self.sort(),
+                self.column_sizer.num_columns,
+            )),
+            Event::Char('O') => Some(TableSort::reverse(self.sort())),
+            // Headers are the first line
+            Event::Mouse {
+                offset,
+                position,
+                event: MouseEvent::Press(MouseButton::Left),
+            } => position
+                .checked_sub(offset)
+                .filter(|pos| pos.y == 0)
+                .and_then(|pos| self.column_sizer.column_at(pos.x))
+                .map(|column| TableSort::by_column(self.sort(), column)),
+            // Move the selection rather than the view, so the detail pane follows
+            Event::Mouse {
+                event: MouseEvent::WheelUp,
+                ..
+            } => return self.scroll_selection(Key::Up),
+            Event::Mouse {
+                event: MouseEvent::WheelDown,
+                ..
+            } => return self.scroll_selection(Key::Down),
+            _ => None,
+        };
+
+        if let Some(sort) = sort {
+            self.set_sort(sort);
+            return EventResult::Consumed(None);
+        }
+
         if self.get_selectview().is_empty() {
             EventResult::Consumed(None)
         } else {
@@ -138,3 +259,51 @@ impl<T: 'static + TableListItem> ViewWrapper for TableListView<T> {
         self.linear_layout.layout(size);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{TableListItem, TableListView};
+    use crate::tui::table::sort::TableSort;
+
+    /// Label and PID
+    struct Job(&'static str, &'static str);
+
+    impl TableListItem for Job {
+        fn as_row(&self) -> Vec<String> {
+            vec![self.0.to_string(), self.1.to_string()]
+        }
+
+        fn id(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn selected(table: &TableListView<Job>) -> Option<&'static str> {
+        table.get_highlighted_row().map(|job| job.0)
+    }
+
+    #[test]
+    fn table_selection_follows_item() {
+        let mut table = TableListView::new(vec![("Label", None), ("PID", None)]);
+
+        table.replace_and_preserve_selection(vec![Job("a", "3"), Job("b", "1"), Job("c", "2")]);
+        table.get_mut_selectview().set_selection(2);
+        assert_eq!(selected(&table), Some("c"));
+
+        // Sorted by PID, c moves up a row
+        table.set_sort(Some(TableSort {
+            column: 1,
+            ascending: true,
+        }));
+        table.replace_and_preserve_selection(vec![Job("a", "3"), Job("b", "1"), Job("c", "2")]);
+        assert_eq!(selected(&table), Some("c"));
+
+        // A new PID moves it to the bottom
+        table.replace_and_preserve_selection(vec![Job("a", "3"), Job("b", "1"), Job("c", "9")]);
+        assert_eq!(selected(&table), Some("c"));
+
+        // Gone, so the row index is kept
+        table.replace_and_preserve_selection(vec![Job("a", "3"), Job("b", "1"), Job("d", "4")]);
+        assert_eq!(selected(&table), Some("d"));
+    }
+}
@@ -0,0 +1,302 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use cursive::theme::{load_toml, BaseColor, Color, Effect, Style};
+use cursive::Cursive;
+
+use crate::config::Config;
+use crate::launchd::search_dirs::expand_home;
+
+/// Applied when the config doesn't name one
+pub static DEFAULT_THEME: &str = "dark";
+
+/// Applied instead of the default when $NO_COLOR is set
+pub static NO_COLOR_THEME: &str = "no-color";
+
+/// Built-in themes: a cursive theme plus a `[roles]` table
+pub static THEMES: [(&str, &str); 4] = [
+    ("dark", include_str!("themes/dark.toml")),
+    ("light", include_str!("themes/light.toml")),
+    ("high-contrast", include_str!("themes/high_contrast.toml")),
+    ("no-color", include_str!("themes/no_color.toml")),
+];
+
+lazy_static! {
+    /// Name and roles of the applied theme, read by views as they draw
+    static ref ACTIVE: RwLock<(String, Roles)> =
+        RwLock::new((DEFAULT_THEME.to_string(), Roles::default()));
+}
+
+/// What launchk's own views draw with, instead of colours of their own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Roles {
+    /// Hints, placeholders and toggles that are off
+    pub subtle: Style,
+    /// The omnibox prompt while it is in use
+    pub accent: Style,
+    /// Job type toggles that are on
+    pub active: Style,
+    pub error: Style,
+    /// Table column headers
+    pub header: Style,
+    /// System info labels
+    pub label: Style,
+    /// Characters a filter or search matched
+    pub matched: Style,
+    /// Rows marked for bulk actions
+    pub marked: Style,
+    /// Service list rows, by their job's status
+    pub running: Style,
+    /// Loaded, but not running
+    pub idle: Style,
+    /// Last exited non-zero
+    pub failed: Style,
+    pub disabled: Style,
+    /// Has health problems
+    pub unhealthy: Style,
+    pub diff_file: Style,
+    pub diff_add: Style,
+    pub diff_remove: Style,
+    pub diff_hunk: Style,
+}
+
+impl Default for Roles {
+    fn default() -> Self {
+        let light = |c| Style::from(Color::Light(c));
+        let dark = |c| Style::from(Color::Dark(c));
+
+        Self {
+            subtle: light(BaseColor::Black),
+            accent: light(BaseColor::Blue),
+            active: light(BaseColor::Blue).combine(Effect::Bold),
+            error: light(BaseColor::Red),
+            header: dark(BaseColor::Blue).combine(Effect::Bold),
+            label: light(BaseColor::White).combine(Effect::Bold),
+            matched: light(BaseColor::Yellow).combine(Effect::Bold),
+            marked: light(BaseColor::Cyan).combine(Effect::Bold),
+            running: dark(BaseColor::Green),
+            idle: dark(BaseColor::White),
+            failed: light(BaseColor::Red),
+            disabled: light(BaseColor::Black),
+            unhealthy: dark(BaseColor::Yellow),
+            diff_file: Style::from(Effect::Bold),
+            diff_add: dark(BaseColor::Green),
+            diff_remove: dark(BaseColor::Red),
+            diff_hunk: dark(BaseColor::Cyan),
+        }
+    }
+}
+
+impl Roles {
+    fn role_mut(&mut self, name: &str) -> Option<&mut Style> {
+        Some(match name {
+            "subtle" => &mut self.subtle,
+            "accent" => &mut self.accent,
+            "active" => &mut self.active,
+            "error" => &mut self.error,
+            "header" => &mut self.header,
+            "label" => &mut self.label,
+            "match" => &mut self.matched,
+            "marked" => &mut self.marked,
+            "running" => &mut self.running,
+            "idle" => &mut self.idle,
+            "failed" => &mut self.failed,
+            "disabled" => &mut self.disabled,
+            "unhealthy" => &mut self.unhealthy,
+            "diff_file" => &mut self.diff_file,
+            "diff_add" => &mut self.diff_add,
+            "diff_remove" => &mut self.diff_remove,
+            "diff_hunk" => &mut self.diff_hunk,
+            _ => return None,
+        })
+    }
+
+    /// The `[roles]` table of a theme file, defaults for any it leaves out
+    fn from_toml(text: &str) -> Result<Self, String> {
+        let value: toml::Value = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut roles = Self::default();
+
+        let table = match value.get("roles") {
+            None => return Ok(roles),
+            Some(toml::Value::Table(table)) => table,
+            Some(_) => return Err("roles must be a table".to_string()),
+        };
+
+        for (name, value) in table.iter() {
+            let style = value
+                .as_str()
+                .ok_or_else(|| format!("roles.{} must be a string", name))
+                .and_then(|s| parse_style(s).map_err(|e| format!("roles.{}: {}", name, e)))?;
+
+            *roles
+                .role_mut(name)
+                .ok_or_else(|| format!("Unknown role {}", name))? = style;
+        }
+
+        Ok(roles)
+    }
+}
+
+/// A colour and any of bold, italic, underline, reverse, e.g. "light blue bold"
+fn parse_style(value: &str) -> Result<Style, String> {
+    let mut style = Style::none();
+    let mut color = vec![];
+
+    for word in value.split_whitespace() {
+        match word {
+            "bold" => style = style.combine(Effect::Bold),
+            "italic" => style = style.combine(Effect::Italic),
+            "underline" => style = style.combine(Effect::Underline),
+            "reverse" => style = style.combine(Effect::Reverse),
+            word => color.push(word),
+        }
+    }
+
+    if color.is_empty() {
+        return Ok(style);
+    }
+
+    let color = color.join(" ");
+
+    Color::parse(&color)
+        .map(|c| style.combine(c))
+        .ok_or_else(|| format!("Unknown colour {}", color))
+}
+
+/// Theme files (name.toml) next to the config file
+pub fn themes_dir() -> PathBuf {
+    Config::path()
+        .parent()
+        .map(|p| p.join("themes"))
+        .unwrap_or_default()
+}
+
+/// A name from the themes dir, or a path (with ~ expanded)
+fn theme_file(name: &str) -> PathBuf {
+    if !name.contains('/') && !name.ends_with(".toml") {
+        return themes_dir().join(format!("{}.toml", name));
+    }
+
+    PathBuf::from(expand_home(name, &env::var("HOME").unwrap_or_default()))
+}
+
+/// Built-in themes, then those in the themes dir
+pub fn available() -> Vec<String> {
+    let mut user: Vec<String> = fs::read_dir(themes_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map(|e| e == "toml").unwrap_or(false))
+                .filter_map(|p| Some(p.file_stem()?.to_string_lossy().to_string()))
+                .filter(|name| THEMES.iter().all(|(builtin, _)| name.as_str() != *builtin))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    user.sort();
+
+    THEMES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .chain(user)
+        .collect()
+}
+
+/// The name of the applied theme
+pub fn current() -> String {
+    ACTIVE.read().expect("Must read theme").0.clone()
+}
+
+/// Styles of the applied theme
+pub fn roles() -> Roles {
+    ACTIVE.read().expect("Must read theme").1
+}
+
+/// A cursive theme and roles, ready to apply
+pub struct Theme {
+    name: String,
+    palette: cursive::theme::Theme,
+    roles: Roles,
+}
+
+impl Theme {
+    /// A built-in theme, one in the themes dir or a path to a theme file
+    pub fn load(name: &str) -> Result<Self, String> {
+        match THEMES.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, text)) => Self::from_toml(name, text),
+            None => {
+                let path = theme_file(name);
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("Cannot read theme {}: {}", path.display(), e))?;
+
+                Self::from_toml(name, &text)
+            }
+        }
+    }
+
+    fn from_toml(name: &str, text: &str) -> Result<Self, String> {
+        let palette = load_toml(text).map_err(|e| format!("Invalid theme {}: {:?}", name, e))?;
+        let roles = Roles::from_toml(text).map_err(|e| format!("Invalid theme {}: {}", name, e))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            palette,
+            roles,
+        })
+    }
+
+    /// Views pick up the roles the next time they draw
+    pub fn apply(self, siv: &mut Cursive) {
+        siv.set_theme(self.palette);
+        *ACTIVE.write().expect("Must write theme") = (self.name, self.roles);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cursive::theme::{BaseColor, Color, Effect, PaletteColor, Style};
+
+    use super::{parse_style, Roles, Theme, NO_COLOR_THEME, THEMES};
+
+    #[test]
+    fn theme_parse_style() {
+        assert_eq!(
+            parse_style("light blue bold"),
+            Ok(Style::from(Color::Light(BaseColor::Blue)).combine(Effect::Bold))
+        );
+        assert_eq!(parse_style("underline"), Ok(Style::from(Effect::Underline)));
+        assert!(parse_style("blurple").is_err());
+    }
+
+    #[test]
+    fn theme_builtins() {
+        for (name, _) in THEMES.iter() {
+            assert!(Theme::load(name).is_ok(), "{} must load", name);
+        }
+
+        // dark.toml spells out the defaults
+        assert_eq!(Theme::load("dark").unwrap().roles, Roles::default());
+
+        // no-color leaves every colour to the terminal
+        let no_color = Theme::load(NO_COLOR_THEME).unwrap();
+        assert_eq!(
+            no_color.palette.palette[PaletteColor::View],
+            Color::TerminalDefault
+        );
+        assert_eq!(no_color.roles.failed, Style::from(Effect::Reverse));
+        assert_eq!(no_color.roles.idle, Style::none());
+    }
+
+    #[test]
+    fn theme_roles() {
+        let roles = Roles::from_toml("[roles]\nerror = \"magenta\"").unwrap();
+
+        assert_eq!(roles.error, Style::from(Color::Dark(BaseColor::Magenta)));
+        assert_eq!(roles.subtle, Roles::default().subtle);
+        assert!(Roles::from_toml("[roles]\nsparkle = \"red\"").is_err());
+        assert!(Roles::from_toml("[roles]\nerror = \"red glitter\"").is_err());
+    }
+}